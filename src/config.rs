@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Env vars `Config::apply` set from the file, as opposed to ones the user exported
+static APPLIED: OnceLock<Vec<&'static str>> = OnceLock::new();
+
+/// Whether `name` got its value from the config file rather than the environment
+pub fn set_by_config(name: &str) -> bool {
+    APPLIED.get().is_some_and(|applied| applied.contains(&name))
+}
+
+/// Defaults read from `config.toml`. Every field is optional; env vars and CLI
+/// flags take precedence over anything set here.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// LLM model (GHOST_MODEL)
+    pub model: Option<String>,
+    /// Context budget in tokens (GHOST_BUDGET)
+    pub budget: Option<usize>,
+    /// Max characters per chunk (GHOST_CHUNK_SIZE)
+    pub chunk_size: Option<usize>,
+    /// Characters neighbouring chunks may share (GHOST_CHUNK_OVERLAP)
+    pub chunk_overlap: Option<usize>,
+    /// Embedding model (GHOST_EMBED_MODEL)
+    pub embed_model: Option<String>,
+    /// Answering backend, `ollama` or `openai` (GHOST_BACKEND)
+    pub backend: Option<String>,
+    /// Ollama host, e.g. `http://localhost` (GHOST_OLLAMA_HOST)
+    pub ollama_host: Option<String>,
+    /// Ollama port (GHOST_OLLAMA_PORT)
+    pub ollama_port: Option<u16>,
+    /// Base URL for the OpenAI-compatible backend (GHOST_OPENAI_BASE_URL)
+    pub openai_base_url: Option<String>,
+    /// Vector store location (GHOST_DATA_DIR)
+    pub data_dir: Option<PathBuf>,
+}
+
+/// Where the config file is looked up: GHOST_CONFIG, else
+/// `$XDG_CONFIG_HOME/ghost-lib/config.toml`, else `~/.config/ghost-lib/config.toml`
+pub fn config_path() -> PathBuf {
+    if let Some(path) = std::env::var_os("GHOST_CONFIG").filter(|v| !v.is_empty()) {
+        return PathBuf::from(path);
+    }
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from(".config"));
+    base.join("ghost-lib").join("config.toml")
+}
+
+impl Config {
+    /// Read the config file; a missing file is the same as an empty one
+    pub fn load() -> Result<Self> {
+        let path = config_path();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Self::parse(&text).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// Env var / value pairs for every field that is set
+    fn vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars = Vec::new();
+        let mut push = |name, value: Option<String>| {
+            if let Some(value) = value {
+                vars.push((name, value));
+            }
+        };
+        push("GHOST_MODEL", self.model.clone());
+        push("GHOST_BUDGET", self.budget.map(|v| v.to_string()));
+        push("GHOST_CHUNK_SIZE", self.chunk_size.map(|v| v.to_string()));
+        push(
+            "GHOST_CHUNK_OVERLAP",
+            self.chunk_overlap.map(|v| v.to_string()),
+        );
+        push("GHOST_EMBED_MODEL", self.embed_model.clone());
+        push("GHOST_BACKEND", self.backend.clone());
+        push("GHOST_OLLAMA_HOST", self.ollama_host.clone());
+        push("GHOST_OLLAMA_PORT", self.ollama_port.map(|v| v.to_string()));
+        push("GHOST_OPENAI_BASE_URL", self.openai_base_url.clone());
+        push(
+            "GHOST_DATA_DIR",
+            self.data_dir.as_ref().map(|d| d.display().to_string()),
+        );
+        vars
+    }
+
+    /// Export the file's values as GHOST_* env vars, leaving any already set
+    /// alone. Must run at startup, before other threads read the environment.
+    pub fn apply(&self) {
+        let mut applied = Vec::new();
+        for (name, value) in self.vars() {
+            if std::env::var_os(name).is_none() {
+                std::env::set_var(name, value);
+                applied.push(name);
+            }
+        }
+        let _ = APPLIED.set(applied);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+model = "mistral"
+budget = 4000
+chunk_size = 1200
+ollama_host = "http://gpu-box"
+ollama_port = 11500
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.model.as_deref(), Some("mistral"));
+        assert_eq!(config.budget, Some(4000));
+        assert_eq!(config.embed_model, None);
+        assert_eq!(
+            config.vars(),
+            vec![
+                ("GHOST_MODEL", "mistral".to_string()),
+                ("GHOST_BUDGET", "4000".to_string()),
+                ("GHOST_CHUNK_SIZE", "1200".to_string()),
+                ("GHOST_OLLAMA_HOST", "http://gpu-box".to_string()),
+                ("GHOST_OLLAMA_PORT", "11500".to_string()),
+            ]
+        );
+
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+        // Typos are reported instead of silently ignored
+        assert!(Config::parse("modle = \"mistral\"").is_err());
+    }
+}
@@ -1,11 +1,14 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use fastembed::TextEmbedding;
-use std::sync::Arc;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex as StdMutex, OnceLock};
 use tokio::sync::Mutex;
 
-use crate::core::ingest;
+use crate::core::{ingest, provider};
 use crate::db::{self, VectorStore};
-use crate::utils::text_cleaner;
+use crate::utils::{cjk, text_cleaner};
 
 /// Result of the distillation process
 pub struct DistillResult {
@@ -15,142 +18,1110 @@ pub struct DistillResult {
     pub compression_ratio: f64,
     pub chunks_retrieved: usize,
     pub chunks_after_dedup: usize,
+    /// Chunks dropped for scoring below GHOST_MIN_SCORE
+    pub chunks_filtered: usize,
+    /// Where each packed chunk came from, in context order
+    pub sources: Vec<SourceRef>,
+    /// Packed chunk bodies without their labels, aligned with `sources`
+    pub passages: Vec<String>,
+    /// Repeated sentences dropped from the packed context (GHOST_SENTENCE_DEDUP)
+    pub sentences_removed: usize,
+    /// Best hybrid score among the retrieved chunks (0.0 when nothing was retrieved).
+    /// In keyword-only mode scores are relative to the best match, so this is 1.0.
+    pub top_score: f64,
+    /// Chunks were ranked by keywords alone because no embedder was available
+    pub keyword_only: bool,
+    /// Rephrasings of the query that were searched as well (GHOST_QUERY_EXPANSION)
+    pub expansions: Vec<String>,
+    /// Retrieved chunks that only the rephrasings found
+    pub expansion_chunks: usize,
+    /// Optional steps that failed and were skipped, for the caller to show
+    pub warnings: Vec<String>,
 }
 
-/// Context budget in estimated tokens
+impl DistillResult {
+    /// Pipeline statistics as reported by `ask --json` and the HTTP API
+    pub fn stats_json(&self) -> Value {
+        json!({
+            "chunks_retrieved": self.chunks_retrieved,
+            "chunks_after_dedup": self.chunks_after_dedup,
+            "chunks_filtered": self.chunks_filtered,
+            "original_tokens": self.original_tokens,
+            "distilled_tokens": self.distilled_tokens,
+            "compression_ratio": self.compression_ratio,
+            "sentences_removed": self.sentences_removed,
+            "top_score": self.top_score,
+            "low_relevance": self.low_relevance(),
+            "keyword_only": self.keyword_only,
+            "expansions": self.expansions,
+            "expansion_chunks": self.expansion_chunks,
+            "warnings": self.warnings,
+        })
+    }
+
+    /// The context with every passage numbered after its source (`[1] notes.md — Intro`),
+    /// so the model can cite passages by index
+    pub fn numbered_context(&self) -> String {
+        self.passages
+            .iter()
+            .zip(&self.sources)
+            .enumerate()
+            .map(|(i, (body, source))| format!("[{}] {}\n{body}", i + 1, source.citation()))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Whether even the best match scored below GHOST_LOW_RELEVANCE, so the
+    /// answer likely rests on thin context. Never set in keyword-only mode,
+    /// whose relative scores say nothing about how well the best match fits.
+    pub fn low_relevance(&self) -> bool {
+        !self.keyword_only && self.top_score < low_relevance_threshold()
+    }
+}
+
+/// Milestones `distill` reports while it runs, so callers can show progress
+#[derive(Debug, Clone, Copy)]
+pub enum DistillEvent {
+    /// Query expansion done; the `variants` rephrasings surfaced `extra` more chunks
+    Expanded { variants: usize, extra: usize },
+    /// Hybrid search done; `filtered` of the `chunks` scored below GHOST_MIN_SCORE
+    Retrieved { chunks: usize, filtered: usize },
+    /// Redundancy removal done, leaving `kept` chunks
+    Deduped { kept: usize },
+    /// `chunks` chunks packed into the budget
+    Packed { chunks: usize },
+}
+
+/// Callback receiving `DistillEvent`s (`Sync` so the distill future stays `Send`)
+pub type DistillProgress<'a> = &'a (dyn Fn(DistillEvent) + Sync);
+
+/// Provenance of one chunk packed into the context
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourceRef {
+    /// Set when the query spanned several collections
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    pub filename: String,
+    pub section: String,
+    pub page: Option<u64>,
+    pub score: f64,
+}
+
+impl SourceRef {
+    /// `collection: filename p.N — section`, as shown in source and reference lists
+    pub fn citation(&self) -> String {
+        let collection = self
+            .collection
+            .as_ref()
+            .map(|c| format!("{c}: "))
+            .unwrap_or_default();
+        let page = self.page.map(|p| format!(" p.{p}")).unwrap_or_default();
+        format!("{collection}{}{page} — {}", self.filename, self.section)
+    }
+}
+
+/// Context budget (GHOST_BUDGET), counted in the chosen `BudgetUnit`
 const DEFAULT_CONTEXT_BUDGET: usize = 3000;
 
+/// What the context budget counts (`ask --budget-unit`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BudgetUnit {
+    /// Estimated tokens: the tokenizer's count, or words × 1.3 without one (default)
+    #[default]
+    Tokens,
+    /// Characters, for a predictable cap independent of the token estimate
+    Chars,
+}
+
+impl BudgetUnit {
+    /// Length of `text` in this unit
+    fn measure(self, text: &str) -> usize {
+        match self {
+            BudgetUnit::Tokens => text_cleaner::estimate_tokens(text),
+            BudgetUnit::Chars => text.chars().count(),
+        }
+    }
+
+    /// Cut `text` to at most `max` of this unit, at a word boundary
+    fn truncate(self, text: &str, max: usize) -> String {
+        match self {
+            BudgetUnit::Tokens => truncate_to_tokens(text, max),
+            BudgetUnit::Chars => truncate_to_chars(text, max),
+        }
+    }
+
+    /// Words that roughly fill `amount` of this unit, for summary length requests
+    fn words(self, amount: usize) -> usize {
+        match self {
+            BudgetUnit::Tokens => (amount as f64 / 1.3) as usize,
+            BudgetUnit::Chars => amount / AVG_WORD_CHARS,
+        }
+    }
+
+    /// Budget left below which an overflowing chunk is dropped rather than shrunk
+    fn min_shrink(self) -> usize {
+        match self {
+            BudgetUnit::Tokens => MIN_SHRINK_TOKENS,
+            BudgetUnit::Chars => MIN_SHRINK_TOKENS * AVG_WORD_CHARS,
+        }
+    }
+}
+
+/// Rough characters per word, counting the following space
+const AVG_WORD_CHARS: usize = 6;
+
 /// Similarity threshold for deduplication
-const DEDUP_THRESHOLD: f32 = 0.85;
+const DEFAULT_DEDUP_THRESHOLD: f32 = 0.85;
 
 /// Top-K results from vector search
-const TOP_K: u64 = 20;
+pub const DEFAULT_TOP_K: u64 = 20;
+
+/// Distinct query terms a chunk must contain before its keyword score counts
+const DEFAULT_MIN_TERM_MATCHES: usize = 1;
+
+/// Hybrid score a chunk needs to be considered at all (GHOST_MIN_SCORE)
+fn min_score() -> f64 {
+    std::env::var("GHOST_MIN_SCORE")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(0.0)
+}
+
+/// Top hybrid score below which answers are flagged as low relevance (GHOST_LOW_RELEVANCE)
+const DEFAULT_LOW_RELEVANCE: f64 = 0.6;
+
+fn low_relevance_threshold() -> f64 {
+    std::env::var("GHOST_LOW_RELEVANCE")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(DEFAULT_LOW_RELEVANCE)
+}
+
+/// Default (vector, keyword) weights for hybrid scoring
+const DEFAULT_WEIGHTS: (f64, f64) = (0.7, 0.3);
+
+/// Hybrid weights from GHOST_VECTOR_WEIGHT / GHOST_KEYWORD_WEIGHT, normalized to sum to 1.0
+fn hybrid_weights() -> (f64, f64) {
+    let read = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<f64>().ok());
+    normalize_weights(read("GHOST_VECTOR_WEIGHT"), read("GHOST_KEYWORD_WEIGHT"))
+}
+
+/// Fill in a missing weight as the complement of the other, then normalize
+fn normalize_weights(vector: Option<f64>, keyword: Option<f64>) -> (f64, f64) {
+    let (v, k) = match (vector, keyword) {
+        (Some(v), Some(k)) => (v, k),
+        (Some(v), None) => (v, 1.0 - v),
+        (None, Some(k)) => (1.0 - k, k),
+        (None, None) => return DEFAULT_WEIGHTS,
+    };
+    let (v, k) = (v.max(0.0), k.max(0.0));
+    let sum = v + k;
+    if sum <= 0.0 || !sum.is_finite() {
+        return DEFAULT_WEIGHTS;
+    }
+    (v / sum, k / sum)
+}
+
+/// Age at which a chunk's recency boost has halved (GHOST_RECENCY_HALF_LIFE_DAYS)
+const DEFAULT_RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+fn recency_half_life_days() -> f64 {
+    std::env::var("GHOST_RECENCY_HALF_LIFE_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|v| *v > 0.0)
+        .unwrap_or(DEFAULT_RECENCY_HALF_LIFE_DAYS)
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Score multiplier for a chunk `age_days` old: 2.0 when brand new, decaying
+/// towards 1.0 (no boost) with the given half-life
+fn recency_multiplier(age_days: f64, half_life_days: f64) -> f64 {
+    1.0 + 0.5f64.powf(age_days.max(0.0) / half_life_days)
+}
+
+/// Boost chunks from recently modified files and re-sort. Chunks without a
+/// recorded modification time keep their score.
+fn apply_recency_boost(chunks: &mut [ScoredChunk], now: u64, half_life_days: f64) {
+    for chunk in chunks.iter_mut() {
+        if let Some(mtime) = chunk.mtime {
+            let age_days = now.saturating_sub(mtime) as f64 / 86_400.0;
+            chunk.score *= recency_multiplier(age_days, half_life_days);
+        }
+    }
+    chunks.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+}
+
+/// Default MMR trade-off between relevance (1.0) and diversity (0.0)
+const DEFAULT_MMR_LAMBDA: f64 = 0.5;
+
+/// How chunks are shrunk to fit the context budget (GHOST_COMPRESS)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Compress {
+    /// Strip stopwords and filler phrases, truncating the chunk that overflows (default)
+    Stopwords,
+    /// Keep chunks verbatim and have the LLM summarize any chunk that overflows
+    Summarize,
+}
+
+fn compress_mode() -> Compress {
+    match std::env::var("GHOST_COMPRESS").as_deref() {
+        Ok("summarize") => Compress::Summarize,
+        _ => Compress::Stopwords,
+    }
+}
+
+/// How redundant chunks are handled before packing (GHOST_RERANK)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rerank {
+    /// Drop chunks too similar to a higher-scored one (default)
+    Dedup,
+    /// Reorder by Maximal Marginal Relevance with the given lambda
+    Mmr(f64),
+}
+
+fn rerank_mode() -> Rerank {
+    match std::env::var("GHOST_RERANK").as_deref() {
+        Ok("mmr") => {
+            let lambda = std::env::var("GHOST_MMR_LAMBDA")
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(DEFAULT_MMR_LAMBDA)
+                .clamp(0.0, 1.0);
+            Rerank::Mmr(lambda)
+        }
+        _ => Rerank::Dedup,
+    }
+}
 
-/// Perform context distillation: hybrid search → dedup → compress → pack
+/// How compressed chunks are fitted into the context budget (GHOST_PACK)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pack {
+    /// Take chunks best-first until the budget runs out (default)
+    Greedy,
+    /// Share the budget across distinct sections so one long section can't take it all
+    Balanced,
+}
+
+fn pack_mode() -> Pack {
+    match std::env::var("GHOST_PACK").as_deref() {
+        Ok("balanced") => Pack::Balanced,
+        _ => Pack::Greedy,
+    }
+}
+
+/// Budget left below which an overflowing chunk is dropped rather than shrunk
+const MIN_SHRINK_TOKENS: usize = 50;
+
+/// Sentence-level redundancy removal after packing (GHOST_SENTENCE_DEDUP)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SentenceDedup {
+    /// Leave packed chunks as they are (default)
+    Off,
+    /// Drop sentences whose normalized text already appeared
+    Hash,
+    /// Drop sentences whose embedding is near one already kept (costs embedding calls)
+    Embed,
+}
+
+fn sentence_dedup_mode() -> SentenceDedup {
+    match std::env::var("GHOST_SENTENCE_DEDUP").as_deref() {
+        Ok("1" | "on" | "hash") => SentenceDedup::Hash,
+        Ok("embed") => SentenceDedup::Embed,
+        _ => SentenceDedup::Off,
+    }
+}
+
+/// Sentences shorter than this (normalized chars) are never treated as repeats
+const MIN_DEDUP_SENTENCE_CHARS: usize = 20;
+
+/// Cosine similarity above which two sentences count as the same in `embed` mode
+const SENTENCE_SIMILARITY_THRESHOLD: f32 = 0.95;
+
+fn min_term_matches() -> usize {
+    std::env::var("GHOST_MIN_TERM_MATCHES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MIN_TERM_MATCHES)
+}
+
+/// Cosine similarity above which a chunk counts as a duplicate of a better one:
+/// the explicit value, else GHOST_DEDUP_THRESHOLD, else 0.85. Lower values drop more chunks.
+pub fn dedup_threshold(explicit: Option<f32>) -> Result<f32> {
+    let (threshold, source) = match explicit {
+        Some(t) => (t, "dedup threshold"),
+        None => match std::env::var("GHOST_DEDUP_THRESHOLD") {
+            Ok(v) => (
+                v.trim()
+                    .parse()
+                    .with_context(|| format!("GHOST_DEDUP_THRESHOLD={v} is not a number"))?,
+                "GHOST_DEDUP_THRESHOLD",
+            ),
+            Err(_) => return Ok(DEFAULT_DEDUP_THRESHOLD),
+        },
+    };
+    if !(0.0..=1.0).contains(&threshold) {
+        bail!("{source} must be between 0.0 and 1.0, got {threshold}");
+    }
+    Ok(threshold)
+}
+
+/// Most rephrasings query expansion asks the model for
+const MAX_QUERY_EXPANSIONS: usize = 3;
+
+/// Whether GHOST_QUERY_EXPANSION asks for the query to be rephrased by the model
+/// and each rephrasing searched as well
+pub fn query_expansion_enabled() -> bool {
+    std::env::var("GHOST_QUERY_EXPANSION").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Whether GHOST_KEYWORD_ONLY asks for retrieval without the embedding model
+pub fn keyword_only_forced() -> bool {
+    std::env::var("GHOST_KEYWORD_ONLY").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Perform context distillation: hybrid search → dedup → compress → pack.
+///
+/// Without an `embedder` (it failed to load, or GHOST_KEYWORD_ONLY is set) chunks
+/// are ranked by BM25 alone and embedding-based redundancy removal is skipped.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(level = "debug", skip_all, fields(query = %query))]
 pub async fn distill(
     query: &str,
-    embedder: &Arc<Mutex<TextEmbedding>>,
-    store: &VectorStore,
+    embedder: Option<&Arc<Mutex<TextEmbedding>>>,
+    stores: &[&VectorStore],
     context_budget: Option<usize>,
+    budget_unit: BudgetUnit,
+    top_k: Option<u64>,
+    dedup: Option<f32>,
+    filter: Option<&db::Filter>,
+    recency_boost: bool,
+    model: Option<&str>,
+    progress: Option<DistillProgress<'_>>,
 ) -> Result<DistillResult> {
-    let budget = context_budget.unwrap_or(DEFAULT_CONTEXT_BUDGET);
+    let started = std::time::Instant::now();
+    let report = |event| {
+        tracing::debug!(
+            ?event,
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "distill stage"
+        );
+        if let Some(progress) = progress {
+            progress(event);
+        }
+    };
+    let budget = context_budget
+        .or_else(|| {
+            std::env::var("GHOST_BUDGET")
+                .ok()
+                .and_then(|v| v.parse().ok())
+        })
+        .unwrap_or(DEFAULT_CONTEXT_BUDGET);
+    let dedup = dedup_threshold(dedup)?;
+    let top_k = top_k.unwrap_or(DEFAULT_TOP_K);
+    tracing::debug!(budget, ?budget_unit, top_k, dedup, "distill settings");
+    let mut warnings = Vec::new();
 
-    // 1. Generate query embedding
-    let query_embedding = ingest::embed_texts(embedder, vec![query.to_string()]).await?;
-    let query_vec = query_embedding.into_iter().next().unwrap();
+    // 0. Optional query expansion; best effort, so a slow or missing model only costs recall
+    let expansions = if embedder.is_some() && query_expansion_enabled() {
+        match provider::expand_query(query, MAX_QUERY_EXPANSIONS, model).await {
+            Ok(expansions) if expansions.is_empty() => {
+                warnings.push(
+                    "GHOST_QUERY_EXPANSION: the model suggested no rephrasings; \
+                     searched with the original query only"
+                        .to_string(),
+                );
+                expansions
+            }
+            Ok(expansions) => expansions,
+            Err(e) => {
+                warnings.push(format!(
+                    "GHOST_QUERY_EXPANSION failed ({e:#}); searched with the original query only"
+                ));
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
 
-    // 2. Vector similarity search
-    let search_results = db::search_vectors(store, query_vec.clone(), TOP_K).await?;
+    // 1-3. Embed, vector search, hybrid scoring
+    let (mut scored_chunks, expansion_chunks) = match embedder {
+        Some(embedder) => {
+            retrieve_expanded(query, &expansions, embedder, stores, top_k, filter).await?
+        }
+        None => (retrieve_keywords(query, stores, top_k, filter).await?, 0),
+    };
+    if !expansions.is_empty() {
+        tracing::debug!(?expansions, extra = expansion_chunks, "query expanded");
+        report(DistillEvent::Expanded {
+            variants: expansions.len(),
+            extra: expansion_chunks,
+        });
+    }
+    let chunks_retrieved = scored_chunks.len();
+    // Chunks come back sorted by score, best first
+    let top_score = scored_chunks.first().map_or(0.0, |c| c.score);
 
-    if search_results.is_empty() {
+    // Drop weak matches so narrow questions don't get padded with noise. Keyword-only
+    // scores are relative to the best match, so the absolute cut doesn't apply to them.
+    if embedder.is_some() {
+        let threshold = min_score();
+        scored_chunks.retain(|c| c.score >= threshold);
+    }
+    let chunks_filtered = chunks_retrieved - scored_chunks.len();
+    // After the relevance cut-offs, so a boost can reorder matches but not rescue weak ones
+    if recency_boost {
+        apply_recency_boost(&mut scored_chunks, unix_now(), recency_half_life_days());
+    }
+    report(DistillEvent::Retrieved {
+        chunks: chunks_retrieved,
+        filtered: chunks_filtered,
+    });
+
+    if scored_chunks.is_empty() {
         return Ok(DistillResult {
             context: String::new(),
+            passages: Vec::new(),
             original_tokens: 0,
             distilled_tokens: 0,
             compression_ratio: 0.0,
-            chunks_retrieved: 0,
+            chunks_retrieved,
             chunks_after_dedup: 0,
+            chunks_filtered,
+            sources: Vec::new(),
+            sentences_removed: 0,
+            top_score,
+            keyword_only: embedder.is_none(),
+            expansions,
+            expansion_chunks,
+            warnings,
         });
     }
 
-    // 3. Hybrid scoring: vector similarity (70%) + keyword TF-IDF (30%)
-    let query_terms = extract_terms(query);
-    let mut scored_chunks: Vec<ScoredChunk> = Vec::new();
-
-    for (vector_score, payload) in &search_results {
-        let text = payload
-            .get("text")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
-        let section = payload
-            .get("section")
-            .and_then(|v| v.as_str())
-            .unwrap_or("(unknown)")
-            .to_string();
-        let filename = payload
-            .get("filename")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
-
-        let keyword_score = compute_tfidf_score(&text, &query_terms);
-        let hybrid_score = vector_score * 0.7 + keyword_score * 0.3;
-
-        scored_chunks.push(ScoredChunk {
-            text,
-            section,
-            filename,
-            score: hybrid_score,
-        });
-    }
-
-    // Sort by hybrid score (descending)
-    scored_chunks.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-
-    let chunks_retrieved = scored_chunks.len();
-
     // 4. Redundancy removal: compute pairwise cosine similarity on embeddings
-    let chunk_texts: Vec<String> = scored_chunks.iter().map(|c| c.text.clone()).collect();
-    let chunk_embeddings = ingest::embed_texts(embedder, chunk_texts).await?;
-
-    let deduped = remove_redundant(&scored_chunks, &chunk_embeddings, DEDUP_THRESHOLD);
+    let deduped = match embedder {
+        Some(embedder) => {
+            let chunk_texts: Vec<String> = scored_chunks.iter().map(|c| c.text.clone()).collect();
+            let chunk_embeddings = ingest::embed_texts(embedder, chunk_texts).await?;
+            match rerank_mode() {
+                Rerank::Dedup => remove_redundant(&scored_chunks, &chunk_embeddings, dedup),
+                Rerank::Mmr(lambda) => mmr_rerank(&scored_chunks, &chunk_embeddings, lambda),
+            }
+        }
+        // Without embeddings only verbatim repeats can be spotted
+        None => {
+            let mut seen = HashSet::new();
+            scored_chunks
+                .iter()
+                .filter(|c| seen.insert(c.text.as_str()))
+                .collect()
+        }
+    };
     let chunks_after_dedup = deduped.len();
+    report(DistillEvent::Deduped {
+        kept: chunks_after_dedup,
+    });
 
     // 5. Compress text and pack into context budget
-    let mut original_tokens = 0;
-    let mut packed_chunks: Vec<String> = Vec::new();
-    let mut current_tokens = 0;
-
-    for chunk in &deduped {
-        let orig_tokens = text_cleaner::estimate_tokens(&chunk.text);
-        original_tokens += orig_tokens;
-
-        let compressed = text_cleaner::compress_text(&chunk.text);
-        let comp_tokens = text_cleaner::estimate_tokens(&compressed);
-
-        if current_tokens + comp_tokens > budget {
-            // Try to fit a truncated version
-            let remaining = budget.saturating_sub(current_tokens);
-            if remaining > 50 {
-                let truncated = truncate_to_tokens(&compressed, remaining);
-                packed_chunks.push(format!("[{}] {}", chunk.section, truncated));
+    let mut shrinker = Shrinker::new(compress_mode(), provider::backend(), model);
+    let Packed {
+        chunks: mut packed_chunks,
+        mut sources,
+        original_tokens,
+    } = match pack_mode() {
+        Pack::Greedy => pack_greedy(&deduped, budget, budget_unit, &mut shrinker).await,
+        Pack::Balanced => pack_balanced(&deduped, budget, budget_unit, &mut shrinker).await,
+    };
+    warnings.extend(shrinker.warning);
+    report(DistillEvent::Packed {
+        chunks: packed_chunks.len(),
+    });
+
+    // 6. Optionally drop sentences repeated across the packed chunks
+    let mut sentences_removed = 0;
+    let mode = sentence_dedup_mode();
+    if mode != SentenceDedup::Off {
+        let bodies: Vec<&str> = packed_chunks.iter().map(|(_, b)| b.as_str()).collect();
+        let sentences: Vec<Vec<&str>> = bodies
+            .iter()
+            .map(|b| text_cleaner::split_sentences(b))
+            .collect();
+        let flat: Vec<&str> = sentences.iter().flatten().copied().collect();
+        let repeated = match (mode, embedder) {
+            (SentenceDedup::Embed, Some(embedder)) => embedded_duplicates(embedder, &flat).await?,
+            _ => hashed_duplicates(&flat),
+        };
+        let (bodies, removed) = strip_sentences(&bodies, &sentences, &repeated);
+        sentences_removed = removed;
+
+        // A chunk whose every sentence was a repeat disappears with its source
+        let mut kept_sources = Vec::new();
+        let mut kept_chunks = Vec::new();
+        for (((label, _), body), source) in packed_chunks.iter().zip(bodies).zip(sources) {
+            if !body.is_empty() {
+                kept_chunks.push((label.clone(), body));
+                kept_sources.push(source);
             }
-            break;
         }
-
-        packed_chunks.push(format!("[{}] {}", chunk.section, compressed));
-        current_tokens += comp_tokens;
+        packed_chunks = kept_chunks;
+        sources = kept_sources;
     }
 
-    let context = packed_chunks.join("\n\n");
+    let context = packed_chunks
+        .iter()
+        .map(|(label, body)| format!("{label} {body}"))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let passages = packed_chunks.into_iter().map(|(_, body)| body).collect();
     let distilled_tokens = text_cleaner::estimate_tokens(&context);
     let compression_ratio = if original_tokens > 0 {
         1.0 - (distilled_tokens as f64 / original_tokens as f64)
     } else {
         0.0
     };
+    tracing::debug!(
+        original_tokens,
+        distilled_tokens,
+        sentences_removed,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "distill done"
+    );
 
     Ok(DistillResult {
         context,
+        passages,
         original_tokens,
         distilled_tokens,
         compression_ratio,
         chunks_retrieved,
         chunks_after_dedup,
+        chunks_filtered,
+        sources,
+        sentences_removed,
+        top_score,
+        keyword_only: embedder.is_none(),
+        expansions,
+        expansion_chunks,
+        warnings,
     })
 }
 
-struct ScoredChunk {
-    text: String,
-    section: String,
-    #[allow(dead_code)]
-    filename: String,
-    score: f64,
+/// Chunks packed into the context, as `(label, body)`, with their sources
+struct Packed {
+    chunks: Vec<(String, String)>,
+    sources: Vec<SourceRef>,
+    /// Tokens of the considered chunks before compression
+    original_tokens: usize,
+}
+
+fn compress_chunk(text: &str, mode: Compress) -> String {
+    match mode {
+        Compress::Stopwords => text_cleaner::compress_text(text),
+        Compress::Summarize => text.to_string(),
+    }
+}
+
+/// Most LLM summaries one query may wait for under GHOST_COMPRESS=summarize;
+/// chunks that overflow after that are truncated
+const MAX_SUMMARIES: usize = 3;
+
+/// Fits overflowing chunks into what is left of the budget
+struct Shrinker<'a> {
+    mode: Compress,
+    /// Backend and model to summarize with (the ones answering the query)
+    backend: Box<dyn provider::Backend>,
+    model: Option<&'a str>,
+    summaries_left: usize,
+    /// Why summaries gave way to truncation, once one failed
+    warning: Option<String>,
+}
+
+impl<'a> Shrinker<'a> {
+    fn new(mode: Compress, backend: Box<dyn provider::Backend>, model: Option<&'a str>) -> Self {
+        Self {
+            mode,
+            backend,
+            model,
+            summaries_left: MAX_SUMMARIES,
+            warning: None,
+        }
+    }
+
+    /// Fit `compressed` into `remaining` budget units by truncating it or, with
+    /// GHOST_COMPRESS=summarize, by having the LLM summarize it. After a failed
+    /// summary the rest of the query truncates rather than retrying.
+    async fn shrink(&mut self, compressed: &str, remaining: usize, unit: BudgetUnit) -> String {
+        if self.mode == Compress::Stopwords || self.summaries_left == 0 {
+            return unit.truncate(compressed, remaining);
+        }
+        self.summaries_left -= 1;
+        let words = unit.words(remaining);
+        match provider::summarize(self.backend.as_ref(), compressed, words, self.model).await {
+            // Still truncated, in case the model overshoots
+            Ok(summary) if !summary.is_empty() => unit.truncate(&summary, remaining),
+            Ok(_) => unit.truncate(compressed, remaining),
+            Err(e) => {
+                self.summaries_left = 0;
+                self.warning = Some(format!(
+                    "GHOST_COMPRESS=summarize could not summarize ({e:#}); truncating instead"
+                ));
+                unit.truncate(compressed, remaining)
+            }
+        }
+    }
+}
+
+/// Take chunks best-first until the budget runs out, shrinking the one that overflows
+async fn pack_greedy(
+    chunks: &[&ScoredChunk],
+    budget: usize,
+    unit: BudgetUnit,
+    shrinker: &mut Shrinker<'_>,
+) -> Packed {
+    let mode = shrinker.mode;
+    let mut packed = Packed {
+        chunks: Vec::new(),
+        sources: Vec::new(),
+        original_tokens: 0,
+    };
+    let mut used = 0;
+
+    for chunk in chunks {
+        packed.original_tokens += text_cleaner::estimate_tokens(&chunk.text);
+
+        let compressed = compress_chunk(&chunk.text, mode);
+        let size = unit.measure(&compressed);
+
+        if used + size <= budget {
+            packed.chunks.push((chunk.label(), compressed));
+            packed.sources.push(chunk.source());
+            used += size;
+            continue;
+        }
+
+        let remaining = budget.saturating_sub(used);
+        if remaining <= unit.min_shrink() {
+            break;
+        }
+
+        let shrunk = shrinker.shrink(&compressed, remaining, unit).await;
+        used += unit.measure(&shrunk);
+        packed.chunks.push((chunk.label(), shrunk));
+        packed.sources.push(chunk.source());
+        // A truncated chunk fills the budget; a summary may leave room for more
+        if mode == Compress::Stopwords {
+            break;
+        }
+    }
+    packed
+}
+
+/// Give each distinct (file, section) a share of the budget weighted by its best
+/// score, then pack each section best-first within its share. Output keeps the
+/// overall score order.
+async fn pack_balanced(
+    chunks: &[&ScoredChunk],
+    budget: usize,
+    unit: BudgetUnit,
+    shrinker: &mut Shrinker<'_>,
+) -> Packed {
+    let compressed: Vec<String> = chunks
+        .iter()
+        .map(|c| compress_chunk(&c.text, shrinker.mode))
+        .collect();
+    let sizes: Vec<usize> = compressed.iter().map(|c| unit.measure(c)).collect();
+
+    // Sections in order of first (= best-scored) appearance
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of: HashMap<(&str, &str), usize> = HashMap::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let key = (chunk.filename.as_str(), chunk.section.as_str());
+        let g = *group_of.entry(key).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[g].push(i);
+    }
+
+    let needs: Vec<usize> = groups
+        .iter()
+        .map(|g| g.iter().map(|&i| sizes[i]).sum())
+        .collect();
+    let weights: Vec<f64> = groups.iter().map(|g| chunks[g[0]].score).collect();
+    let shares = allocate_budget(&needs, &weights, budget);
+
+    let mut picked: Vec<(usize, String)> = Vec::new();
+    for (group, share) in groups.iter().zip(shares) {
+        let mut left = share;
+        for &i in group {
+            if sizes[i] <= left {
+                picked.push((i, compressed[i].clone()));
+                left -= sizes[i];
+                continue;
+            }
+            if left > unit.min_shrink() {
+                picked.push((i, shrinker.shrink(&compressed[i], left, unit).await));
+            }
+            break;
+        }
+    }
+    picked.sort_by_key(|(i, _)| *i);
+
+    Packed {
+        sources: picked.iter().map(|(i, _)| chunks[*i].source()).collect(),
+        chunks: picked
+            .into_iter()
+            .map(|(i, body)| (chunks[i].label(), body))
+            .collect(),
+        original_tokens: chunks
+            .iter()
+            .map(|c| text_cleaner::estimate_tokens(&c.text))
+            .sum(),
+    }
+}
+
+/// Split `budget` across groups in proportion to `weights`, never giving a group
+/// more than it `needs`; whatever a group leaves unused goes to the others
+fn allocate_budget(needs: &[usize], weights: &[f64], budget: usize) -> Vec<usize> {
+    let mut alloc = vec![0; needs.len()];
+    let mut open: Vec<usize> = (0..needs.len()).filter(|&i| needs[i] > 0).collect();
+    let mut left = budget;
+
+    while !open.is_empty() && left > 0 {
+        let weight = |i: usize| weights[i].max(f64::EPSILON);
+        let total: f64 = open.iter().map(|&i| weight(i)).sum();
+        let share = |i: usize| (left as f64 * weight(i) / total).floor() as usize;
+
+        let satisfied: Vec<usize> = open
+            .iter()
+            .copied()
+            .filter(|&i| needs[i] <= share(i))
+            .collect();
+        if satisfied.is_empty() {
+            let shares: Vec<usize> = open.iter().map(|&i| share(i)).collect();
+            for (&i, s) in open.iter().zip(shares) {
+                alloc[i] = s;
+            }
+            break;
+        }
+        for i in satisfied {
+            alloc[i] = needs[i];
+            left -= needs[i];
+        }
+        open.retain(|&i| alloc[i] == 0);
+    }
+    alloc
+}
+
+/// Embed the query, search the stores, and rank the hits by hybrid score (descending).
+/// This is the retrieval half of `distill()`, without dedup, packing, or any LLM call.
+///
+/// With several stores, each contributes its own `top_k` candidates and the
+/// merged pool is scored together; hits are tagged with their collection.
+pub async fn retrieve(
+    query: &str,
+    embedder: &Arc<Mutex<TextEmbedding>>,
+    stores: &[&VectorStore],
+    top_k: u64,
+    filter: Option<&db::Filter>,
+) -> Result<Vec<ScoredChunk>> {
+    let (scored, _) = retrieve_expanded(query, &[], embedder, stores, top_k, filter).await?;
+    Ok(scored)
+}
+
+/// `retrieve`, additionally searching with each of `expansions` and adding the
+/// hits the query itself missed. Returns the chunks and how many only the
+/// expansions found. Keyword scoring still uses the original query's terms.
+async fn retrieve_expanded(
+    query: &str,
+    expansions: &[String],
+    embedder: &Arc<Mutex<TextEmbedding>>,
+    stores: &[&VectorStore],
+    top_k: u64,
+    filter: Option<&db::Filter>,
+) -> Result<(Vec<ScoredChunk>, usize)> {
+    for store in stores {
+        ingest::ensure_dimension(store)?;
+    }
+
+    // 1. Generate query embedding (reused for repeated queries)
+    let query_vec = embed_query(embedder, query).await?;
+
+    // 2. Vector similarity search
+    let hits = search_stores(stores, query_vec, top_k, filter).await?;
+    let mut seen: HashSet<String> = hits.iter().map(|(key, _)| key.clone()).collect();
+    let mut search_results: Vec<_> = hits.into_iter().map(|(_, hit)| hit).collect();
+    let mut expansion_chunks = 0;
+    if !expansions.is_empty() {
+        let vectors = ingest::embed_texts(embedder, expansions.to_vec()).await?;
+        for vector in vectors {
+            for (key, hit) in search_stores(stores, vector, top_k, filter).await? {
+                // A chunk both searches found keeps its score against the original query
+                if seen.insert(key) {
+                    search_results.push(hit);
+                    expansion_chunks += 1;
+                }
+            }
+        }
+    }
+
+    // 3. Hybrid scoring: vector similarity + keyword BM25 (70/30 unless overridden)
+    let query_terms = extract_terms(query);
+    let scored = score_chunks(
+        &search_results,
+        &query_terms,
+        min_term_matches(),
+        hybrid_weights(),
+    );
+    for chunk in &scored {
+        tracing::trace!(
+            filename = %chunk.filename,
+            section = %chunk.section,
+            score = chunk.score,
+            "hybrid score"
+        );
+    }
+    Ok((scored, expansion_chunks))
+}
+
+/// Nearest neighbours of `vector` across `stores`, as `(key, (score, payload))`
+/// where the key is the collection and point id. Payloads are tagged with their
+/// collection when there are several stores.
+async fn search_stores(
+    stores: &[&VectorStore],
+    vector: Vec<f32>,
+    top_k: u64,
+    filter: Option<&db::Filter>,
+) -> Result<Vec<(String, (f64, HashMap<String, Value>))>> {
+    let mut results = Vec::new();
+    for store in stores {
+        for (score, id, mut payload) in
+            db::search_vectors(store, vector.clone(), top_k, filter).await?
+        {
+            if stores.len() > 1 {
+                payload.insert("collection".to_string(), Value::from(store.collection()));
+            }
+            results.push((format!("{}/{id}", store.collection()), (score, payload)));
+        }
+    }
+    Ok(results)
+}
+
+/// Rank every stored chunk by BM25 over the query terms, for when no embedder
+/// is available. Scores are relative to the best match, as in hybrid scoring,
+/// so the top chunk always scores 1.0 and GHOST_MIN_SCORE/GHOST_LOW_RELEVANCE
+/// don't apply.
+pub async fn retrieve_keywords(
+    query: &str,
+    stores: &[&VectorStore],
+    top_k: u64,
+    filter: Option<&db::Filter>,
+) -> Result<Vec<ScoredChunk>> {
+    let mut candidates = Vec::new();
+    for store in stores {
+        for mut payload in db::scroll_points(store, filter).await? {
+            if stores.len() > 1 {
+                payload.insert("collection".to_string(), Value::from(store.collection()));
+            }
+            candidates.push((0.0, payload));
+        }
+    }
+
+    let query_terms = extract_terms(query);
+    let mut scored = score_chunks(
+        &candidates,
+        &query_terms,
+        min_term_matches().max(1),
+        (0.0, 1.0),
+    );
+    scored.retain(|c| c.score > 0.0);
+    scored.truncate(top_k as usize);
+    tracing::debug!(
+        candidates = candidates.len(),
+        matched = scored.len(),
+        "keyword-only retrieval"
+    );
+    Ok(scored)
+}
+
+/// Query embeddings kept for the lifetime of the process
+const QUERY_CACHE_CAPACITY: usize = 64;
+
+/// Small LRU map from query text to its embedding
+struct QueryCache {
+    capacity: usize,
+    /// Least recently used first
+    order: VecDeque<String>,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+impl QueryCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::new(),
+            vectors: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, query: &str) -> Option<Vec<f32>> {
+        let vector = self.vectors.get(query)?.clone();
+        self.touch(query);
+        Some(vector)
+    }
+
+    fn insert(&mut self, query: String, vector: Vec<f32>) {
+        if self.vectors.insert(query.clone(), vector).is_some() {
+            self.touch(&query);
+            return;
+        }
+        self.order.push_back(query);
+        if self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.vectors.remove(&evicted);
+            }
+        }
+    }
+
+    fn touch(&mut self, query: &str) {
+        if let Some(pos) = self.order.iter().position(|q| q == query) {
+            let key = self.order.remove(pos).unwrap();
+            self.order.push_back(key);
+        }
+    }
+}
+
+fn query_cache() -> &'static StdMutex<QueryCache> {
+    static CACHE: OnceLock<StdMutex<QueryCache>> = OnceLock::new();
+    CACHE.get_or_init(|| StdMutex::new(QueryCache::new(QUERY_CACHE_CAPACITY)))
+}
+
+/// Embed `query`, reusing the vector from an earlier identical query
+async fn embed_query(embedder: &Arc<Mutex<TextEmbedding>>, query: &str) -> Result<Vec<f32>> {
+    if let Some(vector) = query_cache().lock().unwrap().get(query) {
+        return Ok(vector);
+    }
+    let embedding = ingest::embed_texts(embedder, vec![query.to_string()]).await?;
+    let vector = embedding.into_iter().next().unwrap();
+    query_cache()
+        .lock()
+        .unwrap()
+        .insert(query.to_string(), vector.clone());
+    Ok(vector)
+}
+
+/// Turn raw search hits into chunks ranked by hybrid score (descending).
+///
+/// BM25 scores are divided by the best candidate's score so the keyword
+/// component shares the 0-1 range of cosine similarity.
+fn score_chunks(
+    search_results: &[(f64, HashMap<String, Value>)],
+    query_terms: &[String],
+    min_matches: usize,
+    (vector_weight, keyword_weight): (f64, f64),
+) -> Vec<ScoredChunk> {
+    let str_field = |payload: &HashMap<String, Value>, key: &str, default: &str| {
+        payload
+            .get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or(default)
+            .to_string()
+    };
+
+    let texts: Vec<String> = search_results
+        .iter()
+        .map(|(_, payload)| str_field(payload, "text", ""))
+        .collect();
+    let text_refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+    let corpus = Bm25Corpus::from_env(&text_refs, query_terms);
+
+    let keyword_scores: Vec<f64> = texts
+        .iter()
+        .map(|text| keyword_score(text, query_terms, min_matches, &corpus))
+        .collect();
+    let max_keyword = keyword_scores.iter().cloned().fold(0.0, f64::max);
+
+    let mut scored_chunks: Vec<ScoredChunk> = Vec::new();
+
+    for (((vector_score, payload), text), keyword) in
+        search_results.iter().zip(texts).zip(keyword_scores)
+    {
+        let keyword_score = if max_keyword > 0.0 {
+            keyword / max_keyword
+        } else {
+            0.0
+        };
+        let hybrid_score = vector_score * vector_weight + keyword_score * keyword_weight;
+
+        scored_chunks.push(ScoredChunk {
+            collection: payload
+                .get("collection")
+                .and_then(|v| v.as_str())
+                .map(String::from),
+            text,
+            section: str_field(payload, "section", "(unknown)"),
+            filename: str_field(payload, "filename", ""),
+            chunk_index: payload.get("chunk_index").and_then(|v| v.as_u64()),
+            page: payload.get("page").and_then(|v| v.as_u64()),
+            mtime: payload.get(ingest::MTIME_FIELD).and_then(|v| v.as_u64()),
+            score: hybrid_score,
+        });
+    }
+
+    // Sort by hybrid score (descending)
+    scored_chunks.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    scored_chunks
+}
+
+/// A retrieved chunk with its hybrid relevance score
+pub struct ScoredChunk {
+    /// Source collection, when several were searched
+    pub collection: Option<String>,
+    pub text: String,
+    pub section: String,
+    pub filename: String,
+    /// Position of the chunk within its document
+    pub chunk_index: Option<u64>,
+    pub page: Option<u64>,
+    /// Modification time of the source file (Unix seconds), when recorded at ingestion
+    pub mtime: Option<u64>,
+    pub score: f64,
+}
+
+impl ScoredChunk {
+    /// Bracketed source label prefixed to the chunk in the packed context
+    fn label(&self) -> String {
+        match self.page {
+            Some(page) => format!("[{} | {} p.{page}]", self.section, self.filename),
+            None => format!("[{}]", self.section),
+        }
+    }
+
+    fn source(&self) -> SourceRef {
+        SourceRef {
+            collection: self.collection.clone(),
+            filename: self.filename.clone(),
+            section: self.section.clone(),
+            page: self.page,
+            score: self.score,
+        }
+    }
 }
 
 /// Extract query terms for keyword matching
-fn extract_terms(query: &str) -> Vec<String> {
+pub fn extract_terms(query: &str) -> Vec<String> {
+    if cjk::enabled(query) {
+        return cjk::keyword_tokens(query)
+            .into_iter()
+            .filter(|w| w.len() > 2)
+            .collect();
+    }
     query
         .split_whitespace()
         .map(|w| {
@@ -162,35 +1133,139 @@ fn extract_terms(query: &str) -> Vec<String> {
         .collect()
 }
 
-/// Compute a simple TF-IDF-like score for keyword matching
-fn compute_tfidf_score(text: &str, query_terms: &[String]) -> f64 {
-    if query_terms.is_empty() {
-        return 0.0;
+/// Lowercased words with surrounding punctuation stripped
+/// (CJK text is segmented by script, see `cjk::keyword_tokens`)
+fn tokenize(text: &str) -> Vec<String> {
+    if cjk::enabled(text) {
+        return cjk::keyword_tokens(text);
     }
+    text.to_lowercase()
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_string())
+        .filter(|w| !w.is_empty())
+        .collect()
+}
 
-    let text_lower = text.to_lowercase();
-    let text_words: Vec<&str> = text_lower.split_whitespace().collect();
-    let total_words = text_words.len() as f64;
+/// BM25 term saturation (GHOST_BM25_K1)
+const DEFAULT_BM25_K1: f64 = 1.2;
+
+/// BM25 length normalization (GHOST_BM25_B)
+const DEFAULT_BM25_B: f64 = 0.75;
+
+/// Document-frequency statistics over the retrieved candidate set
+struct Bm25Corpus {
+    doc_freq: HashMap<String, usize>,
+    n_docs: usize,
+    avg_len: f64,
+    k1: f64,
+    b: f64,
+}
 
-    if total_words == 0.0 {
+impl Bm25Corpus {
+    fn new(texts: &[&str], query_terms: &[String], k1: f64, b: f64) -> Self {
+        let mut doc_freq: HashMap<String, usize> = HashMap::new();
+        let mut total_len = 0usize;
+        for text in texts {
+            let words = tokenize(text);
+            total_len += words.len();
+            let present: HashSet<&String> = words.iter().collect();
+            for term in query_terms.iter().collect::<HashSet<_>>() {
+                if present.contains(term) {
+                    *doc_freq.entry(term.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+        let n_docs = texts.len();
+        let avg_len = if n_docs > 0 {
+            total_len as f64 / n_docs as f64
+        } else {
+            0.0
+        };
+        Self {
+            doc_freq,
+            n_docs,
+            avg_len,
+            k1,
+            b,
+        }
+    }
+
+    fn from_env(texts: &[&str], query_terms: &[String]) -> Self {
+        let read = |name: &str, default: f64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|v| *v >= 0.0)
+                .unwrap_or(default)
+        };
+        let k1 = read("GHOST_BM25_K1", DEFAULT_BM25_K1);
+        let b = read("GHOST_BM25_B", DEFAULT_BM25_B).min(1.0);
+        Self::new(texts, query_terms, k1, b)
+    }
+
+    /// Non-negative (Lucene-style) inverse document frequency
+    fn idf(&self, term: &str) -> f64 {
+        let df = *self.doc_freq.get(term).unwrap_or(&0) as f64;
+        let n = self.n_docs as f64;
+        ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+    }
+}
+
+/// Okapi BM25 score of `text` for the query terms against the candidate corpus
+fn compute_bm25_score(text: &str, query_terms: &[String], corpus: &Bm25Corpus) -> f64 {
+    if query_terms.is_empty() {
         return 0.0;
     }
 
-    let mut score = 0.0;
-    for term in query_terms {
-        let count = text_words
-            .iter()
-            .filter(|w| w.trim_matches(|c: char| !c.is_alphanumeric()) == term.as_str())
-            .count() as f64;
-        // TF component (normalized by text length)
-        let tf = count / total_words;
-        // Simple IDF approximation (treat rarer terms as more important)
-        let idf = (1.0 + count).ln() + 1.0;
-        score += tf * idf;
+    let words = tokenize(text);
+    if words.is_empty() {
+        return 0.0;
     }
 
-    // Normalize to 0-1 range
-    (score / query_terms.len() as f64).min(1.0)
+    let len_norm = if corpus.avg_len > 0.0 {
+        words.len() as f64 / corpus.avg_len
+    } else {
+        1.0
+    };
+
+    query_terms
+        .iter()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .map(|term| {
+            let tf = words.iter().filter(|w| *w == term).count() as f64;
+            if tf == 0.0 {
+                return 0.0;
+            }
+            let saturation =
+                tf * (corpus.k1 + 1.0) / (tf + corpus.k1 * (1.0 - corpus.b + corpus.b * len_norm));
+            corpus.idf(term) * saturation
+        })
+        .sum()
+}
+
+/// Count how many distinct query terms appear in the text
+fn count_matched_terms(text: &str, query_terms: &[String]) -> usize {
+    let words: HashSet<String> = tokenize(text).into_iter().collect();
+    query_terms
+        .iter()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .filter(|t| words.contains(t.as_str()))
+        .count()
+}
+
+/// Raw BM25 keyword score, zeroed unless at least `min_matches` distinct query terms appear
+fn keyword_score(
+    text: &str,
+    query_terms: &[String],
+    min_matches: usize,
+    corpus: &Bm25Corpus,
+) -> f64 {
+    if count_matched_terms(text, query_terms) < min_matches {
+        return 0.0;
+    }
+    compute_bm25_score(text, query_terms, corpus)
 }
 
 /// Remove redundant chunks based on cosine similarity threshold
@@ -214,6 +1289,116 @@ fn remove_redundant<'a>(
     kept.into_iter().map(|(_, c)| c).collect()
 }
 
+/// Lowercased alphanumeric words of a sentence, or `None` if it is too short to dedup
+fn sentence_key(sentence: &str) -> Option<String> {
+    let key = sentence
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ");
+    (key.chars().count() >= MIN_DEDUP_SENTENCE_CHARS).then_some(key)
+}
+
+/// Which sentences repeat an earlier one word for word (ignoring case and punctuation)
+fn hashed_duplicates(sentences: &[&str]) -> Vec<bool> {
+    let mut seen = HashSet::new();
+    sentences
+        .iter()
+        .map(|s| sentence_key(s).is_some_and(|key| !seen.insert(key)))
+        .collect()
+}
+
+/// Which sentences are near-duplicates (by embedding) of an earlier kept one
+async fn embedded_duplicates(
+    embedder: &Arc<Mutex<TextEmbedding>>,
+    sentences: &[&str],
+) -> Result<Vec<bool>> {
+    let candidates: Vec<usize> = (0..sentences.len())
+        .filter(|&i| sentence_key(sentences[i]).is_some())
+        .collect();
+    let texts = candidates
+        .iter()
+        .map(|&i| sentences[i].to_string())
+        .collect();
+    let embeddings = ingest::embed_texts(embedder, texts).await?;
+
+    let mut repeated = vec![false; sentences.len()];
+    let mut kept: Vec<&Vec<f32>> = Vec::new();
+    for (&i, embedding) in candidates.iter().zip(&embeddings) {
+        let similar = kept
+            .iter()
+            .any(|k| text_cleaner::cosine_similarity(embedding, k) > SENTENCE_SIMILARITY_THRESHOLD);
+        if similar {
+            repeated[i] = true;
+        } else {
+            kept.push(embedding);
+        }
+    }
+    Ok(repeated)
+}
+
+/// Rebuild each body without its flagged sentences; returns the bodies and how many were dropped.
+/// Bodies with nothing dropped are returned unchanged.
+fn strip_sentences(
+    bodies: &[&str],
+    sentences: &[Vec<&str>],
+    repeated: &[bool],
+) -> (Vec<String>, usize) {
+    let mut flags = repeated.iter();
+    let mut removed = 0;
+    let bodies = bodies
+        .iter()
+        .zip(sentences)
+        .map(|(body, sentences)| {
+            let kept: Vec<&str> = sentences
+                .iter()
+                .filter(|_| !*flags.next().unwrap())
+                .copied()
+                .collect();
+            if kept.len() == sentences.len() {
+                return body.to_string();
+            }
+            removed += sentences.len() - kept.len();
+            kept.join(" ")
+        })
+        .collect();
+    (bodies, removed)
+}
+
+/// Order chunks by Maximal Marginal Relevance.
+///
+/// Each step picks the chunk maximizing
+/// `lambda * relevance - (1 - lambda) * max_sim_to_selected`, so near-duplicates
+/// sink towards the end where budget packing cuts them off.
+fn mmr_rerank<'a>(
+    chunks: &'a [ScoredChunk],
+    embeddings: &[Vec<f32>],
+    lambda: f64,
+) -> Vec<&'a ScoredChunk> {
+    let mut remaining: Vec<usize> = (0..chunks.len()).collect();
+    let mut selected: Vec<usize> = Vec::with_capacity(chunks.len());
+
+    while !remaining.is_empty() {
+        let mmr = |i: usize| {
+            let max_sim = selected
+                .iter()
+                .map(|&j| text_cleaner::cosine_similarity(&embeddings[i], &embeddings[j]) as f64)
+                .fold(0.0, f64::max);
+            lambda * chunks[i].score - (1.0 - lambda) * max_sim
+        };
+        let (pos, _) = remaining
+            .iter()
+            .enumerate()
+            .map(|(pos, &i)| (pos, mmr(i)))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .unwrap();
+        selected.push(remaining.remove(pos));
+    }
+
+    selected.into_iter().map(|i| &chunks[i]).collect()
+}
+
 /// Truncate text to fit within a token budget
 fn truncate_to_tokens(text: &str, max_tokens: usize) -> String {
     let words: Vec<&str> = text.split_whitespace().collect();
@@ -221,9 +1406,29 @@ fn truncate_to_tokens(text: &str, max_tokens: usize) -> String {
     words[..max_words.min(words.len())].join(" ")
 }
 
+/// Truncate text to at most `max_chars` characters, keeping whole words
+fn truncate_to_chars(text: &str, max_chars: usize) -> String {
+    let mut out = String::new();
+    let mut len = 0;
+    for word in text.split_whitespace() {
+        let sep = usize::from(!out.is_empty());
+        let word_len = word.chars().count();
+        if len + sep + word_len > max_chars {
+            break;
+        }
+        if sep == 1 {
+            out.push(' ');
+        }
+        out.push_str(word);
+        len += sep + word_len;
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn test_extract_terms() {
@@ -236,13 +1441,298 @@ mod tests {
     }
 
     #[test]
-    fn test_tfidf_score() {
+    fn test_bm25_score() {
         let text = "Context distillation is a technique for compressing context";
         let terms = vec!["context".to_string(), "distillation".to_string()];
-        let score = compute_tfidf_score(text, &terms);
+        let corpus = Bm25Corpus::new(&[text], &terms, DEFAULT_BM25_K1, DEFAULT_BM25_B);
+        let score = compute_bm25_score(text, &terms, &corpus);
         assert!(score > 0.0);
     }
 
+    #[test]
+    fn test_bm25_rare_term_outweighs_common_term() {
+        let texts = [
+            "context window limits",
+            "context budget and context packing",
+            "distillation removes noise",
+        ];
+        let terms = vec!["context".to_string(), "distillation".to_string()];
+        let corpus = Bm25Corpus::new(&texts, &terms, DEFAULT_BM25_K1, DEFAULT_BM25_B);
+        assert!(corpus.idf("distillation") > corpus.idf("context"));
+        assert!(
+            compute_bm25_score(texts[2], &terms, &corpus)
+                > compute_bm25_score(texts[0], &terms, &corpus)
+        );
+    }
+
+    #[test]
+    fn test_keyword_floor_ignores_single_stray_term() {
+        let text = "Weather report mentions scheduled system maintenance tonight";
+        let terms = extract_terms("How does the distillation system compress retrieved context?");
+        assert_eq!(count_matched_terms(text, &terms), 1);
+        let corpus = Bm25Corpus::new(&[text], &terms, DEFAULT_BM25_K1, DEFAULT_BM25_B);
+        assert!(keyword_score(text, &terms, 1, &corpus) > 0.0);
+        assert_eq!(keyword_score(text, &terms, 2, &corpus), 0.0);
+    }
+
+    #[test]
+    fn test_bm25_matches_unspaced_japanese() {
+        let texts = [
+            "東京都の人口は約1400万人です。",
+            "大阪の名物はたこ焼きです。",
+        ];
+        let terms = extract_terms("東京の人口");
+        assert_eq!(terms, ["東京", "人口"]);
+        let corpus = Bm25Corpus::new(&texts, &terms, DEFAULT_BM25_K1, DEFAULT_BM25_B);
+        assert!(keyword_score(texts[0], &terms, 2, &corpus) > 0.0);
+        assert_eq!(keyword_score(texts[1], &terms, 1, &corpus), 0.0);
+    }
+
+    #[test]
+    fn test_dedup_threshold_range() {
+        assert_eq!(dedup_threshold(Some(0.6)).unwrap(), 0.6);
+        let err = dedup_threshold(Some(1.5)).unwrap_err().to_string();
+        assert!(err.contains("between 0.0 and 1.0"), "{err}");
+    }
+
+    #[test]
+    fn test_normalize_weights() {
+        assert_eq!(normalize_weights(None, None), DEFAULT_WEIGHTS);
+        assert_eq!(normalize_weights(None, Some(1.0)), (0.0, 1.0));
+        assert_eq!(normalize_weights(Some(2.0), Some(2.0)), (0.5, 0.5));
+        assert_eq!(normalize_weights(Some(0.0), Some(0.0)), DEFAULT_WEIGHTS);
+    }
+
+    #[test]
+    fn test_numbered_context() {
+        let source = |collection: Option<&str>, filename: &str, page| SourceRef {
+            collection: collection.map(String::from),
+            filename: filename.to_string(),
+            section: "Intro".to_string(),
+            page,
+            score: 0.8,
+        };
+        let result = DistillResult {
+            context: String::new(),
+            passages: vec!["First body.".to_string(), "Second body.".to_string()],
+            original_tokens: 0,
+            distilled_tokens: 0,
+            compression_ratio: 0.0,
+            chunks_retrieved: 2,
+            chunks_after_dedup: 2,
+            chunks_filtered: 0,
+            sources: vec![
+                source(None, "notes.md", None),
+                source(Some("work"), "spec.pdf", Some(3)),
+            ],
+            sentences_removed: 0,
+            top_score: 0.8,
+            keyword_only: false,
+            expansions: Vec::new(),
+            expansion_chunks: 0,
+            warnings: Vec::new(),
+        };
+        assert_eq!(
+            result.numbered_context(),
+            "[1] notes.md — Intro\nFirst body.\n\n[2] work: spec.pdf p.3 — Intro\nSecond body."
+        );
+    }
+
+    #[test]
+    fn test_low_relevance_ignores_keyword_only_scores() {
+        let mut result = DistillResult {
+            context: String::new(),
+            passages: Vec::new(),
+            original_tokens: 0,
+            distilled_tokens: 0,
+            compression_ratio: 0.0,
+            chunks_retrieved: 1,
+            chunks_after_dedup: 1,
+            chunks_filtered: 0,
+            sources: Vec::new(),
+            sentences_removed: 0,
+            top_score: 0.01,
+            keyword_only: false,
+            expansions: Vec::new(),
+            expansion_chunks: 0,
+            warnings: Vec::new(),
+        };
+        assert!(result.low_relevance());
+        result.keyword_only = true;
+        assert!(!result.low_relevance());
+    }
+
+    #[test]
+    fn test_merged_hits_keep_their_collection() {
+        let hit = |score: f64, collection: &str| {
+            let payload: HashMap<String, Value> = [
+                ("text".to_string(), Value::from("some text")),
+                ("filename".to_string(), Value::from("notes.md")),
+                ("collection".to_string(), Value::from(collection)),
+            ]
+            .into_iter()
+            .collect();
+            (score, payload)
+        };
+        let results = vec![hit(0.5, "work"), hit(0.9, "personal")];
+
+        let ranked = score_chunks(&results, &[], 1, normalize_weights(None, None));
+        let sources: Vec<Option<String>> = ranked.iter().map(|c| c.source().collection).collect();
+        assert_eq!(
+            sources,
+            vec![Some("personal".to_string()), Some("work".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_keyword_weight_one_ranks_by_bm25() {
+        let hit = |score: f64, text: &str| {
+            let payload: HashMap<String, Value> =
+                [("text".to_string(), Value::String(text.to_string()))]
+                    .into_iter()
+                    .collect();
+            (score, payload)
+        };
+        // Vector scores favour the chunk with the weakest keyword match
+        let results = vec![
+            hit(0.95, "An unrelated paragraph about gardening and soil"),
+            hit(
+                0.60,
+                "Distillation compresses context; distillation keeps context small",
+            ),
+            hit(0.40, "Context distillation explained"),
+        ];
+        let terms = extract_terms("context distillation");
+
+        let ranked = score_chunks(&results, &terms, 1, normalize_weights(None, Some(1.0)));
+        let mut by_bm25: Vec<&str> = results
+            .iter()
+            .map(|(_, p)| p["text"].as_str().unwrap())
+            .collect();
+        let corpus = Bm25Corpus::new(&by_bm25, &terms, DEFAULT_BM25_K1, DEFAULT_BM25_B);
+        by_bm25.sort_by(|a, b| {
+            compute_bm25_score(b, &terms, &corpus)
+                .partial_cmp(&compute_bm25_score(a, &terms, &corpus))
+                .unwrap()
+        });
+
+        let ranked_texts: Vec<&str> = ranked.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(ranked_texts, by_bm25);
+        assert_eq!(
+            ranked_texts[2],
+            "An unrelated paragraph about gardening and soil"
+        );
+    }
+
+    /// Backend whose completions always fail, counting the attempts
+    struct FailingBackend(Arc<AtomicUsize>);
+
+    #[async_trait::async_trait]
+    impl provider::Backend for FailingBackend {
+        fn name(&self) -> String {
+            "failing".into()
+        }
+
+        fn unreachable_hint(&self) -> &'static str {
+            ""
+        }
+
+        async fn health_check(&self) -> bool {
+            false
+        }
+
+        async fn list_models(&self) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn ask_with_context_stream(
+            &self,
+            _query: String,
+            _context: String,
+            _model: Option<String>,
+            _params: provider::GenerationParams,
+            _tx: tokio::sync::mpsc::UnboundedSender<provider::StreamEvent>,
+        ) {
+        }
+
+        async fn complete(
+            &self,
+            _prompt: String,
+            _model: Option<String>,
+            _params: provider::GenerationParams,
+        ) -> Result<String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            anyhow::bail!("backend down")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shrinker_stops_summarizing_after_a_failure() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let backend = Box::new(FailingBackend(calls.clone()));
+        let mut shrinker = Shrinker::new(Compress::Summarize, backend, Some("tiny"));
+        let text = "one two three four five six seven eight nine ten";
+        let shrunk = shrinker.shrink(text, 12, BudgetUnit::Chars).await;
+
+        assert_eq!(shrunk, BudgetUnit::Chars.truncate(text, 12));
+        assert_eq!(shrinker.summaries_left, 0);
+        assert!(shrinker.warning.is_some());
+        // Later overflows truncate without calling the model again
+        assert_eq!(
+            shrinker.shrink(text, 12, BudgetUnit::Chars).await,
+            BudgetUnit::Chars.truncate(text, 12)
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_keyword_only_retrieval() {
+        let point = |filename: &str, text: &str| db::Point {
+            id: db::chunk_id(filename, 0),
+            vector: vec![1.0, 0.0],
+            payload: [
+                ("filename".to_string(), Value::from(filename)),
+                ("text".to_string(), Value::from(text)),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let store = VectorStore::new(
+            std::path::PathBuf::from("unused.json"),
+            vec![
+                point("garden.md", "Soil, compost and watering schedules"),
+                point("rag.md", "Context distillation keeps the prompt small"),
+                point("notes.md", "Distillation of whisky is unrelated"),
+            ],
+        );
+
+        let ranked = retrieve_keywords("context distillation", &[&store], 10, None)
+            .await
+            .unwrap();
+        let files: Vec<&str> = ranked.iter().map(|c| c.filename.as_str()).collect();
+        assert_eq!(files, vec!["rag.md", "notes.md"]);
+        assert_eq!(ranked[0].score, 1.0);
+
+        let only_notes = db::Filter::filename("notes.md");
+        let ranked = retrieve_keywords("context distillation", &[&store], 10, Some(&only_notes))
+            .await
+            .unwrap();
+        assert_eq!(ranked.len(), 1);
+    }
+
+    #[test]
+    fn test_query_cache_evicts_least_recently_used() {
+        let mut cache = QueryCache::new(2);
+        cache.insert("a".into(), vec![1.0]);
+        cache.insert("b".into(), vec![2.0]);
+        assert_eq!(cache.get("a"), Some(vec![1.0]));
+        cache.insert("c".into(), vec![3.0]);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(vec![1.0]));
+        assert_eq!(cache.get("c"), Some(vec![3.0]));
+        assert_eq!(cache.order.len(), 2);
+    }
+
     #[test]
     fn test_truncate_to_tokens() {
         let text = "This is a test sentence with several words in it";
@@ -251,20 +1741,37 @@ mod tests {
         assert!(word_count <= 4); // 5 / 1.3 ≈ 3.8 → 3
     }
 
+    #[test]
+    fn test_budget_in_chars() {
+        let text = "Revenue grew by twelve percent over three years";
+        assert_eq!(BudgetUnit::Chars.measure(text), 47);
+        assert_eq!(truncate_to_chars(text, 20), "Revenue grew by");
+        assert_eq!(truncate_to_chars(text, 100), text);
+        assert_eq!(truncate_to_chars(text, 3), "");
+    }
+
     #[test]
     fn test_redundancy_removal() {
         // Two identical embeddings should result in one being removed
         let chunks = vec![
             ScoredChunk {
+                collection: None,
                 text: "Hello world".to_string(),
                 section: "A".to_string(),
                 filename: "test.md".to_string(),
+                chunk_index: None,
+                page: None,
+                mtime: None,
                 score: 0.9,
             },
             ScoredChunk {
+                collection: None,
                 text: "Hello world again".to_string(),
                 section: "A".to_string(),
                 filename: "test.md".to_string(),
+                chunk_index: None,
+                page: None,
+                mtime: None,
                 score: 0.8,
             },
         ];
@@ -272,4 +1779,98 @@ mod tests {
         let result = remove_redundant(&chunks, &embeddings, 0.85);
         assert_eq!(result.len(), 1);
     }
+
+    #[test]
+    fn test_recency_boost_favours_newer_chunks() {
+        let day = 86_400;
+        let now = 1_000 * day;
+        let chunk = |filename: &str, score: f64, mtime: Option<u64>| ScoredChunk {
+            collection: None,
+            text: String::new(),
+            section: "A".to_string(),
+            filename: filename.to_string(),
+            chunk_index: None,
+            page: None,
+            mtime,
+            score,
+        };
+        let mut chunks = vec![
+            chunk("old.md", 0.8, Some(now - 365 * day)),
+            chunk("undated.md", 0.75, None),
+            chunk("new.md", 0.6, Some(now - 2 * day)),
+        ];
+
+        apply_recency_boost(&mut chunks, now, 30.0);
+        let order: Vec<&str> = chunks.iter().map(|c| c.filename.as_str()).collect();
+        assert_eq!(order, vec!["new.md", "old.md", "undated.md"]);
+        // Undated chunks are left alone
+        assert_eq!(chunks[2].score, 0.75);
+        assert!((recency_multiplier(30.0, 30.0) - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mmr_prefers_diverse_chunk() {
+        let chunk = |text: &str, score: f64| ScoredChunk {
+            collection: None,
+            text: text.to_string(),
+            section: "A".to_string(),
+            filename: "test.md".to_string(),
+            chunk_index: None,
+            page: None,
+            mtime: None,
+            score,
+        };
+        let chunks = vec![
+            chunk("original", 0.9),
+            chunk("near copy", 0.85),
+            chunk("different angle", 0.7),
+        ];
+        let embeddings = vec![vec![1.0, 0.0], vec![0.99, 0.1], vec![0.0, 1.0]];
+
+        let order: Vec<&str> = mmr_rerank(&chunks, &embeddings, 0.5)
+            .iter()
+            .map(|c| c.text.as_str())
+            .collect();
+        assert_eq!(order, vec!["original", "different angle", "near copy"]);
+
+        // lambda = 1.0 is pure relevance ordering
+        let order: Vec<&str> = mmr_rerank(&chunks, &embeddings, 1.0)
+            .iter()
+            .map(|c| c.text.as_str())
+            .collect();
+        assert_eq!(order, vec!["original", "near copy", "different angle"]);
+    }
+
+    #[test]
+    fn test_sentence_dedup_drops_repeats_across_chunks() {
+        let bodies = [
+            "The cache is rebuilt nightly by cron. Keys expire after one hour.",
+            "Unrelated intro. The cache is rebuilt NIGHTLY by cron! Ok.",
+            "The cache is rebuilt nightly by cron.",
+        ];
+        let sentences: Vec<Vec<&str>> = bodies
+            .iter()
+            .map(|b| text_cleaner::split_sentences(b))
+            .collect();
+        let flat: Vec<&str> = sentences.iter().flatten().copied().collect();
+        let repeated = hashed_duplicates(&flat);
+        let (stripped, removed) = strip_sentences(&bodies, &sentences, &repeated);
+
+        assert_eq!(removed, 2);
+        assert_eq!(stripped[0], bodies[0]);
+        assert_eq!(stripped[1], "Unrelated intro. Ok.");
+        assert_eq!(stripped[2], "");
+    }
+
+    #[test]
+    fn test_allocate_budget_shares_leftovers() {
+        // The small section takes only what it needs; the rest is split by weight
+        assert_eq!(
+            allocate_budget(&[100, 5000, 5000], &[0.9, 0.6, 0.3], 1000),
+            [100, 600, 300]
+        );
+        // Everything fits: each section gets exactly its need
+        assert_eq!(allocate_budget(&[10, 20], &[0.5, 0.5], 1000), [10, 20]);
+        assert_eq!(allocate_budget(&[], &[], 1000), Vec::<usize>::new());
+    }
 }
@@ -0,0 +1,233 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::db;
+
+/// SHA-256 of the model name and chunk text
+pub type Key = [u8; 32];
+
+/// Bytes before each vector: key + little-endian u32 dimension
+const HEADER_LEN: usize = 32 + 4;
+
+/// Append-only file of `(key, dim, f32 vector)` records. Only the key → offset
+/// index is held in memory; vectors are read back from disk on a hit.
+///
+/// Several processes may share the file: writers append under an exclusive
+/// lock, and reads check each record's key, so a stale index only costs misses.
+pub struct EmbedCache {
+    path: PathBuf,
+    index: HashMap<Key, (u64, usize)>,
+    /// Length of the file up to the last complete record
+    len: u64,
+}
+
+pub fn key(model: &str, text: &str) -> Key {
+    let mut hasher = Sha256::new();
+    hasher.update(model.as_bytes());
+    hasher.update([0]);
+    hasher.update(text.as_bytes());
+    hasher.finalize().into()
+}
+
+/// The shared cache under the data dir, unless GHOST_NO_CACHE is set
+pub fn global() -> Option<&'static Mutex<EmbedCache>> {
+    static CACHE: OnceLock<Option<Mutex<EmbedCache>>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| {
+            let disabled = std::env::var("GHOST_NO_CACHE").is_ok_and(|v| !v.is_empty() && v != "0");
+            if disabled {
+                return None;
+            }
+            EmbedCache::open(db::data_dir().join("embed-cache.bin"))
+                .ok()
+                .map(Mutex::new)
+        })
+        .as_ref()
+}
+
+impl EmbedCache {
+    /// Index an existing cache file; a truncated final record (interrupted write) is ignored
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let mut cache = Self {
+            path,
+            index: HashMap::new(),
+            len: 0,
+        };
+        if let Ok(file) = File::open(&cache.path) {
+            cache.index_from(&file)?;
+        }
+        Ok(cache)
+    }
+
+    /// Index the complete records after `self.len`, e.g. ones another process appended
+    fn index_from(&mut self, file: &File) -> Result<()> {
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(self.len))?;
+        let mut header = [0u8; HEADER_LEN];
+        let mut vector = Vec::new();
+        while reader.read_exact(&mut header).is_ok() {
+            let key: Key = header[..32].try_into().unwrap();
+            let dim = u32::from_le_bytes(header[32..].try_into().unwrap()) as usize;
+            vector.resize(dim * 4, 0);
+            if reader.read_exact(&mut vector).is_err() {
+                break;
+            }
+            self.index.insert(key, (self.len + HEADER_LEN as u64, dim));
+            self.len += (HEADER_LEN + dim * 4) as u64;
+        }
+        Ok(())
+    }
+
+    /// Cached vectors for each key, `None` on a miss. A record that no longer
+    /// holds the key it was indexed under also counts as a miss.
+    pub fn get_many(&self, keys: &[Key]) -> Result<Vec<Option<Vec<f32>>>> {
+        if !keys.iter().any(|k| self.index.contains_key(k)) {
+            return Ok(vec![None; keys.len()]);
+        }
+        let mut file = File::open(&self.path)
+            .with_context(|| format!("Cannot open {}", self.path.display()))?;
+        let mut header = [0u8; HEADER_LEN];
+        let mut bytes = Vec::new();
+        let mut read = |key: &Key, offset: u64, dim: usize| -> std::io::Result<Option<Vec<f32>>> {
+            file.seek(SeekFrom::Start(offset - HEADER_LEN as u64))?;
+            file.read_exact(&mut header)?;
+            if header[..32] != key[..] || header[32..] != (dim as u32).to_le_bytes() {
+                return Ok(None);
+            }
+            bytes.resize(dim * 4, 0);
+            file.read_exact(&mut bytes)?;
+            Ok(Some(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                    .collect(),
+            ))
+        };
+        Ok(keys
+            .iter()
+            .map(|k| {
+                let &(offset, dim) = self.index.get(k)?;
+                // A short read means the file changed under us: a miss, not an error
+                read(k, offset, dim).ok().flatten()
+            })
+            .collect())
+    }
+
+    /// Append new vectors; keys already cached are skipped
+    pub fn insert_many(&mut self, entries: &[(Key, &[f32])]) -> Result<()> {
+        if entries.iter().all(|(k, _)| self.index.contains_key(k)) {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Cannot write {}", self.path.display()))?;
+        // Held until `file` is dropped; other ghost-lib processes append under it too
+        file.lock()
+            .with_context(|| format!("Cannot lock {}", self.path.display()))?;
+        self.index_from(&file)?;
+        // Past the last complete record there can only be the remains of a writer
+        // that died mid-record (nobody else is writing); drop them so ours line up
+        if file.metadata()?.len() > self.len {
+            file.set_len(self.len)?;
+        }
+
+        let mut seen = HashSet::new();
+        let fresh: Vec<_> = entries
+            .iter()
+            .filter(|(k, _)| !self.index.contains_key(k) && seen.insert(*k))
+            .collect();
+        if fresh.is_empty() {
+            return Ok(());
+        }
+
+        let mut writer = BufWriter::new(&file);
+        let mut len = self.len;
+        let mut added = Vec::with_capacity(fresh.len());
+        for (key, vector) in fresh {
+            writer.write_all(key)?;
+            writer.write_all(&(vector.len() as u32).to_le_bytes())?;
+            for v in vector.iter() {
+                writer.write_all(&v.to_le_bytes())?;
+            }
+            added.push((*key, (len + HEADER_LEN as u64, vector.len())));
+            len += (HEADER_LEN + vector.len() * 4) as u64;
+        }
+        writer.flush()?;
+
+        self.index.extend(added);
+        self.len = len;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_util::TempDir;
+
+    #[test]
+    fn test_cache_roundtrip_and_truncated_tail() {
+        let dir = TempDir::new();
+        let path = dir.join("embed-cache.bin");
+        let a = key("model", "alpha");
+        let b = key("model", "beta");
+        assert_ne!(a, key("other-model", "alpha"));
+
+        let mut cache = EmbedCache::open(path.clone()).unwrap();
+        cache
+            .insert_many(&[(a, &[1.0, 2.0][..]), (b, &[3.0, 4.0, 5.0][..])])
+            .unwrap();
+
+        // Simulate a write cut off mid-record
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[7u8; 10]).unwrap();
+
+        let mut reopened = EmbedCache::open(path.clone()).unwrap();
+        assert_eq!(reopened.index.len(), 2);
+        let hits = reopened.get_many(&[b, key("model", "gamma"), a]).unwrap();
+        assert_eq!(
+            hits,
+            [Some(vec![3.0, 4.0, 5.0]), None, Some(vec![1.0, 2.0])]
+        );
+
+        let c = key("model", "gamma");
+        reopened.insert_many(&[(c, &[6.0][..])]).unwrap();
+        let again = EmbedCache::open(path.clone()).unwrap();
+        assert_eq!(again.get_many(&[c]).unwrap(), [Some(vec![6.0])]);
+
+        // Another process appending keeps what this one wrote, and vice versa
+        let d = key("model", "delta");
+        let e = key("model", "epsilon");
+        let mut other = EmbedCache::open(path.clone()).unwrap();
+        other.insert_many(&[(d, &[7.0, 8.0][..])]).unwrap();
+        reopened.insert_many(&[(e, &[9.0][..])]).unwrap();
+        let merged = EmbedCache::open(path.clone()).unwrap();
+        assert_eq!(
+            merged.get_many(&[a, c, d, e]).unwrap(),
+            [
+                Some(vec![1.0, 2.0]),
+                Some(vec![6.0]),
+                Some(vec![7.0, 8.0]),
+                Some(vec![9.0])
+            ]
+        );
+
+        // An index entry pointing at someone else's record is a miss
+        let mut stale = EmbedCache::open(path).unwrap();
+        let wrong = stale.index[&a];
+        stale.index.insert(b, wrong);
+        assert_eq!(stale.get_many(&[b]).unwrap(), [None]);
+    }
+}
@@ -0,0 +1,172 @@
+use anyhow::Result;
+use fastembed::TextEmbedding;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::core::ingest;
+use crate::utils::{cjk, text_cleaner};
+
+/// Cosine similarity a passage needs to count as support for a sentence, per
+/// embedding model family. Models differ in how similar unrelated text looks:
+/// E5 rarely scores anything below ~0.75, MiniLM spreads scores much wider.
+const VERIFY_THRESHOLDS: &[(&str, f32)] = &[
+    ("multilingual-e5", 0.85),
+    ("bge-", 0.7),
+    ("nomic-embed", 0.65),
+    ("mxbai-embed", 0.65),
+    ("all-minilm", 0.5),
+];
+
+/// For models missing from `VERIFY_THRESHOLDS`
+const DEFAULT_VERIFY_THRESHOLD: f32 = 0.6;
+
+/// Sentences shorter than this ("Yes.", list headings) are not checked
+const MIN_CLAIM_WORDS: usize = 4;
+
+/// Support threshold for `ask --verify`: GHOST_VERIFY_THRESHOLD, else the
+/// calibrated value for the active embedding model
+pub fn verify_threshold() -> f32 {
+    std::env::var("GHOST_VERIFY_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| {
+            let model = ingest::embedding_model()
+                .map(|(model, _)| ingest::model_name(&model))
+                .unwrap_or("unknown");
+            model_threshold(model)
+        })
+}
+
+fn model_threshold(model: &str) -> f32 {
+    VERIFY_THRESHOLDS
+        .iter()
+        .find(|(family, _)| model.starts_with(family))
+        .map_or(DEFAULT_VERIFY_THRESHOLD, |&(_, threshold)| threshold)
+}
+
+/// An answer sentence that no passage of the context supports
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Unsupported {
+    pub sentence: String,
+    /// Similarity to the closest passage
+    pub best_score: f32,
+}
+
+/// The checkable sentences of an answer: list markers and citation markers
+/// (`[1]`, `[^2]`) stripped, too-short fragments dropped
+fn claims(answer: &str) -> Vec<String> {
+    text_cleaner::split_sentences(answer)
+        .into_iter()
+        .map(|s| strip_citations(s.trim_start_matches(['-', '*', '#', '>', ' '])))
+        .filter(|s| s.split_whitespace().count() >= MIN_CLAIM_WORDS || cjk::contains_cjk(s))
+        .collect()
+}
+
+/// `text` without bracketed citation markers such as `[1]`, `[2, 3]` or `[^1]`
+fn strip_citations(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        match after.find(']') {
+            Some(close)
+                if close > 0
+                    && after[..close]
+                        .chars()
+                        .all(|c| c.is_ascii_digit() || matches!(c, '^' | ',' | ' ')) =>
+            {
+                out.truncate(out.trim_end().len());
+                rest = &after[close + 1..];
+            }
+            _ => {
+                out.push('[');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Embed each sentence of `answer` and flag those whose closest passage scores
+/// below `threshold`, in answer order. An empty context supports nothing.
+pub async fn unsupported_sentences(
+    embedder: &Arc<Mutex<TextEmbedding>>,
+    answer: &str,
+    passages: &[String],
+    threshold: f32,
+) -> Result<Vec<Unsupported>> {
+    let claims = claims(answer);
+    if claims.is_empty() {
+        return Ok(Vec::new());
+    }
+    let passage_embeddings = ingest::embed_texts(embedder, passages.to_vec()).await?;
+    let claim_embeddings = ingest::embed_texts(embedder, claims.clone()).await?;
+    Ok(flag_unsupported(
+        claims,
+        &claim_embeddings,
+        &passage_embeddings,
+        threshold,
+    ))
+}
+
+fn flag_unsupported(
+    claims: Vec<String>,
+    claim_embeddings: &[Vec<f32>],
+    passage_embeddings: &[Vec<f32>],
+    threshold: f32,
+) -> Vec<Unsupported> {
+    claims
+        .into_iter()
+        .zip(claim_embeddings)
+        .filter_map(|(sentence, claim)| {
+            let best_score = passage_embeddings
+                .iter()
+                .map(|p| text_cleaner::cosine_similarity(claim, p))
+                .fold(0.0, f32::max);
+            (best_score < threshold).then_some(Unsupported {
+                sentence,
+                best_score,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claims_strip_markers_and_skip_fragments() {
+        let answer = "Yes.\n- The cache is flushed every 30 seconds [1].\n\
+                      It uses an LRU policy [^2] for eviction [2, 3]. See [the docs] for more.";
+        assert_eq!(
+            claims(answer),
+            vec![
+                "The cache is flushed every 30 seconds.",
+                "It uses an LRU policy for eviction.",
+                "See [the docs] for more.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_model_threshold() {
+        assert_eq!(model_threshold("multilingual-e5-small"), 0.85);
+        assert_eq!(model_threshold("bge-base-en-v1.5"), 0.7);
+        assert_eq!(model_threshold("unknown"), DEFAULT_VERIFY_THRESHOLD);
+    }
+
+    #[test]
+    fn test_flag_unsupported_uses_best_passage() {
+        let claims = vec!["backed".to_string(), "made up".to_string()];
+        let claim_embeddings = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        let passages = vec![vec![0.0, -1.0], vec![0.9, 0.1]];
+        let flagged = flag_unsupported(claims, &claim_embeddings, &passages, 0.5);
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].sentence, "made up");
+        assert!(flagged[0].best_score < 0.5);
+    }
+}
@@ -1,43 +1,322 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use futures::FutureExt;
 use indicatif::{ProgressBar, ProgressStyle};
 use serde_json::Value;
-use std::collections::HashMap;
-use std::path::Path;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use text_splitter::MarkdownSplitter;
-use tokio::sync::Mutex;
-use uuid::Uuid;
+use std::time::{Duration, UNIX_EPOCH};
+use text_splitter::{ChunkConfig, MarkdownSplitter};
+use tokio::sync::{mpsc, Mutex};
 
+use crate::core::embed_cache;
 use crate::db;
-use crate::utils::text_cleaner;
+use crate::utils::text_cleaner::HeadingStyle;
+use crate::utils::{docx, encoding, epub, ocr, text_cleaner};
 
-/// Create a shared embedding model (MultilingualE5Small, 384 dims — supports EN/JA/etc.)
-pub fn create_embedder() -> Result<Arc<Mutex<TextEmbedding>>> {
-    let model = TextEmbedding::try_new(
-        InitOptions::new(EmbeddingModel::MultilingualE5Small).with_show_download_progress(true),
+/// Embedding models selectable with GHOST_EMBED_MODEL (first entry is the default)
+const EMBED_MODELS: &[(&str, EmbeddingModel)] = &[
+    ("multilingual-e5-small", EmbeddingModel::MultilingualE5Small),
+    ("multilingual-e5-base", EmbeddingModel::MultilingualE5Base),
+    ("multilingual-e5-large", EmbeddingModel::MultilingualE5Large),
+    ("bge-small-en-v1.5", EmbeddingModel::BGESmallENV15),
+    ("bge-base-en-v1.5", EmbeddingModel::BGEBaseENV15),
+    ("bge-large-en-v1.5", EmbeddingModel::BGELargeENV15),
+    ("all-minilm-l6-v2", EmbeddingModel::AllMiniLML6V2),
+    ("nomic-embed-text-v1.5", EmbeddingModel::NomicEmbedTextV15),
+    ("mxbai-embed-large-v1", EmbeddingModel::MxbaiEmbedLargeV1),
+];
+
+fn parse_embed_model(name: &str) -> Result<EmbeddingModel> {
+    EMBED_MODELS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, model)| model.clone())
+        .ok_or_else(|| {
+            let known: Vec<&str> = EMBED_MODELS.iter().map(|(n, _)| *n).collect();
+            anyhow!(
+                "Unknown GHOST_EMBED_MODEL '{name}'. Supported: {}",
+                known.join(", ")
+            )
+        })
+}
+
+/// The configured embedding model and the dimension of the vectors it produces
+pub fn embedding_model() -> Result<(EmbeddingModel, usize)> {
+    let model = match std::env::var("GHOST_EMBED_MODEL") {
+        Ok(name) if !name.trim().is_empty() => parse_embed_model(&name)?,
+        _ => EMBED_MODELS[0].1.clone(),
+    };
+    let dim = TextEmbedding::get_model_info(&model)?.dim;
+    Ok((model, dim))
+}
+
+/// Payload field recording which embedding model produced a chunk's vector
+pub const MODEL_FIELD: &str = "embed_model";
+
+/// Payload field holding the byte length of the indexed (normalized) document text
+const TEXT_LEN_FIELD: &str = "text_len";
+
+/// Payload field holding the file's modification time (Unix seconds) when it was indexed
+pub const MTIME_FIELD: &str = "mtime";
+
+/// The GHOST_EMBED_MODEL name of a supported model
+pub fn model_name(model: &EmbeddingModel) -> &'static str {
+    EMBED_MODELS
+        .iter()
+        .find(|(_, m)| m == model)
+        .map_or("unknown", |(name, _)| name)
+}
+
+/// Refuse to mix vectors from a different embedding model into the collection,
+/// or to search it with one
+pub fn ensure_dimension(store: &db::VectorStore) -> Result<()> {
+    let (model, dim) = embedding_model()?;
+    let stored = store.points.first().map(|p| {
+        let name = p.payload.get(MODEL_FIELD).and_then(|v| v.as_str());
+        (name, p.vector.len())
+    });
+    check_model(stored, (model_name(&model), dim))
+}
+
+/// Compare the model the collection was built with (name missing for chunks
+/// indexed before it was recorded) against the current one
+fn check_model(stored: Option<(Option<&str>, usize)>, (name, dim): (&str, usize)) -> Result<()> {
+    let Some((stored_name, stored_dim)) = stored else {
+        return Ok(());
+    };
+    if stored_dim == dim && stored_name.is_none_or(|n| n == name) {
+        return Ok(());
+    }
+    bail!(
+        "Collection was built with model {} (dim {stored_dim}), current model is {name} (dim {dim}); \
+         run `ghost-lib reindex` to re-embed it, or use a separate GHOST_COLLECTION.",
+        stored_name.unwrap_or("unknown")
     )
-    .context("Failed to initialize embedding model")?;
+}
+
+/// Create a shared embedding model: the one named by GHOST_EMBED_MODEL, else
+/// multilingual-e5-small (384 dims, EN/JA/etc.). Downloaded on first use.
+pub fn create_embedder() -> Result<Arc<Mutex<TextEmbedding>>> {
+    let (embedding_model, _) = embedding_model()?;
+    let model =
+        TextEmbedding::try_new(InitOptions::new(embedding_model).with_show_download_progress(true))
+            .context("Failed to initialize embedding model")?;
     Ok(Arc::new(Mutex::new(model)))
 }
 
-/// Generate embeddings for texts using spawn_blocking (fastembed is not Send-safe)
+/// Generate embeddings for texts using spawn_blocking (fastembed is not Send-safe).
+/// Texts already in the on-disk embedding cache skip inference.
+#[tracing::instrument(level = "debug", skip_all, fields(texts = texts.len()))]
 pub async fn embed_texts(
     embedder: &Arc<Mutex<TextEmbedding>>,
     texts: Vec<String>,
 ) -> Result<Vec<Vec<f32>>> {
     let embedder = embedder.clone();
+    let (model, _) = embedding_model()?;
+    let model_name = format!("{model:?}");
+    let span = tracing::Span::current();
     tokio::task::spawn_blocking(move || {
-        let model = embedder.blocking_lock();
-        model
-            .embed(texts, None)
-            .context("Embedding generation failed")
+        let _span = span.enter();
+        let started = std::time::Instant::now();
+        let Some(cache) = embed_cache::global() else {
+            let vectors = embed_uncached(&embedder, texts)?;
+            tracing::debug!(
+                embedded = vectors.len(),
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "embedded texts (cache disabled)"
+            );
+            return Ok(vectors);
+        };
+
+        let keys: Vec<embed_cache::Key> = texts
+            .iter()
+            .map(|t| embed_cache::key(&model_name, t))
+            .collect();
+        // The cache only saves work, so its I/O errors fall back to the model
+        let mut vectors = cache
+            .lock()
+            .unwrap()
+            .get_many(&keys)
+            .unwrap_or_else(|_| vec![None; keys.len()]);
+
+        let missing: Vec<usize> = (0..texts.len()).filter(|&i| vectors[i].is_none()).collect();
+        let cached = texts.len() - missing.len();
+        let embedded = missing.len();
+        if !missing.is_empty() {
+            let fresh = embed_uncached(
+                &embedder,
+                missing.iter().map(|&i| texts[i].clone()).collect(),
+            )?;
+            let entries: Vec<(embed_cache::Key, &[f32])> = missing
+                .iter()
+                .zip(&fresh)
+                .map(|(&i, v)| (keys[i], v.as_slice()))
+                .collect();
+            let _ = cache.lock().unwrap().insert_many(&entries);
+            for (i, vector) in missing.into_iter().zip(fresh) {
+                vectors[i] = Some(vector);
+            }
+        }
+        tracing::debug!(
+            cached,
+            embedded,
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "embedded texts"
+        );
+        Ok(vectors.into_iter().flatten().collect())
     })
     .await?
 }
 
+fn embed_uncached(embedder: &Mutex<TextEmbedding>, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+    embedder
+        .blocking_lock()
+        .embed(texts, None)
+        .context("Embedding generation failed")
+}
+
+/// Load the embedding model and embed a probe sentence, bypassing the cache.
+/// Returns the model name and the dimension it produced.
+pub async fn check_embedder() -> Result<(String, usize)> {
+    let (model, expected) = embedding_model()?;
+    let vectors = tokio::task::spawn_blocking(|| {
+        let embedder = create_embedder()?;
+        embed_uncached(&embedder, vec!["health check".to_string()])
+    })
+    .await??;
+    let dim = vectors.first().map_or(0, Vec::len);
+    if dim != expected {
+        bail!("{model:?} produced {dim}-dimensional vectors, expected {expected}");
+    }
+    Ok((format!("{model:?}"), dim))
+}
+
+/// File extensions `read_document` knows how to handle
+pub const SUPPORTED_EXTENSIONS: &[&str] =
+    &["md", "txt", "text", "rst", "org", "pdf", "docx", "epub"];
+
+/// Whether a path has one of the supported document extensions
+pub fn is_supported(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| SUPPORTED_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Recursively collect supported documents under `dir`, skipping hidden entries.
+/// Symlinked directories are followed, but each directory is walked once so a
+/// link back up the tree cannot loop forever.
+pub fn collect_documents(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        let canonical = std::fs::canonicalize(&current)
+            .with_context(|| format!("Failed to resolve directory {}", current.display()))?;
+        if !visited.insert(canonical) {
+            continue;
+        }
+        let entries = std::fs::read_dir(&current)
+            .with_context(|| format!("Failed to read directory {}", current.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_supported(&path) {
+                found.push(path);
+            }
+        }
+    }
+
+    found.sort();
+    Ok(found)
+}
+
+/// Seconds to wait for PDF extraction (GHOST_PDF_TIMEOUT, default 60, 0 = no limit)
+fn pdf_timeout() -> Option<Duration> {
+    let secs: u64 = std::env::var("GHOST_PDF_TIMEOUT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(60);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Extract PDF text page by page off the async runtime, turning parser panics and hangs into errors.
+///
+/// Runs on a detached OS thread rather than `spawn_blocking`: a hung extraction
+/// would otherwise keep the runtime from shutting down after we give up on it.
+async fn extract_pdf_pages(bytes: Vec<u8>) -> Result<Vec<String>> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        let result =
+            std::panic::catch_unwind(move || pdf_extract::extract_text_from_mem_by_pages(&bytes));
+        let _ = tx.send(result);
+    });
+
+    let outcome = match pdf_timeout() {
+        Some(limit) => match tokio::time::timeout(limit, rx).await {
+            Ok(outcome) => outcome,
+            Err(_) => bail!(
+                "Failed to extract PDF: timed out after {}s (raise GHOST_PDF_TIMEOUT for very large files)",
+                limit.as_secs()
+            ),
+        },
+        None => rx.await,
+    };
+
+    match outcome {
+        Ok(Ok(Ok(pages))) => Ok(pages),
+        Ok(Ok(Err(e))) => {
+            Err(e).context("Failed to extract text from PDF (set GHOST_OCR=1 to OCR scanned PDFs)")
+        }
+        Ok(Err(panic)) => bail!(
+            "Failed to extract PDF: the parser crashed on this file ({})",
+            panic_message(panic.as_ref())
+        ),
+        // The extraction thread went away without reporting
+        Err(_) => bail!("Failed to extract PDF: the parser crashed on this file"),
+    }
+}
+
+/// Run a synchronous extractor built on third-party parsers, turning a panic into an error
+fn guard_extractor<T>(format: &str, extract: impl FnOnce() -> Result<T>) -> Result<T> {
+    std::panic::catch_unwind(AssertUnwindSafe(extract)).unwrap_or_else(|panic| {
+        bail!(
+            "Failed to extract {format}: the parser crashed on this file ({})",
+            panic_message(panic.as_ref())
+        )
+    })
+}
+
+/// Text of a caught panic payload, for error messages
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Raw text of a document, kept per page for paginated formats
+enum DocumentText {
+    Plain(String),
+    Paged(Vec<String>),
+}
+
 /// Read a document file and return its text content
-fn read_document(path: &Path) -> Result<String> {
+async fn read_document(path: &Path, progress: &Progress) -> Result<DocumentText> {
     let ext = path
         .extension()
         .and_then(|e| e.to_str())
@@ -45,119 +324,926 @@ fn read_document(path: &Path) -> Result<String> {
         .to_lowercase();
 
     match ext.as_str() {
-        "md" | "txt" | "text" | "rst" => {
-            std::fs::read_to_string(path).context("Failed to read text file")
+        "md" | "txt" | "text" | "rst" | "org" => {
+            let bytes = std::fs::read(path).context("Failed to read text file")?;
+            let (text, encoding) = encoding::decode(&bytes)?;
+            if let Some(encoding) = encoding {
+                progress.status(format!("Decoded {} as {encoding}", document_name(path)));
+            }
+            Ok(DocumentText::Plain(text))
         }
         "pdf" => {
             let bytes = std::fs::read(path).context("Failed to read PDF file")?;
-            pdf_extract::extract_text_from_mem(&bytes)
-                .context("Failed to extract text from PDF (scanned PDFs are not supported)")
+            let extracted = extract_pdf_pages(bytes).await;
+            let scanned = extracted.as_ref().map_or(true, |p| ocr::looks_scanned(p));
+            if scanned && ocr::enabled() {
+                progress.status("Little or no text layer found, running OCR...".into());
+                return ocr::ocr_pdf(path).await.map(DocumentText::Paged);
+            }
+            let pages = extracted?;
+            if pages.iter().all(|p| p.trim().is_empty()) {
+                bail!("PDF has no extractable text (scanned?). Set GHOST_OCR=1 to OCR it with Tesseract");
+            }
+            Ok(DocumentText::Paged(pages))
         }
-        _ => bail!("Unsupported file format: .{ext} (supported: .md, .txt, .pdf)"),
+        "docx" => {
+            let bytes = std::fs::read(path).context("Failed to read DOCX file")?;
+            guard_extractor("DOCX", || docx::extract_docx_text(&bytes)).map(DocumentText::Plain)
+        }
+        "epub" => {
+            let bytes = std::fs::read(path).context("Failed to read EPUB file")?;
+            let (text, warnings) = guard_extractor("EPUB", || epub::extract_epub_text(&bytes))?;
+            for warning in warnings {
+                progress.status(format!("Warning: {warning}"));
+            }
+            Ok(DocumentText::Plain(text))
+        }
+        _ => bail!("Unsupported file format: .{ext} (supported: .md, .txt, .pdf, .docx, .epub)"),
     }
 }
 
-/// Ingest a document: read, split, embed, and store
+/// Name a document is indexed under (the `filename` payload field)
+pub fn document_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Progress update from `ingest_file` when reporting to a channel
+#[derive(Debug, Clone, PartialEq)]
+pub enum IngestEvent {
+    /// A line the CLI would print
+    Status(String),
+    /// Chunks embedded so far out of the document's total
+    Embedded { done: usize, total: usize },
+}
+
+/// Where `ingest_file` reports what it is doing
+#[derive(Debug, Clone)]
+pub enum Progress {
+    /// Print log lines and an indicatif bar (CLI)
+    Terminal,
+    /// Send events instead of printing, e.g. to the TUI
+    Channel(mpsc::UnboundedSender<IngestEvent>),
+}
+
+impl Progress {
+    fn status(&self, line: String) {
+        match self {
+            Progress::Terminal => println!("{line}"),
+            Progress::Channel(tx) => {
+                let _ = tx.send(IngestEvent::Status(line));
+            }
+        }
+    }
+
+    fn embedded(&self, done: usize, total: usize) {
+        if let Progress::Channel(tx) = self {
+            let _ = tx.send(IngestEvent::Embedded { done, total });
+        }
+    }
+
+    /// Per-chunk bar on the terminal, hidden otherwise
+    fn chunk_bar(&self, total: usize) -> ProgressBar {
+        if let Progress::Channel(_) = self {
+            return ProgressBar::hidden();
+        }
+        let pb = ProgressBar::new(total as u64);
+        pb.set_style(
+            ProgressStyle::with_template(
+                "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} chunks ({eta})",
+            )
+            .unwrap()
+            .progress_chars("=>-"),
+        );
+        pb
+    }
+}
+
+/// What `ingest_file` did with a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestOutcome {
+    /// Document was embedded and stored as this many chunks
+    Ingested(usize),
+    /// Same content is already indexed; nothing was embedded
+    Unchanged,
+    /// A different version is indexed and `replace` was not requested
+    AlreadyIndexed,
+}
+
+/// Modification time of a file in Unix seconds, if the platform reports one
+fn file_mtime(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// SHA-256 of the normalized document text, hex encoded
+fn content_hash(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+/// Trimmed, de-duplicated, sorted tags with empty entries dropped
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Mean-embedding similarity at which a new document is flagged as a likely
+/// duplicate of an indexed one (GHOST_DUPLICATE_DOC_THRESHOLD)
+const DEFAULT_DUPLICATE_DOC_THRESHOLD: f32 = 0.97;
+
+fn duplicate_doc_threshold() -> f32 {
+    std::env::var("GHOST_DUPLICATE_DOC_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_DUPLICATE_DOC_THRESHOLD)
+}
+
+/// Ingest a document: read, split, embed, and store.
+///
+/// Unchanged documents (same content hash as the indexed copy) are skipped
+/// before embedding. When a different version is indexed, its chunks are
+/// replaced if `replace` is set, otherwise the document is left alone.
+///
+/// A panic while ingesting (say, deep inside a parser) is returned as an error,
+/// so callers working through a batch can record it and carry on.
 pub async fn ingest_file(
     path: &Path,
     embedder: &Arc<Mutex<TextEmbedding>>,
     store: &mut db::VectorStore,
+    replace: bool,
+    tags: &[String],
+    progress: &Progress,
+) -> Result<IngestOutcome> {
+    AssertUnwindSafe(ingest(path, embedder, store, replace, tags, progress))
+        .catch_unwind()
+        .await
+        .unwrap_or_else(|panic| {
+            Err(anyhow!(
+                "Ingestion crashed: {}",
+                panic_message(panic.as_ref())
+            ))
+        })
+}
+
+async fn ingest(
+    path: &Path,
+    embedder: &Arc<Mutex<TextEmbedding>>,
+    store: &mut db::VectorStore,
+    replace: bool,
+    tags: &[String],
+    progress: &Progress,
+) -> Result<IngestOutcome> {
+    ensure_dimension(store)?;
+
+    let filename = document_name(path);
+    let tags = normalize_tags(tags);
+    let tags = tags.as_slice();
+
+    progress.status(format!("Reading: {filename}"));
+    let (text, page_starts) = read_normalized(path, progress).await?;
+
+    let hash = content_hash(&text);
+    let existing = db::count_by_filename(store, &filename).await?;
+    if existing > 0 {
+        if db::stored_content_hash(store, &filename).await?.as_deref() == Some(hash.as_str()) {
+            // Re-adding an unchanged document with --tag only retags it
+            if !tags.is_empty() && db::set_tags(store, &filename, tags).await? {
+                progress.status(format!("Updated tags for {filename}: {}", tags.join(", ")));
+            }
+            // Documents indexed before modification times were recorded get one now
+            if let Some(mtime) = file_mtime(path) {
+                if db::stored_field(store, &filename, MTIME_FIELD)
+                    .await?
+                    .is_none()
+                {
+                    db::set_fields(store, &filename, &[(MTIME_FIELD, serde_json::json!(mtime))])
+                        .await?;
+                }
+            }
+            return Ok(IngestOutcome::Unchanged);
+        }
+        if !replace {
+            return Ok(IngestOutcome::AlreadyIndexed);
+        }
+    }
+
+    // A replaced document keeps its tags unless new ones are given
+    let tags = if tags.is_empty() && existing > 0 {
+        db::stored_tags(store, &filename).await?
+    } else {
+        tags.to_vec()
+    };
+
+    let (chunk_size, chunk_overlap) = chunk_settings();
+    let chunks = split_text(&text, chunk_size, chunk_overlap)?;
+    let total_chunks = chunks.len();
+
+    if total_chunks == 0 {
+        bail!("No chunks produced from document");
+    }
+
+    progress.status(format!("Split into {total_chunks} chunks"));
+
+    let fields = DocumentFields {
+        filename: &filename,
+        content_hash: &hash,
+        text_len: text.len(),
+        tags: &tags,
+        model: model_name(&embedding_model()?.0),
+        headings: HeadingStyle::for_path(path),
+        mtime: file_mtime(path),
+    };
+    let all_points =
+        embed_chunks(embedder, &text, &page_starts, &chunks, 0, &fields, progress).await?;
+
+    // Swap out the previous version only once the new one is fully embedded. Chunk
+    // ids are deterministic, so the upsert alone would overwrite matching chunks;
+    // deleting first also drops chunks past the new end and legacy random-id points.
+    if existing > 0 {
+        db::delete_by_filename(store, &filename).await?;
+        progress.status(format!(
+            "Replaced {existing} existing chunks for {filename}"
+        ));
+    }
+
+    // Upsert all points
+    db::upsert_points(store, all_points).await?;
+    verify_stored(store, &filename, 0, total_chunks, progress).await?;
+    progress.status(format!(
+        "Ingested {total_chunks} chunks from {filename} ({} tokens est.)",
+        text_cleaner::estimate_tokens(&text)
+    ));
+
+    // Warn (but don't refuse) when this looks like another version of an indexed document
+    for (other, similarity) in
+        db::similar_documents(store, &filename, duplicate_doc_threshold()).await?
+    {
+        progress.status(format!(
+            "Warning: {filename} is very similar to already indexed {other} \
+             (similarity {similarity:.3}); delete one if they are versions of the same document"
+        ));
+    }
+
+    Ok(IngestOutcome::Ingested(total_chunks))
+}
+
+/// Re-read the saved store file and check that `expected` new chunks of
+/// `filename` landed on top of the `before` it already had, warning loudly if
+/// any went missing (e.g. a partial write, or another process saving over it)
+async fn verify_stored(
+    store: &db::VectorStore,
+    filename: &str,
+    before: usize,
+    expected: usize,
+    progress: &Progress,
 ) -> Result<usize> {
-    let filename = path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+    let stored = db::count_saved_by_filename(store, filename)
+        .await?
+        .saturating_sub(before);
+    if stored < expected {
+        progress.status(format!(
+            "Warning: only {stored} of {expected} chunks of {filename} were stored; \
+             {} went missing. Re-add it with --recreate",
+            expected - stored
+        ));
+    }
+    Ok(stored)
+}
+
+/// Payload fields shared by every chunk of a document
+struct DocumentFields<'a> {
+    filename: &'a str,
+    content_hash: &'a str,
+    /// Byte length of the normalized text, so `append_file` can find the indexed prefix
+    text_len: usize,
+    tags: &'a [String],
+    model: &'a str,
+    /// How section headings are marked in this document's format
+    headings: HeadingStyle,
+    /// File modification time, for recency weighting at query time
+    mtime: Option<u64>,
+}
 
-    println!("Reading: {filename}");
-    let raw_text = read_document(path)?;
-    let text = text_cleaner::normalize(&raw_text);
+/// Embed `chunks` (byte offsets into `text`) in batches and build their points,
+/// numbering them from `first_index`
+async fn embed_chunks(
+    embedder: &Arc<Mutex<TextEmbedding>>,
+    text: &str,
+    page_starts: &[(usize, usize)],
+    chunks: &[(usize, &str)],
+    first_index: usize,
+    doc: &DocumentFields<'_>,
+    progress: &Progress,
+) -> Result<Vec<db::Point>> {
+    let total_chunks = chunks.len();
+    let pb = progress.chunk_bar(total_chunks);
+    progress.embedded(0, total_chunks);
+
+    // Heading positions for section metadata
+    let headings = text_cleaner::heading_offsets(text, doc.headings);
+
+    let batch_size = embed_batch_size()?;
+    let mut points = Vec::with_capacity(total_chunks);
+
+    for (batch_idx, batch) in chunks.chunks(batch_size).enumerate() {
+        let texts: Vec<String> = batch.iter().map(|(_, s)| s.to_string()).collect();
+        let embeddings = embed_texts(embedder, texts.clone()).await?;
+
+        for (i, (chunk_text, embedding)) in texts.into_iter().zip(embeddings).enumerate() {
+            let chunk_index = first_index + batch_idx * batch_size + i;
+            let (offset, _) = batch[i];
+
+            let mut payload: HashMap<String, Value> = [
+                ("filename".to_string(), Value::from(doc.filename)),
+                (
+                    "section".to_string(),
+                    Value::String(section_at(&headings, offset)),
+                ),
+                ("chunk_index".to_string(), serde_json::json!(chunk_index)),
+                ("text".to_string(), Value::String(chunk_text)),
+                ("content_hash".to_string(), Value::from(doc.content_hash)),
+                (TEXT_LEN_FIELD.to_string(), serde_json::json!(doc.text_len)),
+                (MODEL_FIELD.to_string(), Value::from(doc.model)),
+            ]
+            .into_iter()
+            .collect();
+            if let Some(page) = page_at(page_starts, offset) {
+                payload.insert("page".to_string(), serde_json::json!(page));
+            }
+            if !doc.tags.is_empty() {
+                payload.insert("tags".to_string(), serde_json::json!(doc.tags));
+            }
+            if let Some(mtime) = doc.mtime {
+                payload.insert(MTIME_FIELD.to_string(), serde_json::json!(mtime));
+            }
+
+            points.push(db::Point {
+                id: db::chunk_id(doc.filename, chunk_index),
+                vector: embedding,
+                payload,
+            });
+            pb.inc(1);
+        }
+        progress.embedded(points.len(), total_chunks);
+    }
+
+    pb.finish_with_message("Done");
+    Ok(points)
+}
+
+/// Index only what was added to the end of an already indexed document, e.g. a
+/// journal that grows daily.
+///
+/// The indexed prefix is recognised by its stored length and content hash. If it
+/// no longer matches (the file was edited, or indexed before lengths were
+/// recorded), the whole document is re-indexed with a warning.
+pub async fn append_file(
+    path: &Path,
+    embedder: &Arc<Mutex<TextEmbedding>>,
+    store: &mut db::VectorStore,
+    progress: &Progress,
+) -> Result<IngestOutcome> {
+    ensure_dimension(store)?;
+
+    let filename = document_name(path);
+    let existing = db::count_by_filename(store, &filename).await?;
+    if existing == 0 {
+        return ingest_file(path, embedder, store, false, &[], progress).await;
+    }
+
+    progress.status(format!("Reading: {filename}"));
+    let (text, page_starts) = read_normalized(path, progress).await?;
+
+    let stored_hash = db::stored_content_hash(store, &filename).await?;
+    let Some(indexed_len) = db::stored_field(store, &filename, TEXT_LEN_FIELD)
+        .await?
+        .and_then(|v| v.as_u64())
+        .map(|len| len as usize)
+    else {
+        progress.status(format!(
+            "Warning: {filename} was indexed without the length --append needs; re-indexing it once"
+        ));
+        return ingest_file(path, embedder, store, true, &[], progress).await;
+    };
+    let prefix_unchanged = text
+        .get(..indexed_len)
+        .is_some_and(|prefix| Some(content_hash(prefix)) == stored_hash);
+    if !prefix_unchanged {
+        progress.status(format!(
+            "Warning: the indexed part of {filename} has changed; re-indexing the whole document"
+        ));
+        return ingest_file(path, embedder, store, true, &[], progress).await;
+    }
+
+    let (chunk_size, chunk_overlap) = chunk_settings();
+    let chunks: Vec<(usize, &str)> = split_text(&text[indexed_len..], chunk_size, chunk_overlap)?
+        .into_iter()
+        .map(|(offset, chunk)| (indexed_len + offset, chunk))
+        .collect();
+    if chunks.is_empty() {
+        return Ok(IngestOutcome::Unchanged);
+    }
+    progress.status(format!(
+        "{} new chunks after the indexed part",
+        chunks.len()
+    ));
 
+    let hash = content_hash(&text);
+    let tags = db::stored_tags(store, &filename).await?;
+    let fields = DocumentFields {
+        filename: &filename,
+        content_hash: &hash,
+        text_len: text.len(),
+        tags: &tags,
+        model: model_name(&embedding_model()?.0),
+        headings: HeadingStyle::for_path(path),
+        mtime: file_mtime(path),
+    };
+    // Not `existing`: after `delete --chunk` the count no longer reaches the last index
+    let first_index = db::next_chunk_index(store, &filename).await?;
+    let points = embed_chunks(
+        embedder,
+        &text,
+        &page_starts,
+        &chunks,
+        first_index,
+        &fields,
+        progress,
+    )
+    .await?;
+    let appended = points.len();
+
+    // Earlier chunks now describe the longer document too; saved together with
+    // the new chunks, so a crash can't mark the tail indexed without them
+    db::append_points(
+        store,
+        &filename,
+        &[
+            ("content_hash", Value::from(hash.as_str())),
+            (TEXT_LEN_FIELD, serde_json::json!(text.len())),
+        ],
+        points,
+    )
+    .await?;
+    verify_stored(store, &filename, existing, appended, progress).await?;
+
+    progress.status(format!(
+        "Appended {appended} chunks to {filename} ({} chunks total)",
+        existing + appended
+    ));
+    Ok(IngestOutcome::Ingested(appended))
+}
+
+/// Normalized document text plus `(byte offset, page)` starts for paged formats
+async fn read_normalized(
+    path: &Path,
+    progress: &Progress,
+) -> Result<(String, Vec<(usize, usize)>)> {
+    let (text, page_starts) = match read_document(path, progress).await? {
+        DocumentText::Plain(raw) => (text_cleaner::normalize(&raw), Vec::new()),
+        DocumentText::Paged(pages) => join_pages(&pages),
+    };
     if text.is_empty() {
         bail!("Document is empty after normalization");
     }
+    Ok((text, page_starts))
+}
 
-    // Semantic split (configurable via GHOST_CHUNK_SIZE, default 2000 chars)
+/// Semantic split settings: (GHOST_CHUNK_SIZE, default 2000 chars; GHOST_CHUNK_OVERLAP, default 0)
+pub fn chunk_settings() -> (usize, usize) {
     let chunk_size: usize = std::env::var("GHOST_CHUNK_SIZE")
         .ok()
         .and_then(|v| v.parse().ok())
         .unwrap_or(2000);
-    let splitter = MarkdownSplitter::new(chunk_size);
-    let chunks: Vec<&str> = splitter.chunks(&text).collect();
-    let total_chunks = chunks.len();
+    let chunk_overlap: usize = std::env::var("GHOST_CHUNK_OVERLAP")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    (chunk_size, chunk_overlap)
+}
 
-    if total_chunks == 0 {
-        bail!("No chunks produced from document");
+/// Chunks embedded per model call (GHOST_EMBED_BATCH, default 32). Smaller
+/// batches keep CPU-only machines responsive; larger ones are faster on big hosts.
+const DEFAULT_EMBED_BATCH: usize = 32;
+
+pub fn embed_batch_size() -> Result<usize> {
+    parse_embed_batch(std::env::var("GHOST_EMBED_BATCH").ok().as_deref())
+}
+
+fn parse_embed_batch(value: Option<&str>) -> Result<usize> {
+    match value {
+        Some(v) if !v.is_empty() => match v.trim().parse::<usize>() {
+            Ok(n) if n >= 1 => Ok(n),
+            _ => bail!("GHOST_EMBED_BATCH must be a whole number of at least 1 (got '{v}')"),
+        },
+        _ => Ok(DEFAULT_EMBED_BATCH),
     }
+}
+
+/// One chunk as `add --dry-run` reports it
+pub struct ChunkPreview {
+    pub section: String,
+    pub page: Option<usize>,
+    pub chars: usize,
+    pub tokens: usize,
+}
+
+/// How a document would be split, without embedding or storing anything
+pub struct DocumentPreview {
+    pub filename: String,
+    pub tokens: usize,
+    pub chunks: Vec<ChunkPreview>,
+    /// Headings detected in the document, in order
+    pub sections: Vec<String>,
+}
+
+/// Read, normalize and split a document exactly as `ingest_file` would
+pub async fn preview_file(path: &Path) -> Result<DocumentPreview> {
+    let (text, page_starts) = read_normalized(path, &Progress::Terminal).await?;
+    let (chunk_size, chunk_overlap) = chunk_settings();
+    let headings = text_cleaner::heading_offsets(&text, HeadingStyle::for_path(path));
+
+    let chunks = split_text(&text, chunk_size, chunk_overlap)?
+        .into_iter()
+        .map(|(offset, chunk)| ChunkPreview {
+            section: section_at(&headings, offset),
+            page: page_at(&page_starts, offset),
+            chars: chunk.chars().count(),
+            tokens: text_cleaner::estimate_tokens(chunk),
+        })
+        .collect();
 
-    println!("Split into {total_chunks} chunks");
+    Ok(DocumentPreview {
+        filename: document_name(path),
+        tokens: text_cleaner::estimate_tokens(&text),
+        chunks,
+        sections: headings.into_iter().map(|(_, heading)| heading).collect(),
+    })
+}
+
+/// Result of re-embedding the whole store
+pub struct ReindexSummary {
+    pub documents: usize,
+    pub chunks: usize,
+    pub old_dims: Option<usize>,
+    pub new_dims: Option<usize>,
+}
 
-    // Progress bar
-    let pb = ProgressBar::new(total_chunks as u64);
+/// Re-embed every stored chunk with the current embedder, document by document.
+///
+/// The collection is rewritten in one go, so a model with a different vector
+/// dimension replaces every old vector rather than mixing sizes.
+pub async fn reindex(
+    embedder: &Arc<Mutex<TextEmbedding>>,
+    store: &mut db::VectorStore,
+) -> Result<ReindexSummary> {
+    let old_dims = store.points.first().map(|p| p.vector.len());
+
+    // Regroup chunks by filename, keeping their original order
+    let mut by_file: Vec<(String, Vec<db::Point>)> = Vec::new();
+    let mut slot: HashMap<String, usize> = HashMap::new();
+    for point in store.points.iter().cloned() {
+        let filename = point
+            .payload
+            .get("filename")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_string();
+        let idx = *slot.entry(filename.clone()).or_insert_with(|| {
+            by_file.push((filename, Vec::new()));
+            by_file.len() - 1
+        });
+        by_file[idx].1.push(point);
+    }
+    for (_, points) in &mut by_file {
+        points.sort_by_key(|p| p.payload.get("chunk_index").and_then(|v| v.as_u64()));
+    }
+
+    let total = store.points.len();
+    let pb = ProgressBar::new(total as u64);
     pb.set_style(
         ProgressStyle::with_template(
-            "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} chunks ({eta})",
+            "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} chunks {msg} ({eta})",
         )
         .unwrap()
         .progress_chars("=>-"),
     );
 
-    // Extract sections for metadata
-    let sections = text_cleaner::extract_markdown_sections(&text);
+    let model = Value::from(model_name(&embedding_model()?.0));
+    let batch_size = embed_batch_size()?;
+    let mut rebuilt = Vec::with_capacity(total);
+    for (filename, points) in &by_file {
+        pb.set_message(filename.clone());
+        for batch in points.chunks(batch_size) {
+            let texts: Vec<String> = batch
+                .iter()
+                .map(|p| {
+                    p.payload
+                        .get("text")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string()
+                })
+                .collect();
+            let embeddings = embed_texts(embedder, texts).await?;
+            for (point, vector) in batch.iter().zip(embeddings) {
+                let mut payload = point.payload.clone();
+                payload.insert(MODEL_FIELD.to_string(), model.clone());
+                // Also moves collections built with random ids onto derived ones
+                let id = db::derived_id(point, filename).unwrap_or_else(|| point.id.clone());
+                rebuilt.push(db::Point {
+                    id,
+                    vector,
+                    payload,
+                });
+                pb.inc(1);
+            }
+        }
+    }
 
-    // Process in batches of 32
-    let batch_size = 32;
-    let mut all_points = Vec::new();
+    let new_dims = rebuilt.first().map(|p| p.vector.len());
+    db::replace_all_points(store, rebuilt).await?;
+    pb.finish_with_message("Done");
 
-    for (batch_idx, batch) in chunks.chunks(batch_size).enumerate() {
-        let texts: Vec<String> = batch.iter().map(|s| s.to_string()).collect();
-        let embeddings = embed_texts(embedder, texts.clone()).await?;
+    Ok(ReindexSummary {
+        documents: by_file.len(),
+        chunks: total,
+        old_dims,
+        new_dims,
+    })
+}
 
-        for (i, (chunk_text, embedding)) in texts.iter().zip(embeddings.iter()).enumerate() {
-            let chunk_index = batch_idx * batch_size + i;
+/// Normalize each page and join them, recording `(byte offset, page number)` for every page start
+fn join_pages(pages: &[String]) -> (String, Vec<(usize, usize)>) {
+    let mut text = String::new();
+    let mut starts = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        let page = text_cleaner::normalize(page);
+        if page.is_empty() {
+            continue;
+        }
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        starts.push((text.len(), i + 1));
+        text.push_str(&page);
+    }
+    (text, starts)
+}
 
-            // Find the section this chunk belongs to
-            let section_name = find_section_for_chunk(chunk_text, &sections);
+/// Page on which the text at `offset` begins (None for unpaginated documents)
+fn page_at(page_starts: &[(usize, usize)], offset: usize) -> Option<usize> {
+    page_starts
+        .iter()
+        .take_while(|(start, _)| *start <= offset)
+        .last()
+        .map(|(_, page)| *page)
+}
 
-            let payload: HashMap<String, Value> = [
-                ("filename".to_string(), Value::String(filename.clone())),
-                ("section".to_string(), Value::String(section_name)),
-                ("chunk_index".to_string(), serde_json::json!(chunk_index)),
-                ("text".to_string(), Value::String(chunk_text.clone())),
-            ]
-            .into_iter()
-            .collect();
+/// Split markdown into `(byte offset, chunk)` pairs of at most `chunk_size` chars,
+/// neighbours sharing up to `overlap` chars
+fn split_text(text: &str, chunk_size: usize, overlap: usize) -> Result<Vec<(usize, &str)>> {
+    let config = ChunkConfig::new(chunk_size)
+        .with_overlap(overlap)
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "GHOST_CHUNK_OVERLAP ({overlap}) must be smaller than GHOST_CHUNK_SIZE ({chunk_size})"
+            )
+        })?;
+    Ok(MarkdownSplitter::new(config).chunk_indices(text).collect())
+}
 
-            let point = db::Point {
-                id: Uuid::new_v4().to_string(),
-                vector: embedding.clone(),
-                payload,
-            };
-            all_points.push(point);
-            pb.inc(1);
+/// Heading of the section in which the text at `offset` begins.
+/// Chunks come from the splitter with their byte offsets, so this is a binary search.
+fn section_at(headings: &[(usize, String)], offset: usize) -> String {
+    match headings.partition_point(|(start, _)| *start <= offset) {
+        0 => "(no heading)".to_string(),
+        i => headings[i - 1].1.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_util::TempDir;
+
+    #[tokio::test]
+    async fn test_verify_stored_warns_on_missing_chunks() {
+        let point = |filename: &str, chunk_index: usize| db::Point {
+            id: db::chunk_id(filename, chunk_index),
+            vector: vec![1.0, 0.0],
+            payload: [("filename".to_string(), Value::from(filename))]
+                .into_iter()
+                .collect(),
+        };
+        let dir = TempDir::new();
+        let mut store = db::VectorStore::new(dir.join("store.json"), Vec::new());
+        db::upsert_points(
+            &mut store,
+            vec![point("a.md", 0), point("a.md", 1), point("b.md", 0)],
+        )
+        .await
+        .unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let progress = Progress::Channel(tx);
+
+        assert_eq!(
+            verify_stored(&store, "a.md", 0, 2, &progress)
+                .await
+                .unwrap(),
+            2
+        );
+        assert!(rx.try_recv().is_err());
+
+        assert_eq!(
+            verify_stored(&store, "a.md", 1, 3, &progress)
+                .await
+                .unwrap(),
+            1
+        );
+        match rx.try_recv() {
+            Ok(IngestEvent::Status(line)) => assert!(line.contains("only 1 of 3 chunks")),
+            other => panic!("expected a warning, got {other:?}"),
         }
+
+        // A save that lost chunks shows up even though memory still has them
+        let saved = serde_json::to_string(&store.points[..1]).unwrap();
+        std::fs::write(dir.join("store.json"), saved).unwrap();
+        assert_eq!(
+            verify_stored(&store, "a.md", 0, 2, &progress)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(rx.try_recv().is_ok());
     }
 
-    // Upsert all points
-    db::upsert_points(store, all_points).await?;
+    #[test]
+    fn test_parse_embed_batch() {
+        assert_eq!(parse_embed_batch(None).unwrap(), 32);
+        assert_eq!(parse_embed_batch(Some("")).unwrap(), 32);
+        assert_eq!(parse_embed_batch(Some(" 8 ")).unwrap(), 8);
+        assert!(parse_embed_batch(Some("0")).is_err());
+        assert!(parse_embed_batch(Some("lots")).is_err());
+    }
 
-    pb.finish_with_message("Done");
-    println!(
-        "Ingested {total_chunks} chunks from {filename} ({} tokens est.)",
-        text_cleaner::estimate_tokens(&text)
-    );
+    #[test]
+    fn test_guard_extractor_turns_panics_into_errors() {
+        let err = guard_extractor("EPUB", || -> Result<String> { panic!("bad zip") }).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to extract EPUB: the parser crashed on this file (bad zip)"
+        );
+        assert_eq!(
+            guard_extractor("EPUB", || Ok("text".to_string())).unwrap(),
+            "text"
+        );
+    }
 
-    Ok(total_chunks)
-}
+    #[test]
+    fn test_parse_embed_model() {
+        assert_eq!(
+            parse_embed_model("BGE-small-en-v1.5").unwrap(),
+            EmbeddingModel::BGESmallENV15
+        );
+        let err = parse_embed_model("word2vec").unwrap_err().to_string();
+        assert!(err.contains("multilingual-e5-small"));
+        for (_, model) in EMBED_MODELS {
+            assert!(TextEmbedding::get_model_info(model).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_section_at_follows_chunk_offsets() {
+        let doc = "Preamble text.\n\n# Intro\nIntro body.\n\n## Setup\n\
+                   Setup body that repeats Intro body.\n\n## Usage\nUsage body.";
+        let headings = text_cleaner::heading_offsets(doc, HeadingStyle::Markdown);
+        let chunks = split_text(doc, 40, 0).unwrap();
+        let attributed: Vec<(String, &str)> = chunks
+            .iter()
+            .map(|(offset, chunk)| (section_at(&headings, *offset), *chunk))
+            .collect();
+
+        let section_of = |needle: &str| {
+            attributed
+                .iter()
+                .find(|(_, chunk)| chunk.contains(needle))
+                .map(|(section, _)| section.as_str())
+                .unwrap()
+        };
+        assert_eq!(section_of("Preamble"), "(no heading)");
+        assert_eq!(section_of("Intro body."), "Intro");
+        // Text repeated from an earlier section still lands where it actually is
+        assert_eq!(section_of("repeats"), "Setup");
+        assert_eq!(section_of("Usage body"), "Usage");
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let tags = vec![
+            " project-x ".to_string(),
+            "notes".to_string(),
+            String::new(),
+            "project-x".to_string(),
+        ];
+        assert_eq!(normalize_tags(&tags), vec!["notes", "project-x"]);
+    }
+
+    #[test]
+    fn test_chunk_overlap_shares_content() {
+        let text = "alpha bravo charlie delta echo foxtrot golf hotel india juliet \
+                    kilo lima mike november oscar papa quebec romeo sierra tango";
+
+        let plain = split_text(text, 40, 0).unwrap();
+        let overlapped = split_text(text, 40, 15).unwrap();
+        assert!(overlapped.len() >= plain.len());
 
-/// Find which markdown section a chunk belongs to
-fn find_section_for_chunk(chunk: &str, sections: &[(String, String)]) -> String {
-    for (heading, content) in sections {
-        if content.contains(chunk)
-            || chunk.contains(content.get(..50.min(content.len())).unwrap_or(content))
-        {
-            return heading.clone();
+        for pair in overlapped.windows(2) {
+            let (prev, next) = (pair[0].1, pair[1].1);
+            let first_word = next.split_whitespace().next().unwrap();
+            assert!(
+                prev.contains(first_word),
+                "expected {next:?} to start with content from {prev:?}"
+            );
         }
     }
-    "(unknown)".to_string()
+
+    #[test]
+    fn test_pdf_pages_map_chunks_to_starting_page() {
+        let pages = vec![
+            "Intro page".to_string(),
+            "   ".to_string(),
+            "Third page text".to_string(),
+        ];
+        let (text, starts) = join_pages(&pages);
+        assert_eq!(text, "Intro page\n\nThird page text");
+        assert_eq!(page_at(&starts, 0), Some(1));
+        assert_eq!(page_at(&starts, 5), Some(1));
+        assert_eq!(page_at(&starts, text.find("Third").unwrap()), Some(3));
+        assert_eq!(page_at(&[], 10), None);
+    }
+
+    #[test]
+    fn test_content_hash_is_stable() {
+        assert_eq!(content_hash("same text"), content_hash("same text"));
+        assert_ne!(content_hash("same text"), content_hash("same text!"));
+        assert_eq!(content_hash("").len(), 64);
+    }
+
+    #[test]
+    fn test_chunk_overlap_must_be_smaller_than_size() {
+        assert!(split_text("some text", 10, 10).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_preview_file_reports_chunks_and_sections() {
+        let dir = TempDir::new();
+        let path = dir.join("notes.md");
+        std::fs::write(&path, "# Intro\n\nHello there.\n\n## Usage\n\nRun it.\n").unwrap();
+
+        let preview = preview_file(&path).await.unwrap();
+        assert_eq!(preview.filename, "notes.md");
+        assert_eq!(preview.sections, ["Intro", "Usage"]);
+        assert!(!preview.chunks.is_empty());
+        assert_eq!(preview.chunks[0].section, "Intro");
+        assert!(preview
+            .chunks
+            .iter()
+            .all(|c| c.page.is_none() && c.tokens > 0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_documents_survives_symlink_loop() {
+        let dir = TempDir::new();
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join("docs/a.md"), "# A\n").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("docs/loop")).unwrap();
+
+        let found = collect_documents(&dir).unwrap();
+        assert_eq!(found, [dir.join("docs/a.md")]);
+    }
+
+    #[test]
+    fn test_check_model() {
+        let current = ("multilingual-e5-small", 384);
+        assert!(check_model(None, current).is_ok());
+        assert!(check_model(Some((Some("multilingual-e5-small"), 384)), current).is_ok());
+        // Chunks indexed before the model name was recorded: dimension only
+        assert!(check_model(Some((None, 384)), current).is_ok());
+
+        let err = check_model(Some((Some("bge-small-en-v1.5"), 384)), current).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Collection was built with model bge-small-en-v1.5 (dim 384), current model is multilingual-e5-small (dim 384)"));
+        assert!(check_model(Some((None, 768)), current).is_err());
+    }
 }
@@ -1,3 +1,5 @@
 pub mod distill;
+pub mod embed_cache;
+pub mod grounding;
 pub mod ingest;
 pub mod provider;
@@ -1,12 +1,21 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use ollama_rs::error::OllamaError;
 use ollama_rs::generation::completion::request::GenerationRequest;
 use ollama_rs::generation::options::GenerationOptions;
+use ollama_rs::models::pull::PullModelStatusStream;
 use ollama_rs::Ollama;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashSet;
+use std::future::Future;
 use std::io::Write;
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
 
-const SYSTEM_PROMPT: &str = r#"You are Ghost Librarian, a precise research assistant. Answer questions using ONLY the provided context. Follow these rules strictly:
+pub const SYSTEM_PROMPT: &str = r#"You are Ghost Librarian, a precise research assistant. Answer questions using ONLY the provided context. Follow these rules strictly:
 
 1. Base your answer exclusively on the provided context
 2. If the context doesn't contain enough information, say so clearly
@@ -14,8 +23,67 @@ const SYSTEM_PROMPT: &str = r#"You are Ghost Librarian, a precise research assis
 4. Be concise and factual — avoid speculation
 5. If the context contains conflicting information, acknowledge it"#;
 
+/// The system prompt to answer with: GHOST_SYSTEM_PROMPT, else the contents of
+/// GHOST_SYSTEM_PROMPT_FILE, else the built-in `SYSTEM_PROMPT`
+pub fn system_prompt() -> Result<String> {
+    if let Some(prompt) = std::env::var("GHOST_SYSTEM_PROMPT")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+    {
+        return Ok(prompt);
+    }
+    match std::env::var("GHOST_SYSTEM_PROMPT_FILE") {
+        Ok(path) if !path.is_empty() => std::fs::read_to_string(&path)
+            .map(|p| p.trim().to_string())
+            .with_context(|| format!("Cannot read GHOST_SYSTEM_PROMPT_FILE {path}")),
+        _ => Ok(SYSTEM_PROMPT.to_string()),
+    }
+}
+
 const DEFAULT_MODEL: &str = "llama3";
 
+const DEFAULT_TEMPERATURE: f32 = 0.1;
+
+const DEFAULT_MAX_TOKENS: i32 = 1024;
+
+/// Sampling settings for answer generation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerationParams {
+    pub temperature: f32,
+    pub max_tokens: i32,
+}
+
+impl GenerationParams {
+    /// Defaults overridden by GHOST_TEMPERATURE / GHOST_MAX_TOKENS
+    pub fn from_env() -> Self {
+        Self {
+            temperature: std::env::var("GHOST_TEMPERATURE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_TEMPERATURE),
+            max_tokens: std::env::var("GHOST_MAX_TOKENS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_TOKENS),
+        }
+    }
+
+    /// Environment settings with explicit (CLI or request) values taking precedence
+    pub fn with_overrides(temperature: Option<f32>, max_tokens: Option<i32>) -> Self {
+        let env = Self::from_env();
+        Self {
+            temperature: temperature.unwrap_or(env.temperature),
+            max_tokens: max_tokens.unwrap_or(env.max_tokens),
+        }
+    }
+
+    fn options(&self) -> GenerationOptions {
+        GenerationOptions::default()
+            .temperature(self.temperature)
+            .num_predict(self.max_tokens)
+    }
+}
+
 fn ollama_host() -> String {
     std::env::var("GHOST_OLLAMA_HOST").unwrap_or_else(|_| "http://localhost".to_string())
 }
@@ -27,6 +95,71 @@ fn ollama_port() -> u16 {
         .unwrap_or(11434)
 }
 
+/// Retries after a transient Ollama failure (GHOST_OLLAMA_RETRIES)
+const DEFAULT_OLLAMA_RETRIES: u32 = 2;
+
+/// Delay before the first retry; doubled for each one after
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+fn ollama_retries() -> u32 {
+    std::env::var("GHOST_OLLAMA_RETRIES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_OLLAMA_RETRIES)
+}
+
+/// Error bodies of 5xx responses: Ollama's own (busy, crashed or stuck model
+/// runner) and a reverse proxy's default pages
+const SERVER_ERROR_BODIES: &[&str] = &[
+    "server busy",
+    "try again",
+    "internal server error",
+    "bad gateway",
+    "service unavailable",
+    "gateway time",
+    "runner process has terminated",
+    "unexpectedly stopped",
+    "timed out waiting for llama runner",
+];
+
+/// Connection failures, timeouts and server errors are worth retrying;
+/// anything else (unknown model, bad request) would only fail again
+fn is_transient(err: &OllamaError) -> bool {
+    match err {
+        OllamaError::ReqwestError(e) => e.is_connect() || e.is_timeout(),
+        // ollama-rs turns any error status into `Other(body)` and drops the
+        // status, so 5xx responses are recognised by their body. Ollama always
+        // explains a 4xx, so an empty body comes from something in between.
+        OllamaError::Other(body) => {
+            let body = body.to_lowercase();
+            body.trim().is_empty() || SERVER_ERROR_BODIES.iter().any(|s| body.contains(s))
+        }
+        _ => false,
+    }
+}
+
+/// Run `op`, retrying up to `retries` times with exponential backoff while `transient` holds
+async fn retry_with_backoff<T, E, Fut>(
+    retries: u32,
+    base_delay: Duration,
+    transient: impl Fn(&E) -> bool,
+    mut op: impl FnMut() -> Fut,
+) -> std::result::Result<T, E>
+where
+    Fut: Future<Output = std::result::Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Err(e) if attempt < retries && transient(&e) => {
+                tokio::time::sleep(base_delay * 2u32.pow(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
 fn default_model() -> String {
     std::env::var("GHOST_MODEL").unwrap_or_else(|_| DEFAULT_MODEL.to_string())
 }
@@ -35,13 +168,16 @@ fn create_ollama() -> Ollama {
     Ollama::new(ollama_host(), ollama_port())
 }
 
+/// Assemble the user prompt exactly as it is sent to the model
+pub fn build_prompt(query: &str, context: &str) -> String {
+    format!(
+        "CONTEXT:\n{context}\n\n---\nQUESTION: {query}\n\nProvide a precise answer based only on the context above."
+    )
+}
+
 /// Check if Ollama is running and accessible
 pub async fn health_check() -> Result<bool> {
-    let ollama = create_ollama();
-    match ollama.list_local_models().await {
-        Ok(_) => Ok(true),
-        Err(_) => Ok(false),
-    }
+    Ok(OllamaBackend.health_check().await)
 }
 
 /// List available models from Ollama
@@ -54,50 +190,249 @@ pub async fn list_models() -> Result<Vec<String>> {
     Ok(models.into_iter().map(|m| m.name).collect())
 }
 
-/// Generate a response using Ollama with streaming output
-pub async fn ask_with_context(query: &str, context: &str, model: Option<&str>) -> Result<String> {
+/// Start pulling a model from the Ollama registry, streaming status updates
+pub async fn pull_model_stream(name: &str) -> Result<PullModelStatusStream> {
     let ollama = create_ollama();
-    let model_name = model.unwrap_or(&default_model()).to_string();
+    ollama
+        .pull_model_stream(name.to_string(), false)
+        .await
+        .with_context(|| format!("Failed to pull model '{name}'"))
+}
 
+/// Ask `backend` to condense `text` to roughly `words` words (non-streaming)
+pub async fn summarize(
+    backend: &dyn Backend,
+    text: &str,
+    words: usize,
+    model: Option<&str>,
+) -> Result<String> {
     let prompt = format!(
-        "CONTEXT:\n{context}\n\n---\nQUESTION: {query}\n\nProvide a precise answer based only on the context above."
+        "Summarize the following passage in at most {words} words. Keep names, numbers, \
+         and technical terms exactly as written. Reply with the summary only.\n\n{text}"
     );
+    let params = GenerationParams {
+        temperature: 0.0,
+        // About 1.3 tokens per English word, plus slack for the model's wording
+        max_tokens: (words * 2 + 16).min(i32::MAX as usize) as i32,
+    };
+    let summary = backend
+        .complete(prompt, model.map(String::from), params)
+        .await
+        .context("Failed to summarize chunk")?;
+    Ok(summary.trim().to_string())
+}
 
-    let request = GenerationRequest::new(model_name, prompt)
-        .system(SYSTEM_PROMPT.to_string())
-        .options(
-            GenerationOptions::default()
-                .temperature(0.1)
-                .num_predict(1024),
-        );
+/// Longest reply `expand_query` lets the model write, in tokens
+const EXPANSION_MAX_TOKENS: i32 = 96;
 
-    let mut stream = ollama
-        .generate_stream(request)
-        .await
-        .context("Failed to connect to Ollama. Is it running? (ollama serve)")?;
+/// Longest `expand_query` waits for the model before giving up
+const EXPANSION_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Ask the configured backend for up to `n` rephrasings of `query`
+/// (non-streaming), for widening retrieval. The reply is capped in length and time.
+pub async fn expand_query(query: &str, n: usize, model: Option<&str>) -> Result<Vec<String>> {
+    let prompt = format!(
+        "Rewrite the search query below in {n} different ways, using synonyms and \
+         closely related terms. Reply with one rewrite per line and nothing else.\n\n\
+         Query: {query}"
+    );
+    let params = GenerationParams {
+        temperature: 0.3,
+        max_tokens: EXPANSION_MAX_TOKENS,
+    };
+    let backend = backend();
+    let reply = tokio::time::timeout(
+        EXPANSION_TIMEOUT,
+        backend.complete(prompt, model.map(String::from), params),
+    )
+    .await
+    .context("Query expansion timed out")??;
+    Ok(parse_expansions(&reply, query, n))
+}
+
+/// The distinct rephrasings in a model reply, one per line, with list markers
+/// stripped and anything repeating the original query dropped
+fn parse_expansions(reply: &str, query: &str, n: usize) -> Vec<String> {
+    let mut seen = HashSet::from([query.trim().to_lowercase()]);
+    reply
+        .lines()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches(|c: char| c.is_ascii_digit() || "-*•.)".contains(c))
+                .trim()
+                .trim_matches('"')
+                .trim()
+        })
+        .filter(|line| !line.is_empty() && seen.insert(line.to_lowercase()))
+        .map(str::to_string)
+        .take(n)
+        .collect()
+}
+
+/// Generate a response with the configured backend, printing tokens as they stream in
+pub async fn ask_with_context(
+    query: &str,
+    context: &str,
+    model: Option<&str>,
+    params: GenerationParams,
+) -> Result<Answer> {
+    collect_answer(
+        query,
+        context,
+        model,
+        params,
+        true,
+        CancellationToken::new(),
+    )
+    .await
+}
+
+/// Like `ask_with_context`, but stops generating as soon as `cancel` fires
+/// (e.g. on Ctrl+C) and returns what arrived so far, marked `interrupted`
+pub async fn ask_with_context_until(
+    query: &str,
+    context: &str,
+    model: Option<&str>,
+    params: GenerationParams,
+    cancel: CancellationToken,
+) -> Result<Answer> {
+    collect_answer(query, context, model, params, true, cancel).await
+}
+
+/// Generate a complete response without printing anything
+pub async fn answer_with_context(
+    query: &str,
+    context: &str,
+    model: Option<&str>,
+    params: GenerationParams,
+) -> Result<Answer> {
+    collect_answer(
+        query,
+        context,
+        model,
+        params,
+        false,
+        CancellationToken::new(),
+    )
+    .await
+}
+
+async fn collect_answer(
+    query: &str,
+    context: &str,
+    model: Option<&str>,
+    params: GenerationParams,
+    echo: bool,
+    cancel: CancellationToken,
+) -> Result<Answer> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(ask_with_context_stream(
+        query.to_string(),
+        context.to_string(),
+        model.map(String::from),
+        params,
+        tx,
+        cancel.clone(),
+    ));
 
     let mut full_response = String::new();
+    let mut usage = None;
 
-    while let Some(Ok(responses)) = stream.next().await {
-        for response in responses {
-            print!("{}", response.response);
-            let _ = std::io::stdout().flush();
-            full_response.push_str(&response.response);
+    loop {
+        let event = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => break,
+            event = rx.recv() => event,
+        };
+        let Some(event) = event else { break };
+        match event {
+            StreamEvent::Token(text) => {
+                if echo {
+                    print!("{text}");
+                    let _ = std::io::stdout().flush();
+                }
+                full_response.push_str(&text);
+            }
+            StreamEvent::Usage(u) => usage = Some(u),
+            StreamEvent::Done => break,
+            StreamEvent::Error(e) => bail!(e),
         }
     }
-    println!();
+    if echo {
+        println!();
+    }
+
+    Ok(Answer {
+        text: full_response,
+        usage,
+        interrupted: cancel.is_cancelled(),
+    })
+}
 
-    Ok(full_response)
+/// A complete answer and, when the backend reports it, what it cost
+#[derive(Debug, Clone)]
+pub struct Answer {
+    pub text: String,
+    pub usage: Option<Usage>,
+    /// Generation was cancelled before the model finished; `text` is partial
+    pub interrupted: bool,
+}
+
+/// Token counts and timing reported by the backend for one answer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Usage {
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+    /// Whole generation, including prompt evaluation and model loading
+    pub total_ms: Option<u64>,
+}
+
+impl Usage {
+    /// e.g. "812 prompt + 143 answer tokens, 4.2s"; None if nothing was reported
+    pub fn summary(&self) -> Option<String> {
+        let tokens = match (self.prompt_tokens, self.completion_tokens) {
+            (Some(p), Some(c)) => Some(format!("{p} prompt + {c} answer tokens")),
+            (Some(p), None) => Some(format!("{p} prompt tokens")),
+            (None, Some(c)) => Some(format!("{c} answer tokens")),
+            (None, None) => None,
+        };
+        let time = self
+            .total_ms
+            .map(|ms| format!("{:.1}s", ms as f64 / 1000.0));
+        match (tokens, time) {
+            (Some(t), Some(d)) => Some(format!("{t}, {d}")),
+            (t, d) => t.or(d),
+        }
+    }
 }
 
 /// Events sent through the streaming channel
 #[derive(Debug)]
 pub enum StreamEvent {
     Token(String),
+    /// Sent just before `Done` by backends that report it
+    Usage(Usage),
     Done,
     Error(String),
 }
 
+/// Ollama's reply for a model that hasn't been pulled, e.g.
+/// `model "llama9" not found, try pulling it first`
+pub fn is_model_not_found(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("model") && message.contains("not found")
+}
+
+/// Whether GHOST_AUTO_PULL=1 allows pulling a missing model without asking
+pub fn auto_pull() -> bool {
+    std::env::var("GHOST_AUTO_PULL").as_deref() == Ok("1")
+}
+
+/// Ollama reports untagged models as `name:latest`
+pub fn is_same_model(listed: &str, wanted: &str) -> bool {
+    listed == wanted || listed.strip_suffix(":latest") == Some(wanted)
+}
+
 /// Return the active model name (from env or default)
 pub fn active_model_name(model_override: Option<&str>) -> String {
     model_override
@@ -105,46 +440,661 @@ pub fn active_model_name(model_override: Option<&str>) -> String {
         .unwrap_or_else(default_model)
 }
 
+/// A chat backend that answers a query from distilled context
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// Human-readable name used in status and error messages
+    fn name(&self) -> String;
+
+    /// What to do when `health_check` fails
+    fn unreachable_hint(&self) -> &'static str;
+
+    /// Whether the backend is reachable
+    async fn health_check(&self) -> bool;
+
+    /// Names of the models the backend can answer with
+    async fn list_models(&self) -> Result<Vec<String>>;
+
+    /// Stream the answer through `tx`, finishing with `Done` or `Error`
+    async fn ask_with_context_stream(
+        &self,
+        query: String,
+        context: String,
+        model: Option<String>,
+        params: GenerationParams,
+        tx: mpsc::UnboundedSender<StreamEvent>,
+    );
+
+    /// Complete a bare `prompt` (no system prompt or context) and return the whole reply
+    async fn complete(
+        &self,
+        prompt: String,
+        model: Option<String>,
+        params: GenerationParams,
+    ) -> Result<String>;
+}
+
+/// Whether GHOST_BACKEND selects the OpenAI-compatible backend
+pub fn is_openai_backend() -> bool {
+    std::env::var("GHOST_BACKEND").as_deref() == Ok("openai")
+}
+
+/// The backend selected by GHOST_BACKEND (`ollama` unless set to `openai`)
+pub fn backend() -> Box<dyn Backend> {
+    if is_openai_backend() {
+        Box::new(OpenAiBackend::from_env())
+    } else {
+        Box::new(OllamaBackend)
+    }
+}
+
+/// Whole-answer limit (GHOST_LLM_TIMEOUT_SECS)
+const DEFAULT_LLM_TIMEOUT_SECS: u64 = 300;
+
+/// Longest silence between two tokens (GHOST_LLM_IDLE_TIMEOUT_SECS)
+const DEFAULT_LLM_IDLE_TIMEOUT_SECS: u64 = 30;
+
+/// Limits that stop a hung generation; `None` means no limit
+#[derive(Debug, Clone, Copy)]
+pub struct LlmTimeouts {
+    pub total: Option<Duration>,
+    /// Only applies once the first token has arrived, so model loading isn't cut short
+    pub idle: Option<Duration>,
+}
+
+impl LlmTimeouts {
+    /// Read from GHOST_LLM_TIMEOUT_SECS / GHOST_LLM_IDLE_TIMEOUT_SECS (`0` = no limit)
+    pub fn from_env() -> Self {
+        let secs = |var: &str, default: u64| {
+            let secs = std::env::var(var)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default);
+            (secs > 0).then(|| Duration::from_secs(secs))
+        };
+        Self {
+            total: secs("GHOST_LLM_TIMEOUT_SECS", DEFAULT_LLM_TIMEOUT_SECS),
+            idle: secs("GHOST_LLM_IDLE_TIMEOUT_SECS", DEFAULT_LLM_IDLE_TIMEOUT_SECS),
+        }
+    }
+}
+
 /// Channel-based streaming: spawnable with owned parameters.
-/// Sends tokens through `tx` as they arrive from Ollama.
+/// Sends tokens through `tx` as they arrive from the configured backend.
+/// Cancelling `cancel` drops the backend stream and sends nothing further;
+/// exceeding the `LlmTimeouts` drops it and sends `Error`.
+#[tracing::instrument(
+    level = "debug",
+    skip_all,
+    fields(model = model.as_deref().unwrap_or("default"), context_chars = context.len())
+)]
 pub async fn ask_with_context_stream(
     query: String,
     context: String,
     model: Option<String>,
+    params: GenerationParams,
     tx: mpsc::UnboundedSender<StreamEvent>,
+    cancel: CancellationToken,
 ) {
-    let ollama = create_ollama();
-    let model_name = model.unwrap_or_else(default_model);
-
-    let prompt = format!(
-        "CONTEXT:\n{context}\n\n---\nQUESTION: {query}\n\nProvide a precise answer based only on the context above."
+    let backend = backend();
+    tracing::debug!(backend = %backend.name(), ?params, "generation started");
+    let started = std::time::Instant::now();
+    let (inner_tx, inner_rx) = mpsc::unbounded_channel();
+    let generation = backend.ask_with_context_stream(query, context, model, params, inner_tx);
+    forward_with_timeouts(generation, inner_rx, tx, cancel, LlmTimeouts::from_env()).await;
+    tracing::debug!(
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "generation finished"
     );
+}
 
-    let request = GenerationRequest::new(model_name, prompt)
-        .system(SYSTEM_PROMPT.to_string())
-        .options(
-            GenerationOptions::default()
-                .temperature(0.1)
-                .num_predict(1024),
-        );
-
-    let stream_result = ollama.generate_stream(request).await;
+/// Drive `generation`, relaying its events from `rx` to `tx` until it finishes,
+/// is cancelled, or runs out of time
+async fn forward_with_timeouts(
+    generation: impl Future<Output = ()>,
+    mut rx: mpsc::UnboundedReceiver<StreamEvent>,
+    tx: mpsc::UnboundedSender<StreamEvent>,
+    cancel: CancellationToken,
+    timeouts: LlmTimeouts,
+) {
+    tokio::pin!(generation);
+    let deadline = tokio::time::sleep(timeouts.total.unwrap_or(Duration::ZERO));
+    tokio::pin!(deadline);
+    let mut generating = true;
+    let mut started = false;
 
-    match stream_result {
-        Ok(mut stream) => {
-            while let Some(Ok(responses)) = stream.next().await {
-                for response in responses {
-                    if tx.send(StreamEvent::Token(response.response)).is_err() {
+    loop {
+        let idle = timeouts.idle.filter(|_| started);
+        let next = async {
+            match idle {
+                Some(limit) => tokio::time::timeout(limit, rx.recv()).await.ok(),
+                None => Some(rx.recv().await),
+            }
+        };
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = &mut deadline, if timeouts.total.is_some() => {
+                let secs = timeouts.total.unwrap_or_default().as_secs();
+                let _ = tx.send(StreamEvent::Error(format!(
+                    "Generation timed out after {secs}s (GHOST_LLM_TIMEOUT_SECS)"
+                )));
+                return;
+            }
+            // The backend closes its sender when done; keep relaying what it queued
+            _ = &mut generation, if generating => generating = false,
+            event = next => match event {
+                None => {
+                    let secs = idle.unwrap_or_default().as_secs();
+                    let _ = tx.send(StreamEvent::Error(format!(
+                        "No output from the model for {secs}s (GHOST_LLM_IDLE_TIMEOUT_SECS)"
+                    )));
+                    return;
+                }
+                Some(None) => return,
+                Some(Some(event)) => {
+                    let last = matches!(event, StreamEvent::Done | StreamEvent::Error(_));
+                    if !started {
+                        tracing::debug!("first event from the model");
+                    }
+                    started = true;
+                    if tx.send(event).is_err() || last {
                         return;
                     }
                 }
+            },
+        }
+    }
+}
+
+/// Local Ollama server (GHOST_OLLAMA_HOST / GHOST_OLLAMA_PORT)
+pub struct OllamaBackend;
+
+#[async_trait]
+impl Backend for OllamaBackend {
+    fn name(&self) -> String {
+        format!("Ollama ({}:{})", ollama_host(), ollama_port())
+    }
+
+    fn unreachable_hint(&self) -> &'static str {
+        "Start it with: ollama serve"
+    }
+
+    async fn health_check(&self) -> bool {
+        create_ollama().list_local_models().await.is_ok()
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        list_models().await
+    }
+
+    async fn ask_with_context_stream(
+        &self,
+        query: String,
+        context: String,
+        model: Option<String>,
+        params: GenerationParams,
+        tx: mpsc::UnboundedSender<StreamEvent>,
+    ) {
+        let ollama = create_ollama();
+        let model_name = model.unwrap_or_else(default_model);
+
+        let prompt = build_prompt(&query, &context);
+        let system = match system_prompt() {
+            Ok(system) => system,
+            Err(e) => {
+                let _ = tx.send(StreamEvent::Error(format!("{e:#}")));
+                return;
+            }
+        };
+
+        let request = GenerationRequest::new(model_name.clone(), prompt)
+            .system(system)
+            .options(params.options());
+
+        let stream_result =
+            retry_with_backoff(ollama_retries(), RETRY_BASE_DELAY, is_transient, || {
+                ollama.generate_stream(request.clone())
+            })
+            .await;
+
+        match stream_result {
+            Ok(mut stream) => {
+                let mut usage = None;
+                while let Some(Ok(responses)) = stream.next().await {
+                    for response in responses {
+                        // The final item carries Ollama's counters
+                        if response.done {
+                            usage = Some(Usage {
+                                prompt_tokens: response.prompt_eval_count.map(u64::from),
+                                completion_tokens: response.eval_count.map(u64::from),
+                                total_ms: response.total_duration.map(|ns| ns / 1_000_000),
+                            });
+                        }
+                        if tx.send(StreamEvent::Token(response.response)).is_err() {
+                            return;
+                        }
+                    }
+                }
+                if let Some(usage) = usage {
+                    let _ = tx.send(StreamEvent::Usage(usage));
+                }
+                let _ = tx.send(StreamEvent::Done);
+            }
+            Err(OllamaError::Other(message)) if is_model_not_found(&message) => {
+                let _ = tx.send(StreamEvent::Error(format!(
+                    "Model '{model_name}' is not pulled. Run: ollama pull {model_name}"
+                )));
+            }
+            Err(e) => {
+                let _ = tx.send(StreamEvent::Error(format!(
+                    "Failed to connect to Ollama: {e}. Is it running? (ollama serve)"
+                )));
+            }
+        }
+    }
+
+    async fn complete(
+        &self,
+        prompt: String,
+        model: Option<String>,
+        params: GenerationParams,
+    ) -> Result<String> {
+        let ollama = create_ollama();
+        let model_name = model.unwrap_or_else(default_model);
+        let request = GenerationRequest::new(model_name.clone(), prompt).options(params.options());
+
+        match retry_with_backoff(ollama_retries(), RETRY_BASE_DELAY, is_transient, || {
+            ollama.generate(request.clone())
+        })
+        .await
+        {
+            Ok(response) => Ok(response.response),
+            Err(OllamaError::Other(message)) if is_model_not_found(&message) => {
+                bail!("Model '{model_name}' is not pulled. Run: ollama pull {model_name}")
+            }
+            Err(e) => bail!("Failed to connect to Ollama: {e}. Is it running? (ollama serve)"),
+        }
+    }
+}
+
+const DEFAULT_OPENAI_BASE_URL: &str = "http://localhost:8000/v1";
+
+/// OpenAI-compatible chat completions endpoint, e.g. vLLM or llama.cpp server
+/// (GHOST_OPENAI_BASE_URL / GHOST_OPENAI_API_KEY)
+pub struct OpenAiBackend {
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl OpenAiBackend {
+    pub fn from_env() -> Self {
+        let base_url = std::env::var("GHOST_OPENAI_BASE_URL")
+            .unwrap_or_else(|_| DEFAULT_OPENAI_BASE_URL.to_string());
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: std::env::var("GHOST_OPENAI_API_KEY")
+                .ok()
+                .filter(|k| !k.is_empty()),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let req = self
+            .client
+            .request(method, format!("{}{path}", self.base_url));
+        match &self.api_key {
+            Some(key) => req.bearer_auth(key),
+            None => req,
+        }
+    }
+
+    async fn stream_chat(
+        &self,
+        body: serde_json::Value,
+        tx: &mpsc::UnboundedSender<StreamEvent>,
+    ) -> Result<()> {
+        let response = self
+            .request(reqwest::Method::POST, "/chat/completions")
+            .json(&body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.base_url))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let detail = response.text().await.unwrap_or_default();
+            bail!("{} returned {status}: {}", self.base_url, detail.trim());
+        }
+
+        // Server-sent events: `data: {...}` lines, terminated by `data: [DONE]`
+        let mut bytes = response.bytes_stream();
+        let mut buffer = String::new();
+        while let Some(chunk) = bytes.next().await {
+            let chunk = chunk.context("Connection dropped while streaming")?;
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+            while let Some(newline) = buffer.find('\n') {
+                let line: String = buffer.drain(..=newline).collect();
+                match parse_sse_line(&line)? {
+                    SseLine::Delta(text) => {
+                        if tx.send(StreamEvent::Token(text)).is_err() {
+                            return Ok(());
+                        }
+                    }
+                    SseLine::Done => return Ok(()),
+                    SseLine::Skip => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Backend for OpenAiBackend {
+    fn name(&self) -> String {
+        format!("OpenAI-compatible endpoint ({})", self.base_url)
+    }
+
+    fn unreachable_hint(&self) -> &'static str {
+        "Check GHOST_OPENAI_BASE_URL and GHOST_OPENAI_API_KEY"
+    }
+
+    async fn health_check(&self) -> bool {
+        self.request(reqwest::Method::GET, "/models")
+            .send()
+            .await
+            .is_ok_and(|r| r.status().is_success())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let response: serde_json::Value = self
+            .request(reqwest::Method::GET, "/models")
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.base_url))?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Malformed /models response")?;
+        Ok(response["data"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m["id"].as_str().map(String::from))
+            .collect())
+    }
+
+    async fn ask_with_context_stream(
+        &self,
+        query: String,
+        context: String,
+        model: Option<String>,
+        params: GenerationParams,
+        tx: mpsc::UnboundedSender<StreamEvent>,
+    ) {
+        let system = match system_prompt() {
+            Ok(system) => system,
+            Err(e) => {
+                let _ = tx.send(StreamEvent::Error(format!("{e:#}")));
+                return;
+            }
+        };
+        let body = json!({
+            "model": model.unwrap_or_else(default_model),
+            "stream": true,
+            "temperature": params.temperature,
+            "max_tokens": params.max_tokens,
+            "messages": [
+                { "role": "system", "content": system },
+                { "role": "user", "content": build_prompt(&query, &context) },
+            ],
+        });
+
+        match self.stream_chat(body, &tx).await {
+            Ok(()) => {
+                let _ = tx.send(StreamEvent::Done);
+            }
+            Err(e) => {
+                let _ = tx.send(StreamEvent::Error(format!("{e:#}")));
             }
-            let _ = tx.send(StreamEvent::Done);
         }
-        Err(e) => {
-            let _ = tx.send(StreamEvent::Error(format!(
-                "Failed to connect to Ollama: {e}"
-            )));
+    }
+
+    async fn complete(
+        &self,
+        prompt: String,
+        model: Option<String>,
+        params: GenerationParams,
+    ) -> Result<String> {
+        let body = json!({
+            "model": model.unwrap_or_else(default_model),
+            "stream": false,
+            "temperature": params.temperature,
+            "max_tokens": params.max_tokens,
+            "messages": [{ "role": "user", "content": prompt }],
+        });
+        let response = self
+            .request(reqwest::Method::POST, "/chat/completions")
+            .json(&body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.base_url))?;
+        let status = response.status();
+        if !status.is_success() {
+            let detail = response.text().await.unwrap_or_default();
+            bail!("{} returned {status}: {}", self.base_url, detail.trim());
         }
+        let reply: serde_json::Value = response
+            .json()
+            .await
+            .context("Malformed chat completion response")?;
+        reply
+            .pointer("/choices/0/message/content")
+            .and_then(|c| c.as_str())
+            .map(String::from)
+            .context("Chat completion response has no message content")
+    }
+}
+
+/// One line of an OpenAI streaming response
+#[derive(Debug, PartialEq)]
+enum SseLine {
+    Delta(String),
+    Done,
+    Skip,
+}
+
+fn parse_sse_line(line: &str) -> Result<SseLine> {
+    let Some(data) = line.trim().strip_prefix("data:") else {
+        // Blank separators, comments and `event:` lines carry no tokens
+        return Ok(SseLine::Skip);
+    };
+    let data = data.trim();
+    if data == "[DONE]" {
+        return Ok(SseLine::Done);
+    }
+
+    let event: serde_json::Value =
+        serde_json::from_str(data).context("Malformed streaming response")?;
+    if let Some(message) = event.pointer("/error/message").and_then(|m| m.as_str()) {
+        bail!("{message}");
+    }
+    match event
+        .pointer("/choices/0/delta/content")
+        .and_then(|c| c.as_str())
+    {
+        Some(text) if !text.is_empty() => Ok(SseLine::Delta(text.to_string())),
+        _ => Ok(SseLine::Skip),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_usage_summary_omits_missing_fields() {
+        let full = Usage {
+            prompt_tokens: Some(812),
+            completion_tokens: Some(143),
+            total_ms: Some(4210),
+        };
+        assert_eq!(
+            full.summary().as_deref(),
+            Some("812 prompt + 143 answer tokens, 4.2s")
+        );
+        let timing_only = Usage {
+            total_ms: Some(1500),
+            ..Usage::default()
+        };
+        assert_eq!(timing_only.summary().as_deref(), Some("1.5s"));
+        assert_eq!(Usage::default().summary(), None);
+    }
+
+    #[tokio::test]
+    async fn test_forward_with_timeouts_stops_stalled_stream() {
+        let timeouts = LlmTimeouts {
+            total: Some(Duration::from_secs(5)),
+            idle: Some(Duration::from_millis(50)),
+        };
+        let (inner_tx, inner_rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        // One token, then silence
+        let generation = async move {
+            let _ = inner_tx.send(StreamEvent::Token("partial".into()));
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        };
+        forward_with_timeouts(generation, inner_rx, tx, CancellationToken::new(), timeouts).await;
+
+        assert!(matches!(rx.recv().await, Some(StreamEvent::Token(t)) if t == "partial"));
+        assert!(matches!(rx.recv().await, Some(StreamEvent::Error(e)) if e.contains("IDLE")));
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_forward_with_timeouts_relays_finished_stream() {
+        let timeouts = LlmTimeouts {
+            total: Some(Duration::from_millis(50)),
+            idle: None,
+        };
+        let (inner_tx, inner_rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let generation = async move {
+            let _ = inner_tx.send(StreamEvent::Token("a".into()));
+            let _ = inner_tx.send(StreamEvent::Done);
+        };
+        forward_with_timeouts(generation, inner_rx, tx, CancellationToken::new(), timeouts).await;
+
+        assert!(matches!(rx.recv().await, Some(StreamEvent::Token(_))));
+        assert!(matches!(rx.recv().await, Some(StreamEvent::Done)));
+    }
+
+    #[test]
+    fn test_parse_expansions_strips_markers_and_repeats() {
+        let reply = "1. rust borrow rules\n- Rust Borrow Rules\n\n* \"ownership in rust\"\n\
+                     How does the borrow checker work?\nlifetimes";
+        assert_eq!(
+            parse_expansions(reply, "How does the borrow checker work?", 3),
+            vec!["rust borrow rules", "ownership in rust", "lifetimes"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_retries_proxy_server_errors() {
+        // What ollama-rs returns for a 502 from nginx in front of Ollama
+        let bad_gateway = "<html>\r\n<head><title>502 Bad Gateway</title></head>\r\n\
+                           <body>\r\n<center><h1>502 Bad Gateway</h1></center>\r\n</body>\r\n</html>";
+        let calls = Cell::new(0);
+        let result = retry_with_backoff(2, Duration::ZERO, is_transient, || {
+            calls.set(calls.get() + 1);
+            let n = calls.get();
+            async move {
+                if n < 3 {
+                    Err(OllamaError::Other(bad_gateway.to_string()))
+                } else {
+                    Ok(n)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 3);
+
+        calls.set(0);
+        let result = retry_with_backoff(2, Duration::ZERO, is_transient, || {
+            calls.set(calls.get() + 1);
+            async { Err::<(), _>(OllamaError::Other(String::new())) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_only_retries_transient_errors() {
+        let calls = Cell::new(0);
+        let flaky = || {
+            calls.set(calls.get() + 1);
+            let n = calls.get();
+            async move {
+                if n < 3 {
+                    Err("busy")
+                } else {
+                    Ok(n)
+                }
+            }
+        };
+        let result = retry_with_backoff(2, Duration::ZERO, |e| *e == "busy", flaky).await;
+        assert_eq!(result, Ok(3));
+
+        calls.set(0);
+        let result: std::result::Result<(), _> = retry_with_backoff(
+            5,
+            Duration::ZERO,
+            |e| *e == "busy",
+            || {
+                calls.set(calls.get() + 1);
+                async { Err("model not found") }
+            },
+        )
+        .await;
+        assert_eq!(result, Err("model not found"));
+        assert_eq!(calls.get(), 1);
+
+        assert!(is_transient(&OllamaError::Other(
+            "server busy, please try again.  maximum pending requests exceeded".into()
+        )));
+        assert!(is_transient(&OllamaError::Other(
+            r#"{"error":"llama runner process has terminated: signal: killed"}"#.into()
+        )));
+        assert!(is_model_not_found(
+            "model \"llama9\" not found, try pulling it first"
+        ));
+        assert!(!is_transient(&OllamaError::Other(
+            "model \"llama9\" not found, try pulling it first".into()
+        )));
+    }
+
+    #[test]
+    fn test_parse_sse_delta_and_done() {
+        let line = r#"data: {"choices":[{"index":0,"delta":{"content":"Hello"}}]}"#;
+        assert_eq!(
+            parse_sse_line(line).unwrap(),
+            SseLine::Delta("Hello".into())
+        );
+        assert_eq!(parse_sse_line("data: [DONE]\n").unwrap(), SseLine::Done);
+        assert_eq!(parse_sse_line(": keep-alive").unwrap(), SseLine::Skip);
+        let role_only = r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#;
+        assert_eq!(parse_sse_line(role_only).unwrap(), SseLine::Skip);
+    }
+
+    #[test]
+    fn test_parse_sse_error_event() {
+        let line = r#"data: {"error":{"message":"model not found"}}"#;
+        let err = parse_sse_line(line).unwrap_err();
+        assert!(err.to_string().contains("model not found"));
     }
 }
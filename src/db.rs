@@ -1,13 +1,22 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
 
 pub const COLLECTION_NAME: &str = "ghost_library";
 
+/// Active collection: GHOST_COLLECTION, or `ghost_library` by default
+pub fn collection_name() -> String {
+    std::env::var("GHOST_COLLECTION")
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| COLLECTION_NAME.to_string())
+}
+
 // ── Data types ──────────────────────────────────────────────────
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -18,17 +27,24 @@ pub struct Point {
 }
 
 /// File-backed vector store.  All data lives in a single JSON file
-/// under `~/.ghost-librarian/store.json`.  For typical document
+/// under `~/.ghost-librarian/store.json` (or `<collection>.json` when
+/// `GHOST_COLLECTION` selects another library).  For typical document
 /// collections (< 50 k chunks) this is more than fast enough and
 /// removes the need for any external database.
 pub struct VectorStore {
     path: PathBuf,
     pub points: Vec<Point>,
+    /// Positions in `points` per `filename` payload, so per-document
+    /// lookups don't scan the whole collection
+    by_filename: HashMap<String, Vec<usize>>,
+    /// Collection this store was opened as
+    collection: String,
 }
 
 // ── Paths ───────────────────────────────────────────────────────
 
-fn data_dir() -> PathBuf {
+/// Directory holding the store and other per-user state (GHOST_DATA_DIR)
+pub fn data_dir() -> PathBuf {
     if let Ok(dir) = std::env::var("GHOST_DATA_DIR") {
         PathBuf::from(dir)
     } else if let Ok(home) = std::env::var("HOME") {
@@ -38,22 +54,84 @@ fn data_dir() -> PathBuf {
     }
 }
 
-fn store_path() -> PathBuf {
-    data_dir().join("store.json")
+/// File stem of the default collection's store, kept from before named collections
+const DEFAULT_STORE_STEM: &str = "store";
+
+/// The default collection keeps its original `store.json`; others get `<name>.json`
+fn store_path(name: &str) -> Result<PathBuf> {
+    if name == COLLECTION_NAME {
+        return Ok(data_dir().join(format!("{DEFAULT_STORE_STEM}.json")));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("Invalid collection name '{name}': use letters, digits, '-' or '_'");
+    }
+    // Case-insensitive filesystems would map `Store` to `store.json` too
+    if name.eq_ignore_ascii_case(DEFAULT_STORE_STEM) {
+        bail!(
+            "Collection name '{name}' is reserved: its file would be the default \
+             collection's {DEFAULT_STORE_STEM}.json. Pick another name"
+        );
+    }
+    Ok(data_dir().join(format!("{name}.json")))
 }
 
 // ── VectorStore impl ────────────────────────────────────────────
 
 impl VectorStore {
-    fn open() -> Result<Self> {
-        let path = store_path();
+    pub(crate) fn new(path: PathBuf, points: Vec<Point>) -> Self {
+        let mut store = Self {
+            path,
+            points,
+            by_filename: HashMap::new(),
+            collection: COLLECTION_NAME.to_string(),
+        };
+        store.rebuild_filename_index();
+        store
+    }
+
+    fn open(name: &str) -> Result<Self> {
+        let path = store_path(name)?;
         let points = if path.exists() {
-            let data = fs::read_to_string(&path).context("Failed to read vector store")?;
-            serde_json::from_str(&data).context("Failed to parse vector store")?
+            read_points(&path)?
         } else {
             Vec::new()
         };
-        Ok(Self { path, points })
+        let mut store = Self::new(path, points);
+        store.collection = name.to_string();
+        Ok(store)
+    }
+
+    pub fn collection(&self) -> &str {
+        &self.collection
+    }
+
+    /// Must be called whenever points are removed or reordered
+    fn rebuild_filename_index(&mut self) {
+        self.by_filename.clear();
+        self.index_points_from(0);
+    }
+
+    /// Index points appended at `start..`
+    fn index_points_from(&mut self, start: usize) {
+        for (i, point) in self.points.iter().enumerate().skip(start) {
+            if let Some(name) = point.payload.get("filename").and_then(|v| v.as_str()) {
+                self.by_filename
+                    .entry(name.to_string())
+                    .or_default()
+                    .push(i);
+            }
+        }
+    }
+
+    fn points_for(&self, filename: &str) -> impl Iterator<Item = &Point> {
+        self.by_filename
+            .get(filename)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.points[i])
     }
 
     fn save(&self) -> Result<()> {
@@ -66,43 +144,234 @@ impl VectorStore {
     }
 }
 
+fn read_points(path: &Path) -> Result<Vec<Point>> {
+    let data = fs::read_to_string(path).context("Failed to read vector store")?;
+    serde_json::from_str(&data).context("Failed to parse vector store")
+}
+
 // ── Public API (kept async for call-site compatibility) ─────────
 
 pub async fn open_store() -> Result<VectorStore> {
-    VectorStore::open()
+    VectorStore::open(&collection_name())
+}
+
+/// Open a named collection for reading; unlike `open_store`, a collection that
+/// was never written to is an error rather than an empty store
+pub async fn open_collection(name: &str) -> Result<VectorStore> {
+    if !store_path(name)?.exists() {
+        bail!("Collection '{name}' does not exist");
+    }
+    VectorStore::open(name)
 }
 
+/// Namespace for chunk ids, so they can't collide with other UUIDv5 schemes
+const CHUNK_ID_NAMESPACE: uuid::Uuid =
+    uuid::Uuid::from_u128(0x6c1e_5a0d_3f4b_4e2a_9d7c_21b8_f0e4_a935);
+
+/// Deterministic point id for a chunk: a UUIDv5 over `filename#chunk_index`, so
+/// re-ingesting a document overwrites its points instead of duplicating them
+pub fn chunk_id(filename: &str, chunk_index: usize) -> String {
+    uuid::Uuid::new_v5(
+        &CHUNK_ID_NAMESPACE,
+        format!("{filename}#{chunk_index}").as_bytes(),
+    )
+    .to_string()
+}
+
+/// The id a stored point should have under `filename`, if it records its chunk index
+pub fn derived_id(point: &Point, filename: &str) -> Option<String> {
+    let index = point.payload.get("chunk_index")?.as_u64()?;
+    Some(chunk_id(filename, index as usize))
+}
+
+/// Insert points, replacing any stored point that has the same id
 pub async fn upsert_points(store: &mut VectorStore, points: Vec<Point>) -> Result<()> {
+    insert_points(store, points);
+    store.save()
+}
+
+/// Set payload fields on the chunks already stored for `filename`, then add
+/// `points`, in a single write so the store never records one without the other
+pub async fn append_points(
+    store: &mut VectorStore,
+    filename: &str,
+    fields: &[(&str, Value)],
+    points: Vec<Point>,
+) -> Result<()> {
+    apply_fields(store, filename, fields);
+    insert_points(store, points);
+    store.save()
+}
+
+fn insert_points(store: &mut VectorStore, points: Vec<Point>) {
+    let before = store.points.len();
+    {
+        let ids: HashSet<&str> = points.iter().map(|p| p.id.as_str()).collect();
+        store.points.retain(|p| !ids.contains(p.id.as_str()));
+    }
+    let start = store.points.len();
     store.points.extend(points);
+    if start == before {
+        store.index_points_from(start);
+    } else {
+        store.rebuild_filename_index();
+    }
+}
+
+/// Replace the whole collection, e.g. after re-embedding with a different model
+pub async fn replace_all_points(store: &mut VectorStore, points: Vec<Point>) -> Result<()> {
+    store.points = points;
+    store.rebuild_filename_index();
     store.save()
 }
 
 /// Minimum cosine similarity to include in results.
 const MIN_SCORE: f64 = 0.1;
 
+/// Payload filter for `search_vectors` and `delete_points`: every condition must hold.
+///
+/// A condition matches when the payload field equals one of its values, or,
+/// for array fields such as `tags`, when the array contains one of them.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    must: Vec<(String, Vec<Value>)>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require `key` to match `value`
+    pub fn must(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.must.push((key.into(), vec![value.into()]));
+        self
+    }
+
+    /// Require `key` to match at least one of `values`
+    pub fn must_any<V: Into<Value>>(
+        mut self,
+        key: impl Into<String>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        self.must
+            .push((key.into(), values.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Only chunks of the document stored under `name`
+    pub fn filename(name: impl Into<String>) -> Self {
+        Self::new().must("filename", name.into())
+    }
+
+    fn matches(&self, payload: &HashMap<String, Value>) -> bool {
+        self.must
+            .iter()
+            .all(|(key, wanted)| match payload.get(key) {
+                Some(Value::Array(items)) => wanted.iter().any(|w| items.contains(w)),
+                Some(value) => wanted.contains(value),
+                None => false,
+            })
+    }
+}
+
+/// Vector similarity used by `search_vectors` (GHOST_DISTANCE). It is computed
+/// at query time, so switching metrics needs no re-indexing.
+///
+/// Every metric is scored on the cosine scale, which GHOST_MIN_SCORE,
+/// GHOST_LOW_RELEVANCE and the hybrid weights are calibrated for. On unit-length
+/// embeddings (what the bundled models produce) all three give the same scores
+/// and differ only for unnormalized vectors, where dot products are unbounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distance {
+    Cosine,
+    Dot,
+    Euclid,
+}
+
+impl Distance {
+    pub fn from_env() -> Result<Self> {
+        let Ok(value) = std::env::var("GHOST_DISTANCE") else {
+            return Ok(Distance::Cosine);
+        };
+        match value.trim().to_lowercase().as_str() {
+            "" | "cosine" => Ok(Distance::Cosine),
+            "dot" => Ok(Distance::Dot),
+            "euclid" | "euclidean" => Ok(Distance::Euclid),
+            other => bail!("Unknown GHOST_DISTANCE '{other}' (expected cosine, dot or euclid)"),
+        }
+    }
+
+    /// Higher is closer. Euclidean distance `d` maps to `1 - d² / 2`, which is
+    /// the cosine similarity of unit vectors `d` apart.
+    fn similarity(self, a: &[f32], b: &[f32]) -> f64 {
+        match self {
+            Distance::Cosine => cosine_similarity(a, b) as f64,
+            Distance::Dot => a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>() as f64,
+            Distance::Euclid => {
+                let d2: f32 = a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum();
+                1.0 - d2 as f64 / 2.0
+            }
+        }
+    }
+}
+
+/// `(score, point id, payload)` for one `search_vectors` result
+pub type SearchHit = (f64, String, HashMap<String, Value>);
+
+#[tracing::instrument(
+    level = "debug",
+    skip_all,
+    fields(collection = %store.collection(), points = store.points.len(), limit)
+)]
 pub async fn search_vectors(
     store: &VectorStore,
     query_vector: Vec<f32>,
     limit: u64,
-) -> Result<Vec<(f64, HashMap<String, Value>)>> {
-    // Parallel cosine similarity computation via rayon
+    filter: Option<&Filter>,
+) -> Result<Vec<SearchHit>> {
+    let distance = Distance::from_env()?;
+    let started = std::time::Instant::now();
+    // Parallel similarity computation via rayon
     let mut scored: Vec<(f64, usize)> = store
         .points
         .par_iter()
         .enumerate()
-        .map(|(i, p)| {
-            let sim = cosine_similarity(&query_vector, &p.vector) as f64;
-            (sim, i)
-        })
+        .filter(|(_, p)| filter.is_none_or(|f| f.matches(&p.payload)))
+        .map(|(i, p)| (distance.similarity(&query_vector, &p.vector), i))
         .filter(|(sim, _)| *sim > MIN_SCORE)
         .collect();
 
+    let above_min = scored.len();
     scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
     scored.truncate(limit as usize);
+    tracing::debug!(
+        above_min,
+        returned = scored.len(),
+        best = scored.first().map(|(s, _)| *s),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "vector search"
+    );
 
     Ok(scored
         .into_iter()
-        .map(|(score, i)| (score, store.points[i].payload.clone()))
+        .map(|(score, i)| {
+            let point = &store.points[i];
+            (score, point.id.clone(), point.payload.clone())
+        })
+        .collect())
+}
+
+/// Payloads of every point matching `filter`, for retrieval without a query vector
+pub async fn scroll_points(
+    store: &VectorStore,
+    filter: Option<&Filter>,
+) -> Result<Vec<HashMap<String, Value>>> {
+    Ok(store
+        .points
+        .iter()
+        .filter(|p| filter.is_none_or(|f| f.matches(&p.payload)))
+        .map(|p| p.payload.clone())
         .collect())
 }
 
@@ -111,31 +380,259 @@ pub async fn collection_info(store: &VectorStore) -> Result<(u64, u64)> {
 }
 
 pub async fn list_filenames(store: &VectorStore) -> Result<Vec<(String, usize)>> {
-    let mut filenames: HashMap<String, usize> = HashMap::new();
-    for point in &store.points {
-        if let Some(Value::String(name)) = point.payload.get("filename") {
-            *filenames.entry(name.clone()).or_insert(0) += 1;
-        }
-    }
-    let mut result: Vec<(String, usize)> = filenames.into_iter().collect();
+    let mut result: Vec<(String, usize)> = store
+        .by_filename
+        .iter()
+        .map(|(name, positions)| (name.clone(), positions.len()))
+        .collect();
     result.sort_by(|a, b| a.0.cmp(&b.0));
     Ok(result)
 }
 
+/// Content hash recorded when `filename` was ingested, if any
+pub async fn stored_content_hash(store: &VectorStore, filename: &str) -> Result<Option<String>> {
+    Ok(store
+        .points_for(filename)
+        .find_map(|p| p.payload.get("content_hash").and_then(|v| v.as_str()))
+        .map(String::from))
+}
+
+/// Tags recorded for `filename`, if any
+pub async fn stored_tags(store: &VectorStore, filename: &str) -> Result<Vec<String>> {
+    Ok(store
+        .points_for(filename)
+        .next()
+        .and_then(|p| p.payload.get("tags"))
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default())
+}
+
+/// Replace the tags on every chunk of `filename`; returns whether anything changed
+pub async fn set_tags(store: &mut VectorStore, filename: &str, tags: &[String]) -> Result<bool> {
+    let tags = Value::from(tags.to_vec());
+    let positions = store.by_filename.get(filename).cloned().unwrap_or_default();
+    let mut changed = false;
+    for i in positions {
+        let payload = &mut store.points[i].payload;
+        if payload.get("tags") != Some(&tags) {
+            payload.insert("tags".to_string(), tags.clone());
+            changed = true;
+        }
+    }
+    if changed {
+        store.save()?;
+    }
+    Ok(changed)
+}
+
+/// A payload field as recorded on the chunks of `filename`, if any
+pub async fn stored_field(store: &VectorStore, filename: &str, key: &str) -> Result<Option<Value>> {
+    Ok(store
+        .points_for(filename)
+        .find_map(|p| p.payload.get(key))
+        .cloned())
+}
+
+/// Index the next chunk appended to `filename` should get: one past the highest
+/// recorded `chunk_index`, so deleted chunks don't cause id reuse
+pub async fn next_chunk_index(store: &VectorStore, filename: &str) -> Result<usize> {
+    Ok(store
+        .points_for(filename)
+        .filter_map(|p| p.payload.get("chunk_index").and_then(Value::as_u64))
+        .max()
+        .map_or(0, |max| max as usize + 1))
+}
+
+/// Set payload fields on every chunk of `filename`
+pub async fn set_fields(
+    store: &mut VectorStore,
+    filename: &str,
+    fields: &[(&str, Value)],
+) -> Result<()> {
+    apply_fields(store, filename, fields);
+    store.save()
+}
+
+fn apply_fields(store: &mut VectorStore, filename: &str, fields: &[(&str, Value)]) {
+    let positions = store.by_filename.get(filename).cloned().unwrap_or_default();
+    for i in positions {
+        for (key, value) in fields {
+            store.points[i]
+                .payload
+                .insert(key.to_string(), value.clone());
+        }
+    }
+}
+
+/// Stored chunk text of every chunk of `filename`
+pub async fn chunk_texts<'a>(store: &'a VectorStore, filename: &str) -> Result<Vec<&'a str>> {
+    Ok(store
+        .points_for(filename)
+        .filter_map(|p| p.payload.get("text").and_then(|v| v.as_str()))
+        .collect())
+}
+
+/// Point every chunk of `old` at the name `new`, leaving vectors untouched;
+/// returns how many chunks were renamed
+pub async fn rename_document(store: &mut VectorStore, old: &str, new: &str) -> Result<usize> {
+    let Some(positions) = store.by_filename.get(old).cloned() else {
+        bail!("No indexed document named '{old}'");
+    };
+    if old == new {
+        return Ok(0);
+    }
+    if store.by_filename.contains_key(new) {
+        bail!("A document named '{new}' is already indexed");
+    }
+    for &i in &positions {
+        let point = &mut store.points[i];
+        point
+            .payload
+            .insert("filename".to_string(), Value::from(new));
+        // Ids derive from the filename, so they move with it
+        if let Some(id) = derived_id(point, new) {
+            point.id = id;
+        }
+    }
+    store.rebuild_filename_index();
+    store.save()?;
+    Ok(positions.len())
+}
+
+pub async fn count_by_filename(store: &VectorStore, filename: &str) -> Result<usize> {
+    Ok(store.by_filename.get(filename).map_or(0, Vec::len))
+}
+
+/// Chunks of `filename` in the store's file on disk, as opposed to the copy in
+/// memory: what the last save actually wrote
+pub async fn count_saved_by_filename(store: &VectorStore, filename: &str) -> Result<usize> {
+    Ok(read_points(&store.path)?
+        .iter()
+        .filter(|p| p.payload.get("filename").and_then(|v| v.as_str()) == Some(filename))
+        .count())
+}
+
 pub async fn delete_by_filename(store: &mut VectorStore, filename: &str) -> Result<u64> {
+    delete_points(store, &Filter::filename(filename)).await
+}
+
+/// Remove every point whose payload matches `filter`; returns how many were removed
+pub async fn delete_points(store: &mut VectorStore, filter: &Filter) -> Result<u64> {
     let before = store.points.len();
-    store
-        .points
-        .retain(|p| p.payload.get("filename").and_then(|v| v.as_str()) != Some(filename));
-    let deleted = (before - store.points.len()) as u64;
-    if deleted > 0 {
+    store.points.retain(|p| !filter.matches(&p.payload));
+    let removed = before - store.points.len();
+    if removed > 0 {
+        store.rebuild_filename_index();
         store.save()?;
     }
-    Ok(deleted)
+    Ok(removed as u64)
+}
+
+/// Remove every point from the collection; returns how many were removed
+pub async fn clear_collection(store: &mut VectorStore) -> Result<u64> {
+    let (removed, _) = collection_info(store).await?;
+    replace_all_points(store, Vec::new()).await?;
+    Ok(removed)
+}
+
+/// Write every point as one JSON object per line; returns the number written
+pub async fn export_jsonl(store: &VectorStore, path: &Path) -> Result<usize> {
+    let file =
+        fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    for point in &store.points {
+        serde_json::to_writer(&mut out, point).context("Failed to serialize point")?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(store.points.len())
+}
+
+/// Load points from a JSONL export, replacing any with the same id; returns the number read
+pub async fn import_jsonl(store: &mut VectorStore, path: &Path) -> Result<usize> {
+    let file =
+        fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let mut points: Vec<Point> = Vec::new();
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let point: Point = serde_json::from_str(&line)
+            .with_context(|| format!("{}:{}: not a valid point", path.display(), n + 1))?;
+        points.push(point);
+    }
+
+    // Vectors from a different embedding model can't be searched together
+    let expected_dim = store
+        .points
+        .first()
+        .or(points.first())
+        .map(|p| p.vector.len());
+    if let Some(dim) = expected_dim {
+        if let Some(point) = points.iter().find(|p| p.vector.len() != dim) {
+            bail!(
+                "Point {} has {} dimensions, but the collection uses {dim}. \
+                 Was the export made with a different embedding model?",
+                point.id,
+                point.vector.len()
+            );
+        }
+    }
+
+    let count = points.len();
+    upsert_points(store, points).await?;
+    Ok(count)
 }
 
 // ── Helpers ─────────────────────────────────────────────────────
 
+/// Element-wise mean of `vectors`, or `None` when there are none
+pub fn mean_vector<'a>(vectors: impl IntoIterator<Item = &'a [f32]>) -> Option<Vec<f32>> {
+    let mut sum: Vec<f32> = Vec::new();
+    let mut count = 0;
+    for vector in vectors {
+        if sum.is_empty() {
+            sum = vec![0.0; vector.len()];
+        }
+        for (s, v) in sum.iter_mut().zip(vector) {
+            *s += v;
+        }
+        count += 1;
+    }
+    if count == 0 {
+        return None;
+    }
+    sum.iter_mut().for_each(|s| *s /= count as f32);
+    Some(sum)
+}
+
+/// Documents other than `filename` whose mean chunk embedding has at least
+/// `threshold` cosine similarity to `filename`'s, most similar first
+pub async fn similar_documents(
+    store: &VectorStore,
+    filename: &str,
+    threshold: f32,
+) -> Result<Vec<(String, f32)>> {
+    let Some(target) = mean_vector(store.points_for(filename).map(|p| p.vector.as_slice())) else {
+        return Ok(Vec::new());
+    };
+    let mut similar: Vec<(String, f32)> = store
+        .by_filename
+        .keys()
+        .filter(|name| name.as_str() != filename)
+        .filter_map(|name| {
+            let mean = mean_vector(store.points_for(name).map(|p| p.vector.as_slice()))?;
+            let sim = cosine_similarity(&target, &mean);
+            (sim >= threshold).then(|| (name.clone(), sim))
+        })
+        .collect();
+    similar.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(similar)
+}
+
 fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() {
         return 0.0;
@@ -148,3 +645,256 @@ fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     }
     dot / (norm_a * norm_b)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_util::TempDir;
+
+    fn point(filename: &str, vector: Vec<f32>) -> Point {
+        Point {
+            id: uuid::Uuid::new_v4().to_string(),
+            vector,
+            payload: [
+                ("filename".to_string(), Value::from(filename)),
+                ("tags".to_string(), Value::from(vec!["draft"])),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn test_store_path_reserves_default_file() {
+        let default = store_path(COLLECTION_NAME).unwrap();
+        assert!(default.ends_with("store.json"));
+        assert!(store_path("work").unwrap().ends_with("work.json"));
+        assert!(store_path("store").is_err());
+        assert!(store_path("Store").is_err());
+        assert!(store_path("../x").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_vectors_filter() {
+        let store = VectorStore::new(
+            PathBuf::from("unused.json"),
+            vec![
+                point("a.md", vec![1.0, 0.0]),
+                point("b.md", vec![0.9, 0.1]),
+                point("a.md", vec![0.8, 0.2]),
+            ],
+        );
+
+        let all = search_vectors(&store, vec![1.0, 0.0], 10, None)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 3);
+
+        let only_b = Filter::filename("b.md");
+        let hits = search_vectors(&store, vec![1.0, 0.0], 10, Some(&only_b))
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].1, store.points[1].id);
+        assert_eq!(hits[0].2["filename"], "b.md");
+
+        let tagged = Filter::new().must("tags", "draft").must("filename", "a.md");
+        let hits = search_vectors(&store, vec![1.0, 0.0], 10, Some(&tagged))
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+
+        let either = Filter::new().must_any("filename", ["b.md", "c.md"]);
+        let hits = search_vectors(&store, vec![1.0, 0.0], 10, Some(&either))
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+
+        let missing = Filter::new().must("section", "Intro");
+        let hits = search_vectors(&store, vec![1.0, 0.0], 10, Some(&missing))
+            .await
+            .unwrap();
+        assert!(hits.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_export_import_roundtrip() {
+        let dir = TempDir::new();
+        let source = VectorStore::new(
+            dir.join("source.json"),
+            vec![point("a.md", vec![1.0, 0.0]), point("b.md", vec![0.0, 1.0])],
+        );
+        let export = dir.join("export.jsonl");
+
+        assert_eq!(export_jsonl(&source, &export).await.unwrap(), 2);
+
+        let mut target = VectorStore::new(dir.join("target.json"), Vec::new());
+        assert_eq!(import_jsonl(&mut target, &export).await.unwrap(), 2);
+        assert_eq!(
+            collection_info(&target).await.unwrap(),
+            collection_info(&source).await.unwrap()
+        );
+
+        // Importing the same file again replaces rather than duplicates
+        import_jsonl(&mut target, &export).await.unwrap();
+        assert_eq!(target.points.len(), 2);
+        assert_eq!(target.points[0].payload, source.points[0].payload);
+    }
+
+    #[tokio::test]
+    async fn test_filename_index_tracks_mutations() {
+        let dir = TempDir::new();
+        let mut store = VectorStore::new(
+            dir.join("store.json"),
+            vec![point("a.md", vec![1.0, 0.0]), point("b.md", vec![0.0, 1.0])],
+        );
+        upsert_points(&mut store, vec![point("a.md", vec![0.5, 0.5])])
+            .await
+            .unwrap();
+        assert_eq!(count_by_filename(&store, "a.md").await.unwrap(), 2);
+
+        assert_eq!(delete_by_filename(&mut store, "a.md").await.unwrap(), 2);
+        assert_eq!(delete_by_filename(&mut store, "a.md").await.unwrap(), 0);
+        assert_eq!(
+            list_filenames(&store).await.unwrap(),
+            vec![("b.md".to_string(), 1)]
+        );
+        assert_eq!(store.points_for("b.md").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_points_single_chunk() {
+        let dir = TempDir::new();
+        let mut points: Vec<Point> = (0..3).map(|_| point("a.md", vec![1.0, 0.0])).collect();
+        for (i, p) in points.iter_mut().enumerate() {
+            p.payload.insert("chunk_index".to_string(), Value::from(i));
+        }
+        let mut store = VectorStore::new(dir.join("store.json"), points);
+
+        let second = Filter::filename("a.md").must("chunk_index", 1);
+        assert_eq!(delete_points(&mut store, &second).await.unwrap(), 1);
+        assert_eq!(next_chunk_index(&store, "a.md").await.unwrap(), 3);
+        let last = Filter::filename("a.md").must("chunk_index", 2);
+        assert_eq!(delete_points(&mut store, &last).await.unwrap(), 1);
+        assert_eq!(next_chunk_index(&store, "a.md").await.unwrap(), 1);
+        assert_eq!(delete_points(&mut store, &second).await.unwrap(), 0);
+        assert_eq!(count_by_filename(&store, "a.md").await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_clear_collection() {
+        let dir = TempDir::new();
+        let mut store = VectorStore::new(
+            dir.join("store.json"),
+            vec![point("a.md", vec![1.0, 0.0]), point("b.md", vec![0.0, 1.0])],
+        );
+
+        assert_eq!(clear_collection(&mut store).await.unwrap(), 2);
+        assert!(list_filenames(&store).await.unwrap().is_empty());
+        assert_eq!(count_by_filename(&store, "a.md").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_rename_document() {
+        let dir = TempDir::new();
+        let mut store = VectorStore::new(
+            dir.join("store.json"),
+            vec![
+                point("a.md", vec![1.0, 0.0]),
+                point("b.md", vec![0.0, 1.0]),
+                point("a.md", vec![0.5, 0.5]),
+            ],
+        );
+
+        assert!(rename_document(&mut store, "missing.md", "c.md")
+            .await
+            .is_err());
+        assert!(rename_document(&mut store, "a.md", "b.md").await.is_err());
+        assert_eq!(
+            rename_document(&mut store, "a.md", "c.md").await.unwrap(),
+            2
+        );
+        assert_eq!(count_by_filename(&store, "a.md").await.unwrap(), 0);
+        assert_eq!(count_by_filename(&store, "c.md").await.unwrap(), 2);
+        assert_eq!(store.points[2].vector, vec![0.5, 0.5]);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_replaces_same_ids() {
+        let dir = TempDir::new();
+        let mut store =
+            VectorStore::new(dir.join("store.json"), vec![point("b.md", vec![0.0, 1.0])]);
+        let chunk = |i: usize, vector| {
+            let mut p = point("a.md", vector);
+            p.id = chunk_id("a.md", i);
+            p.payload.insert("chunk_index".to_string(), Value::from(i));
+            p
+        };
+        assert_eq!(chunk_id("a.md", 0), chunk_id("a.md", 0));
+        assert_ne!(chunk_id("a.md", 0), chunk_id("a.md", 1));
+
+        upsert_points(
+            &mut store,
+            vec![chunk(0, vec![1.0, 0.0]), chunk(1, vec![1.0, 0.0])],
+        )
+        .await
+        .unwrap();
+        upsert_points(
+            &mut store,
+            vec![chunk(0, vec![0.5, 0.5]), chunk(1, vec![0.5, 0.5])],
+        )
+        .await
+        .unwrap();
+        assert_eq!(store.points.len(), 3);
+        assert_eq!(count_by_filename(&store, "a.md").await.unwrap(), 2);
+        assert!(store.points_for("a.md").all(|p| p.vector == vec![0.5, 0.5]));
+
+        // Renaming re-derives the ids from the new filename
+        rename_document(&mut store, "a.md", "c.md").await.unwrap();
+        let ids: HashSet<String> = store.points_for("c.md").map(|p| p.id.clone()).collect();
+        assert_eq!(
+            ids,
+            HashSet::from([chunk_id("c.md", 0), chunk_id("c.md", 1)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_similar_documents() {
+        let store = VectorStore::new(
+            PathBuf::from("unused.json"),
+            vec![
+                point("draft.md", vec![1.0, 0.0]),
+                point("draft.md", vec![0.8, 0.2]),
+                point("final.md", vec![0.9, 0.1]),
+                point("other.md", vec![0.0, 1.0]),
+            ],
+        );
+
+        let similar = similar_documents(&store, "draft.md", 0.95).await.unwrap();
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].0, "final.md");
+        assert!((similar[0].1 - 1.0).abs() < 1e-6);
+        assert!(similar_documents(&store, "missing.md", 0.95)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_distance_similarity() {
+        let (a, b) = ([1.0, 0.0], [2.0, 0.0]);
+        assert!((Distance::Cosine.similarity(&a, &b) - 1.0).abs() < 1e-6);
+        assert!((Distance::Dot.similarity(&a, &b) - 2.0).abs() < 1e-6);
+        assert!((Distance::Euclid.similarity(&a, &b) - 0.5).abs() < 1e-6);
+        // Closer points score higher under every metric
+        let c = [0.0, 1.0];
+        for d in [Distance::Cosine, Distance::Dot, Distance::Euclid] {
+            assert!(d.similarity(&a, &b) > d.similarity(&a, &c));
+        }
+        // Unit vectors score the same under every metric, so thresholds carry over
+        let (u, v) = ([1.0, 0.0], [0.6, 0.8]);
+        for d in [Distance::Dot, Distance::Euclid] {
+            assert!((d.similarity(&u, &v) - Distance::Cosine.similarity(&u, &v)).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,25 @@
+// Modules the `ghost-lib` binary is built from. They are public so the CLI can
+// reach its internals, but hidden from the docs: the supported library API is
+// the re-exports below.
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod core;
+#[doc(hidden)]
+pub mod db;
+#[doc(hidden)]
+pub mod server;
+#[doc(hidden)]
+pub mod tui;
+#[doc(hidden)]
+pub mod utils;
+
+pub use crate::core::distill::{
+    distill, BudgetUnit, DistillEvent, DistillProgress, DistillResult, SourceRef,
+};
+pub use crate::core::ingest::{create_embedder, ingest_file, IngestEvent, IngestOutcome, Progress};
+pub use crate::core::provider::{
+    answer_with_context, ask_with_context, ask_with_context_stream, ask_with_context_until, Answer,
+    GenerationParams, StreamEvent, Usage,
+};
+pub use crate::db::{open_collection, open_store, Filter, VectorStore};
@@ -1,11 +1,19 @@
-mod core;
-mod db;
-mod tui;
-mod utils;
-
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use ghost_lib::core::ingest::IngestOutcome;
+use ghost_lib::{config, core, db, server, tui, utils};
+
+/// Exit status after Ctrl+C, as a shell reports SIGINT (128 + 2)
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Flush what was printed and exit with `EXIT_INTERRUPTED`
+fn exit_interrupted() -> ! {
+    let _ = std::io::stdout().flush();
+    std::process::exit(EXIT_INTERRUPTED)
+}
 
 #[derive(Parser)]
 #[command(
@@ -17,67 +25,438 @@ use std::path::PathBuf;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Print extra diagnostics to stderr (for `ask`: the exact prompt sent to the model)
+    #[arg(short, long, global = true)]
+    verbose: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Add a document to the library (supports .md, .txt, .pdf)
+    /// Add a document to the library (supports .md, .txt, .pdf, .docx, .epub)
     Add {
-        /// Path to the document file
-        path: PathBuf,
+        /// Document file, directory (indexed recursively), or quoted glob like "notes/**/*.md"
+        path: String,
+        /// Replace documents that are already indexed under the same filename
+        #[arg(long)]
+        recreate: bool,
+        /// Tag the document (repeatable); re-adding an unchanged file updates its tags
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Show how documents would be chunked (counts, tokens, sections) without
+        /// embedding or storing anything
+        #[arg(long, conflicts_with_all = ["recreate", "tags"])]
+        dry_run: bool,
+        /// Only embed text added to the end of an indexed file (e.g. a journal);
+        /// re-indexes the whole file if its indexed part changed
+        #[arg(long, conflicts_with_all = ["recreate", "tags", "dry_run"])]
+        append: bool,
     },
     /// Ask a question using context distillation + local LLM
     Ask {
-        /// Your question
+        /// Your question, or `-` to read it from stdin
         query: String,
         /// LLM model to use (default: llama3, override with GHOST_MODEL)
         #[arg(short, long)]
         model: Option<String>,
-        /// Context budget in tokens (default: 3000)
+        /// Context budget in tokens (default: 3000, override with GHOST_BUDGET)
         #[arg(short, long)]
         budget: Option<usize>,
+        /// What --budget counts: estimated tokens, or characters for a predictable cap
+        #[arg(long, value_enum, default_value_t = core::distill::BudgetUnit::Tokens)]
+        budget_unit: core::distill::BudgetUnit,
+        /// Candidate chunks to retrieve before dedup (default: 20).
+        /// Raising it only helps while the distilled chunks still fit in --budget
+        #[arg(short = 'k', long)]
+        top_k: Option<u64>,
+        /// Similarity (0.0–1.0) above which chunks count as duplicates (default: 0.85,
+        /// override with GHOST_DEDUP_THRESHOLD). Lower values drop more chunks
+        #[arg(long, value_parser = parse_fraction)]
+        dedup_threshold: Option<f32>,
+        /// Sampling temperature (default: 0.1, override with GHOST_TEMPERATURE)
+        #[arg(long)]
+        temperature: Option<f32>,
+        /// Maximum tokens to generate (default: 1024, override with GHOST_MAX_TOKENS)
+        #[arg(long)]
+        max_tokens: Option<i32>,
+        /// Only retrieve from this document (as shown in `ghost-lib list`)
+        #[arg(long, value_name = "FILENAME")]
+        file: Option<String>,
+        /// Only retrieve chunks carrying this tag (repeatable; tags are ORed)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Search this collection instead of the active one (repeatable; results are
+        /// merged and ranked together)
+        #[arg(long = "collection", value_name = "NAME")]
+        collections: Vec<String>,
+        /// Rank chunks from recently modified files higher (half-life:
+        /// GHOST_RECENCY_HALF_LIFE_DAYS, default 30); files indexed without a date are unaffected
+        #[arg(long)]
+        recency_boost: bool,
+        /// Citation style: `inline` numbers sources as `[1]` in the answer and appends a
+        /// reference list, `footnote` uses Markdown footnotes `[^1]`
+        #[arg(long, value_enum, default_value_t = Cite::None)]
+        cite: Cite,
+        /// Print the exact system message and prompt sent to the model, then exit
+        #[arg(long)]
+        raw_prompt: bool,
+        /// With --raw-prompt, continue and generate the answer after printing
+        #[arg(long, requires = "raw_prompt")]
+        proceed: bool,
+        /// Print one JSON object (answer, stats, sources) instead of streaming text
+        #[arg(long, conflicts_with = "raw_prompt")]
+        json: bool,
+        /// Print only the finished answer on stdout (progress, stats and sources go to stderr)
+        #[arg(long, conflicts_with_all = ["raw_prompt", "json"])]
+        no_stream: bool,
+        /// After answering, list sentences no retrieved passage supports (similarity
+        /// below GHOST_VERIFY_THRESHOLD, calibrated per embedding model by default)
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Show the chunks that best match a query, without calling the LLM
+    Search {
+        /// Your query
+        query: String,
+        /// Number of chunks to show
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+        /// Only search chunks carrying this tag (repeatable; tags are ORed)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
     },
     /// List all indexed documents
-    List,
-    /// Delete an indexed document by filename
+    List {
+        /// Show at most this many documents
+        #[arg(long, value_parser = parse_limit)]
+        limit: Option<usize>,
+        /// Skip this many documents first (after sorting)
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        /// Order by filename, or by chunk count (largest first)
+        #[arg(long, value_enum, default_value_t = ListSort::Name)]
+        sort: ListSort,
+    },
+    /// Delete an indexed document by filename, one chunk with --chunk, or everything with --all
     Delete {
         /// Filename to delete (as shown in `ghost-lib list`)
-        filename: String,
+        #[arg(required_unless_present_any = ["all", "chunk"])]
+        filename: Option<String>,
+        /// Delete only one chunk of a document (its index as shown by `search`)
+        #[arg(
+            long,
+            num_args = 2,
+            value_names = ["FILENAME", "CHUNK_INDEX"],
+            conflicts_with_all = ["filename", "all"]
+        )]
+        chunk: Option<Vec<String>>,
+        /// Wipe the whole collection
+        #[arg(long, conflicts_with = "filename")]
+        all: bool,
+        /// Skip the confirmation prompt for --all
+        #[arg(short, long, requires = "all")]
+        yes: bool,
+    },
+    /// Change the filename a document is indexed under (no re-embedding)
+    Rename {
+        /// Current name (as shown in `ghost-lib list`)
+        old: String,
+        /// New name
+        new: String,
     },
     /// Show index statistics
-    Stats,
+    Stats {
+        /// List each document with its chunk count and estimated tokens
+        #[arg(long)]
+        per_doc: bool,
+    },
+    /// Re-embed every stored document with the current embedding model
+    Reindex,
+    /// Write every indexed chunk (id, vector, payload) to a JSONL file
+    Export {
+        /// Output file, e.g. library.jsonl
+        path: PathBuf,
+    },
+    /// Load chunks from a JSONL export without re-embedding
+    Import {
+        /// File written by `ghost-lib export`
+        path: PathBuf,
+    },
     /// Health check for Ollama
-    Check,
+    Check {
+        /// Print one JSON object (backend, models, embedder, store) and exit
+        /// non-zero when a required service is down
+        #[arg(long)]
+        json: bool,
+    },
+    /// Manage Ollama models
+    Models {
+        #[command(subcommand)]
+        action: ModelsAction,
+    },
+    /// Inspect the config file that supplies defaults
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Serve the library over a local HTTP API
+    Serve {
+        /// Address to bind (keep on localhost unless you trust the network)
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 8765)]
+        port: u16,
+        /// Full address to bind, e.g. 127.0.0.1:9000 (instead of --host/--port)
+        #[arg(long, value_name = "ADDR", conflicts_with_all = ["host", "port"])]
+        bind: Option<String>,
+    },
     /// Interactive TUI chat with context distillation
     Chat {
         /// LLM model to use (default: llama3, override with GHOST_MODEL)
         #[arg(short, long)]
         model: Option<String>,
-        /// Context budget in tokens (default: 3000)
+        /// Context budget in tokens (default: 3000, override with GHOST_BUDGET)
         #[arg(short, long)]
         budget: Option<usize>,
+        /// Draw inline without the alternate screen or mouse capture, for terminals
+        /// and SSH/tmux setups where those misbehave (automatic when TERM is dumb)
+        #[arg(long)]
+        simple: bool,
+    },
+}
+
+/// Order of `ghost-lib list`
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ListSort {
+    /// Alphabetical by filename
+    Name,
+    /// Most chunks first
+    Chunks,
+}
+
+/// How `ask` cites the distilled sources in its answer
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Cite {
+    /// No citations in the answer (sources are still listed after it)
+    None,
+    /// Numbered markers like `[1]`, followed by a reference list
+    Inline,
+    /// Markdown footnote markers like `[^1]`, followed by footnote definitions
+    Footnote,
+}
+
+impl Cite {
+    /// Instruction appended to the question, if the model should cite passages
+    fn instruction(self) -> Option<&'static str> {
+        match self {
+            Cite::None => None,
+            Cite::Inline => Some(
+                "Cite the numbered passages you rely on with their number in square brackets, \
+                 e.g. [1] or [2][3]. Do not add a reference list.",
+            ),
+            Cite::Footnote => Some(
+                "Cite the numbered passages you rely on with Markdown footnote markers, \
+                 e.g. [^1] or [^2][^3]. Do not write the footnote definitions.",
+            ),
+        }
+    }
+
+    /// Reference list keyed to the passage numbers the model was told to cite
+    fn references(self, sources: &[core::distill::SourceRef]) -> String {
+        let lines = sources.iter().enumerate().map(|(i, src)| match self {
+            Cite::Footnote => format!("[^{}]: {}", i + 1, src.citation()),
+            _ => format!("  [{}] {}", i + 1, src.citation()),
+        });
+        let header = match self {
+            Cite::Footnote => "",
+            _ => "References:\n",
+        };
+        format!("{header}{}", lines.collect::<Vec<_>>().join("\n"))
+    }
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print where the config file is looked up
+    Path,
+}
+
+#[derive(Subcommand)]
+enum ModelsAction {
+    /// List models available in Ollama
+    List,
+    /// Download a model into Ollama
+    Pull {
+        /// Model name (e.g. llama3, mistral:7b)
+        name: String,
     },
+    /// Show the model `ask` and `chat` use by default
+    Current,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     let cli = Cli::parse();
+    init_logging();
+    // `config path` must still work when the file itself doesn't parse. Applied
+    // before the runtime starts, while this is the only thread.
+    if !matches!(cli.command, Commands::Config { .. }) {
+        config::Config::load()?.apply();
+    }
+
+    tokio::runtime::Runtime::new()
+        .context("Failed to start the async runtime")?
+        .block_on(run(cli))
+}
 
+async fn run(cli: Cli) -> Result<()> {
     match cli.command {
-        Commands::Add { path } => cmd_add(&path).await,
+        Commands::Add {
+            path,
+            recreate,
+            tags,
+            dry_run,
+            append,
+        } => {
+            if append {
+                cmd_append(&path).await
+            } else {
+                cmd_add(&path, recreate, &tags, dry_run).await
+            }
+        }
         Commands::Ask {
             query,
             model,
             budget,
-        } => cmd_ask(&query, model.as_deref(), budget).await,
-        Commands::List => cmd_list().await,
-        Commands::Delete { filename } => cmd_delete(&filename).await,
-        Commands::Stats => cmd_stats().await,
-        Commands::Check => cmd_check().await,
-        Commands::Chat { model, budget } => tui::cmd_chat(model.as_deref(), budget).await,
+            budget_unit,
+            top_k,
+            dedup_threshold,
+            temperature,
+            max_tokens,
+            file,
+            tags,
+            collections,
+            recency_boost,
+            cite,
+            raw_prompt,
+            proceed,
+            json,
+            no_stream,
+            verify,
+        } => {
+            let opts = AskOptions {
+                model,
+                budget,
+                budget_unit,
+                top_k,
+                dedup_threshold,
+                file,
+                tags,
+                collections,
+                recency_boost,
+                cite,
+                verbose: cli.verbose,
+                params: core::provider::GenerationParams::with_overrides(temperature, max_tokens),
+                raw_prompt,
+                proceed,
+                json,
+                no_stream,
+                verify,
+            };
+            let query = if query == "-" {
+                read_stdin_query()?
+            } else {
+                query
+            };
+            cmd_ask(&query, opts).await
+        }
+        Commands::Search { query, limit, tags } => cmd_search(&query, limit, &tags).await,
+        Commands::List {
+            limit,
+            offset,
+            sort,
+        } => cmd_list(limit, offset, sort).await,
+        Commands::Delete {
+            filename,
+            chunk,
+            all,
+            yes,
+        } => match (filename, chunk) {
+            (_, Some(chunk)) => cmd_delete_chunk(&chunk[0], &chunk[1]).await,
+            (Some(filename), _) if !all => cmd_delete(&filename).await,
+            _ => cmd_clear(yes).await,
+        },
+        Commands::Rename { old, new } => cmd_rename(&old, &new).await,
+        Commands::Stats { per_doc } => cmd_stats(per_doc).await,
+        Commands::Reindex => cmd_reindex().await,
+        Commands::Export { path } => cmd_export(&path).await,
+        Commands::Import { path } => cmd_import(&path).await,
+        Commands::Check { json } => {
+            if json {
+                cmd_check_json().await
+            } else {
+                cmd_check().await
+            }
+        }
+        Commands::Models { action } => match action {
+            ModelsAction::List => cmd_models_list().await,
+            ModelsAction::Pull { name } => cmd_models_pull(&name).await,
+            ModelsAction::Current => cmd_models_current().await,
+        },
+        Commands::Config { action } => match action {
+            ConfigAction::Path => cmd_config_path(),
+        },
+        Commands::Serve { host, port, bind } => {
+            let addr = bind.unwrap_or_else(|| format!("{host}:{port}"));
+            server::cmd_serve(&addr).await
+        }
+        Commands::Chat {
+            model,
+            budget,
+            simple,
+        } => tui::cmd_chat(model.as_deref(), budget, simple).await,
+    }
+}
+
+fn cmd_config_path() -> Result<()> {
+    let path = config::config_path();
+    println!("{}", path.display());
+    if !path.exists() {
+        eprintln!("(not found; built-in defaults and GHOST_* env vars apply)");
+    }
+    Ok(())
+}
+
+/// Clap parser for values that must lie in 0.0–1.0
+fn parse_fraction(s: &str) -> Result<f32, String> {
+    let value: f32 = s.parse().map_err(|_| format!("'{s}' is not a number"))?;
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("must be between 0.0 and 1.0, got {value}"))
     }
 }
 
+fn parse_limit(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(limit) => Ok(limit),
+        Err(_) => Err(format!("'{s}' is not a whole number")),
+    }
+}
+
+/// Pre-flight check: ensure the answering backend (GHOST_BACKEND) is reachable
+async fn require_backend() -> Result<()> {
+    let backend = core::provider::backend();
+    if !backend.health_check().await {
+        anyhow::bail!(
+            "{} is not reachable.\n{}",
+            backend.name(),
+            backend.unreachable_hint()
+        );
+    }
+    Ok(())
+}
+
 /// Pre-flight check: ensure Ollama is reachable
 async fn require_ollama() -> Result<()> {
     if !core::provider::health_check().await? {
@@ -89,64 +468,750 @@ async fn require_ollama() -> Result<()> {
     Ok(())
 }
 
-async fn cmd_add(path: &std::path::Path) -> Result<()> {
+async fn cmd_add(target: &str, recreate: bool, tags: &[String], dry_run: bool) -> Result<()> {
+    let path = std::path::Path::new(target);
+    if !path.exists() && is_glob(target) {
+        let files = expand_glob(target)?;
+        if files.is_empty() {
+            println!("No files match pattern: {target}");
+            println!("Check the pattern, and quote it so your shell does not expand it first.");
+            return Ok(());
+        }
+        if dry_run {
+            return preview_many(&files).await;
+        }
+        return add_many(&files, recreate, tags).await;
+    }
+
     if !path.exists() {
         anyhow::bail!("File not found: {}", path.display());
     }
 
+    if path.is_dir() {
+        let files = core::ingest::collect_documents(path)?;
+        if files.is_empty() {
+            println!(
+                "No supported documents found in {} (supported: .{})",
+                path.display(),
+                core::ingest::SUPPORTED_EXTENSIONS.join(", .")
+            );
+            return Ok(());
+        }
+        if dry_run {
+            return preview_many(&files).await;
+        }
+        return add_many(&files, recreate, tags).await;
+    }
+
+    if dry_run {
+        return preview_many(&[path.to_path_buf()]).await;
+    }
+
     let mut store = db::open_store().await?;
 
     let embedder = core::ingest::create_embedder()?;
-    let chunks = core::ingest::ingest_file(path, &embedder, &mut store).await?;
+    match core::ingest::ingest_file(
+        path,
+        &embedder,
+        &mut store,
+        recreate,
+        tags,
+        &core::ingest::Progress::Terminal,
+    )
+    .await?
+    {
+        IngestOutcome::Ingested(chunks) => {
+            println!(
+                "\nSuccessfully indexed {chunks} chunks from {}",
+                path.display()
+            );
+        }
+        IngestOutcome::Unchanged => {
+            println!("{} is unchanged, skipped.", path.display());
+        }
+        IngestOutcome::AlreadyIndexed => {
+            println!(
+                "A different version of {} is already indexed — skipped.",
+                core::ingest::document_name(path)
+            );
+            println!("Use `ghost-lib add {target} --recreate` to replace it.");
+        }
+    }
+    Ok(())
+}
 
-    println!(
-        "\nSuccessfully indexed {chunks} chunks from {}",
-        path.display()
+async fn cmd_append(target: &str) -> Result<()> {
+    let path = Path::new(target);
+    if !path.is_file() {
+        anyhow::bail!("--append takes a single existing file, got {target}");
+    }
+
+    let mut store = db::open_store().await?;
+    let embedder = core::ingest::create_embedder()?;
+    match core::ingest::append_file(
+        path,
+        &embedder,
+        &mut store,
+        &core::ingest::Progress::Terminal,
+    )
+    .await?
+    {
+        IngestOutcome::Ingested(chunks) => {
+            println!("\nIndexed {chunks} new chunks from {}", path.display());
+        }
+        _ => println!("Nothing new in {} since it was indexed.", path.display()),
+    }
+    Ok(())
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Expand a glob to regular files, de-duplicated and in sorted order
+fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut seen = std::collections::HashSet::new();
+    let mut files = Vec::new();
+    for entry in glob::glob(pattern).map_err(|e| anyhow::anyhow!("Invalid pattern: {e}"))? {
+        let path = entry?;
+        if !path.is_file() {
+            continue;
+        }
+        let key = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if seen.insert(key) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// `add --dry-run`: print how each file would be chunked
+async fn preview_many(files: &[PathBuf]) -> Result<()> {
+    let (chunk_size, chunk_overlap) = core::ingest::chunk_settings();
+    println!("Dry run (GHOST_CHUNK_SIZE={chunk_size}, GHOST_CHUNK_OVERLAP={chunk_overlap}); nothing will be indexed.");
+
+    let mut total_chunks = 0;
+    let mut total_tokens = 0;
+    for path in files {
+        let preview = match core::ingest::preview_file(path).await {
+            Ok(preview) => preview,
+            Err(e) => {
+                eprintln!("\n{}: {e:#}", path.display());
+                continue;
+            }
+        };
+        total_chunks += preview.chunks.len();
+        total_tokens += preview.tokens;
+
+        println!(
+            "\n{}: {} chunks, ~{} tokens",
+            preview.filename,
+            preview.chunks.len(),
+            preview.tokens
+        );
+        println!(
+            "  {:>4}  {:>6}  {:>7}  {:>5}  Section",
+            "#", "Chars", "~Tokens", "Page"
+        );
+        for (i, chunk) in preview.chunks.iter().enumerate() {
+            let page = chunk.page.map(|p| p.to_string()).unwrap_or_default();
+            println!(
+                "  {:>4}  {:>6}  {:>7}  {page:>5}  {}",
+                i + 1,
+                chunk.chars,
+                chunk.tokens,
+                chunk.section
+            );
+        }
+        if preview.sections.is_empty() {
+            println!("  No markdown headings detected.");
+        } else {
+            println!("  Sections ({}):", preview.sections.len());
+            for section in &preview.sections {
+                println!("    {section}");
+            }
+        }
+    }
+
+    if files.len() > 1 {
+        println!(
+            "\nTotal: {total_chunks} chunks, ~{total_tokens} tokens across {} file(s)",
+            files.len()
+        );
+    }
+    Ok(())
+}
+
+/// Ingest several files with one embedder, skipping (not aborting on) failures
+async fn add_many(files: &[PathBuf], recreate: bool, tags: &[String]) -> Result<()> {
+    let mut store = db::open_store().await?;
+    let embedder = core::ingest::create_embedder()?;
+
+    let mut total_chunks = 0;
+    let mut indexed = 0;
+    let mut unchanged = 0;
+    let mut already = 0;
+    let mut failed: Vec<(&PathBuf, anyhow::Error)> = Vec::new();
+
+    for (i, file) in files.iter().enumerate() {
+        println!("\n[{}/{}] {}", i + 1, files.len(), file.display());
+        match core::ingest::ingest_file(
+            file,
+            &embedder,
+            &mut store,
+            recreate,
+            tags,
+            &core::ingest::Progress::Terminal,
+        )
+        .await
+        {
+            Ok(IngestOutcome::Ingested(chunks)) => {
+                total_chunks += chunks;
+                indexed += 1;
+            }
+            Ok(IngestOutcome::Unchanged) => {
+                println!("  Unchanged, skipped");
+                unchanged += 1;
+            }
+            Ok(IngestOutcome::AlreadyIndexed) => {
+                println!("  Different version already indexed — skipped");
+                already += 1;
+            }
+            Err(e) => {
+                eprintln!("  Failed: {e:#}");
+                failed.push((file, e));
+            }
+        }
+    }
+
+    println!("\n--- Summary ---");
+    println!("  Files indexed:  {indexed}");
+    println!("  Chunks indexed: {total_chunks}");
+    if unchanged > 0 {
+        println!("  Unchanged:      {unchanged}");
+    }
+    if already > 0 {
+        println!("  Already indexed: {already} (use --recreate to replace them)");
+    }
+    if !failed.is_empty() {
+        println!("  Files failed:   {}", failed.len());
+        for (file, e) in &failed {
+            println!("    {}: {e:#}", file.display());
+        }
+        anyhow::bail!("{} of {} files failed to index", failed.len(), files.len());
+    }
+
+    Ok(())
+}
+
+/// Diagnostic logs go to stderr and stay off unless RUST_LOG enables them
+/// (e.g. `RUST_LOG=ghost_lib=debug`); normal output is unaffected
+fn init_logging() {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("off"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .init();
+}
+
+/// The embedding model for `ask`, or None for keyword-only retrieval when
+/// GHOST_KEYWORD_ONLY is set or the model can't be loaded (e.g. offline)
+fn ask_embedder() -> Option<std::sync::Arc<tokio::sync::Mutex<fastembed::TextEmbedding>>> {
+    if core::distill::keyword_only_forced() {
+        eprintln!(
+            "Warning: GHOST_KEYWORD_ONLY is set — ranking by keywords only, \
+             so relevant passages that use different wording will be missed."
+        );
+        return None;
+    }
+    match core::ingest::create_embedder() {
+        Ok(embedder) => Some(embedder),
+        Err(e) => {
+            eprintln!(
+                "Warning: {e:#}\n\
+                 Falling back to keyword-only retrieval — relevant passages that use \
+                 different wording will be missed."
+            );
+            None
+        }
+    }
+}
+
+/// Flags for `ghost-lib ask`
+struct AskOptions {
+    model: Option<String>,
+    budget: Option<usize>,
+    budget_unit: core::distill::BudgetUnit,
+    top_k: Option<u64>,
+    dedup_threshold: Option<f32>,
+    file: Option<String>,
+    tags: Vec<String>,
+    collections: Vec<String>,
+    recency_boost: bool,
+    cite: Cite,
+    verbose: bool,
+    params: core::provider::GenerationParams,
+    raw_prompt: bool,
+    proceed: bool,
+    json: bool,
+    no_stream: bool,
+    verify: bool,
+}
+
+fn distill_spinner() -> indicatif::ProgressBar {
+    let spinner = indicatif::ProgressBar::new_spinner();
+    spinner.set_style(
+        indicatif::ProgressStyle::with_template("{spinner:.green} {msg} [{elapsed}]").unwrap(),
     );
+    spinner.set_message("Embedding query and searching...");
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+    spinner
+}
+
+/// What the `ask` spinner says after each distillation milestone
+fn distill_progress_message(event: core::distill::DistillEvent) -> String {
+    use core::distill::DistillEvent;
+    match event {
+        DistillEvent::Expanded { variants, extra } => {
+            format!("Searched {variants} rephrasings of the query (+{extra} chunks)...")
+        }
+        DistillEvent::Retrieved { chunks, filtered } if filtered > 0 => format!(
+            "Retrieved {chunks} chunks ({filtered} below min score); removing redundancy..."
+        ),
+        DistillEvent::Retrieved { chunks, .. } => {
+            format!("Retrieved {chunks} chunks; removing redundancy...")
+        }
+        DistillEvent::Deduped { kept } => format!("{kept} chunks after dedup; packing..."),
+        DistillEvent::Packed { chunks } => format!("Packed {chunks} chunks; finishing up..."),
+    }
+}
+
+/// Query for `ask -`: all of stdin, minus trailing whitespace
+fn read_stdin_query() -> Result<String> {
+    use anyhow::Context;
+    use std::io::Read;
+    let mut query = String::new();
+    std::io::stdin()
+        .read_to_string(&mut query)
+        .context("Failed to read the query from stdin")?;
+    let query = query.trim_end();
+    if query.trim_start().is_empty() {
+        anyhow::bail!(
+            "No query on stdin: `ghost-lib ask -` reads the question from standard input"
+        );
+    }
+    Ok(query.to_string())
+}
+
+/// The collections named with `--collection`, or the active one when none are
+async fn open_collections(names: &[String]) -> Result<Vec<db::VectorStore>> {
+    if names.is_empty() {
+        return Ok(vec![db::open_store().await?]);
+    }
+    let mut seen = std::collections::HashSet::new();
+    let mut stores = Vec::new();
+    for name in names.iter().filter(|n| seen.insert(n.as_str())) {
+        stores.push(db::open_collection(name).await?);
+    }
+    Ok(stores)
+}
+
+async fn cmd_ask(query: &str, opts: AskOptions) -> Result<()> {
+    // --raw-prompt alone never talks to the model, so the backend is optional
+    if !opts.raw_prompt || opts.proceed {
+        require_backend().await?;
+        ensure_model_pulled(&core::provider::active_model_name(opts.model.as_deref())).await?;
+    }
+
+    let stores = open_collections(&opts.collections).await?;
+    let stores: Vec<&db::VectorStore> = stores.iter().collect();
+
+    if let Some(name) = &opts.file {
+        let mut indexed = 0;
+        for store in &stores {
+            indexed += db::count_by_filename(store, name).await?;
+        }
+        if indexed == 0 {
+            anyhow::bail!(
+                "'{name}' is not in the index.\n\
+                 Use `ghost-lib list` to see indexed documents."
+            );
+        }
+    }
+    let filter = retrieval_filter(opts.file.as_deref(), &opts.tags);
+
+    let embedder = ask_embedder();
+
+    // Progress and stats go to stderr with --no-stream, leaving stdout for the answer
+    let mut out: Box<dyn Write> = if opts.no_stream {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    };
+
+    // Live progress on stderr; indicatif hides it when stderr isn't a terminal
+    let spinner = if opts.json {
+        indicatif::ProgressBar::hidden()
+    } else {
+        writeln!(out, "Distilling context...\n")?;
+        distill_spinner()
+    };
+    let report = |event| spinner.set_message(distill_progress_message(event));
+    let result = core::distill::distill(
+        query,
+        embedder.as_ref(),
+        &stores,
+        opts.budget,
+        opts.budget_unit,
+        opts.top_k,
+        opts.dedup_threshold,
+        filter.as_ref(),
+        opts.recency_boost,
+        opts.model.as_deref(),
+        Some(&report),
+    )
+    .await;
+    spinner.finish_and_clear();
+    let result = result?;
+    for warning in &result.warnings {
+        eprintln!("Warning: {warning}");
+    }
+
+    // With --cite the model sees numbered passages and is told to cite them
+    let (question, context) = match opts.cite.instruction() {
+        Some(instruction) => (
+            format!("{query}\n\n{instruction}"),
+            result.numbered_context(),
+        ),
+        None => (query.to_string(), result.context.clone()),
+    };
+    // --raw-prompt already shows the prompt on stdout
+    if opts.verbose && !opts.raw_prompt && !context.is_empty() {
+        print_raw_prompt(&mut std::io::stderr(), &question, &context)?;
+    }
+
+    if opts.json {
+        let answer = if result.context.is_empty() {
+            None
+        } else {
+            Some(
+                core::provider::answer_with_context(
+                    &question,
+                    &context,
+                    opts.model.as_deref(),
+                    opts.params,
+                )
+                .await?,
+            )
+        };
+        let unsupported = match (&answer, opts.verify) {
+            (Some(answer), true) => {
+                check_grounding(embedder.as_ref(), &answer.text, &result.passages).await?
+            }
+            _ => None,
+        };
+        let mut output = serde_json::json!({
+            "query": query,
+            "answer": answer.as_ref().map(|a| &a.text),
+            "stats": result.stats_json(),
+            "sources": result.sources,
+            "usage": answer.and_then(|a| a.usage),
+        });
+        if let Some((unsupported, threshold)) = unsupported {
+            output["unsupported"] = serde_json::json!(unsupported);
+            output["verify_threshold"] = serde_json::json!(threshold);
+        }
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if result.context.is_empty() {
+        if result.chunks_filtered > 0 {
+            writeln!(
+                out,
+                "All {} retrieved chunks scored below GHOST_MIN_SCORE.",
+                result.chunks_filtered
+            )?;
+            writeln!(out, "No relevant documents found.")?;
+        } else {
+            writeln!(
+                out,
+                "No relevant documents found. Add documents first with: ghost-lib add <path>"
+            )?;
+        }
+        return Ok(());
+    }
+
+    writeln!(out, "--- Distillation Stats ---")?;
+    writeln!(out, "  Chunks retrieved:   {}", result.chunks_retrieved)?;
+    if !result.expansions.is_empty() {
+        writeln!(
+            out,
+            "  From expansion:     {} ({} rephrasings)",
+            result.expansion_chunks,
+            result.expansions.len()
+        )?;
+    }
+    if result.chunks_filtered > 0 {
+        writeln!(out, "  Below min score:    {}", result.chunks_filtered)?;
+    }
+    writeln!(out, "  After dedup:        {}", result.chunks_after_dedup)?;
+    if result.sentences_removed > 0 {
+        writeln!(out, "  Repeated sentences: {}", result.sentences_removed)?;
+    }
+    writeln!(out, "  Original tokens:    {}", result.original_tokens)?;
+    writeln!(out, "  Distilled tokens:   {}", result.distilled_tokens)?;
+    writeln!(
+        out,
+        "  Compression:        {:.1}%",
+        result.compression_ratio * 100.0
+    )?;
+    if result.keyword_only {
+        writeln!(
+            out,
+            "  Retrieval:          keyword fallback (no embeddings)"
+        )?;
+    } else {
+        writeln!(out, "  Top score:          {:.3}", result.top_score)?;
+    }
+    writeln!(out, "--------------------------\n")?;
+    if result.low_relevance() {
+        writeln!(
+            out,
+            "Warning: low relevance — no chunk matched well, so the answer may not come from your documents.\n\
+             Consider adding documents on this topic.\n"
+        )?;
+    }
+
+    if opts.raw_prompt {
+        print_raw_prompt(&mut std::io::stdout(), &question, &context)?;
+        if !opts.proceed {
+            return Ok(());
+        }
+    }
+
+    writeln!(out, "Generating answer...\n")?;
+    let model = opts.model.as_deref();
+    let answer = if opts.no_stream {
+        let answer =
+            core::provider::answer_with_context(&question, &context, model, opts.params).await?;
+        println!("{}", answer.text.trim());
+        answer
+    } else {
+        // Ctrl+C stops the stream (closing the request, so the backend stops
+        // generating too) but still prints what arrived
+        let cancel = tokio_util::sync::CancellationToken::new();
+        let on_ctrl_c = tokio::spawn({
+            let cancel = cancel.clone();
+            async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    cancel.cancel();
+                }
+            }
+        });
+        let answer =
+            core::provider::ask_with_context_until(&question, &context, model, opts.params, cancel)
+                .await;
+        on_ctrl_c.abort();
+        let answer = answer?;
+        if answer.interrupted {
+            // The stream stopped mid-line
+            println!(" (interrupted)");
+        }
+        answer
+    };
+    // The reference list belongs to the answer, so it goes to stdout
+    if opts.cite != Cite::None {
+        // A streamed answer ends mid-line; a --no-stream one already ended its line
+        let gap = if opts.no_stream { "\n" } else { "\n\n" };
+        println!("{gap}{}", opts.cite.references(&result.sources));
+    }
+    if opts.verify {
+        if let Some((unsupported, threshold)) =
+            check_grounding(embedder.as_ref(), &answer.text, &result.passages).await?
+        {
+            print_unsupported(&mut out, &unsupported, threshold)?;
+        }
+    }
+    if let Some(summary) = answer.usage.and_then(|u| u.summary()) {
+        writeln!(out, "\nUsage: {summary}")?;
+    }
+
+    if opts.cite == Cite::None {
+        print_sources(&mut out, &result.sources)?;
+    }
+
+    if answer.interrupted {
+        exit_interrupted();
+    }
     Ok(())
 }
 
-async fn cmd_ask(query: &str, model: Option<&str>, budget: Option<usize>) -> Result<()> {
-    require_ollama().await?;
+/// `ask --verify`: answer sentences no passage supports and the threshold used,
+/// or `None` (with a warning) when there is no embedder to check with
+async fn check_grounding(
+    embedder: Option<&std::sync::Arc<tokio::sync::Mutex<fastembed::TextEmbedding>>>,
+    answer: &str,
+    passages: &[String],
+) -> Result<Option<(Vec<core::grounding::Unsupported>, f32)>> {
+    let Some(embedder) = embedder else {
+        eprintln!("Warning: --verify needs the embedding model; skipping the grounding check.");
+        return Ok(None);
+    };
+    let threshold = core::grounding::verify_threshold();
+    core::grounding::unsupported_sentences(embedder, answer, passages, threshold)
+        .await
+        .map(|unsupported| Some((unsupported, threshold)))
+}
+
+/// "Possibly unsupported:" list printed after the answer by `ask --verify`
+fn print_unsupported(
+    out: &mut dyn Write,
+    unsupported: &[core::grounding::Unsupported],
+    threshold: f32,
+) -> Result<()> {
+    if unsupported.is_empty() {
+        writeln!(
+            out,
+            "\nNo sentence scored below the support threshold ({threshold:.2}). This is a \
+             similarity check: it can miss wrong details in on-topic sentences."
+        )?;
+        return Ok(());
+    }
+    writeln!(out, "\nPossibly unsupported:")?;
+    for claim in unsupported {
+        writeln!(
+            out,
+            "  - {} (best match {:.3})",
+            claim.sentence, claim.best_score
+        )?;
+    }
+    Ok(())
+}
+
+/// "Sources:" footer listing the documents the packed context was drawn from
+fn print_sources(out: &mut dyn Write, sources: &[core::distill::SourceRef]) -> Result<()> {
+    if sources.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\nSources:")?;
+    for (i, src) in sources.iter().enumerate() {
+        writeln!(
+            out,
+            "  [{}] {} (score {:.3})",
+            i + 1,
+            src.citation(),
+            src.score
+        )?;
+    }
+    Ok(())
+}
+
+/// Payload filter for `--file` / `--tag`, or `None` to search everything
+fn retrieval_filter(file: Option<&str>, tags: &[String]) -> Option<db::Filter> {
+    let tags = core::ingest::normalize_tags(tags);
+    if file.is_none() && tags.is_empty() {
+        return None;
+    }
+    let mut filter = db::Filter::new();
+    if let Some(name) = file {
+        filter = filter.must("filename", name);
+    }
+    if !tags.is_empty() {
+        filter = filter.must_any("tags", tags);
+    }
+    Some(filter)
+}
 
+async fn cmd_search(query: &str, limit: usize, tags: &[String]) -> Result<()> {
     let store = db::open_store().await?;
     let embedder = core::ingest::create_embedder()?;
 
-    println!("Distilling context...\n");
-    let result = core::distill::distill(query, &embedder, &store, budget).await?;
+    // Hybrid scoring reorders within the candidates, so fetch at least the usual top-k
+    let top_k = (limit as u64).max(core::distill::DEFAULT_TOP_K);
+    let filter = retrieval_filter(None, tags);
+    let chunks =
+        core::distill::retrieve(query, &embedder, &[&store], top_k, filter.as_ref()).await?;
 
-    if result.context.is_empty() {
+    if chunks.is_empty() {
         println!("No relevant documents found. Add documents first with: ghost-lib add <path>");
         return Ok(());
     }
 
-    println!("--- Distillation Stats ---");
-    println!("  Chunks retrieved:   {}", result.chunks_retrieved);
-    println!("  After dedup:        {}", result.chunks_after_dedup);
-    println!("  Original tokens:    {}", result.original_tokens);
-    println!("  Distilled tokens:   {}", result.distilled_tokens);
+    let terms = core::distill::extract_terms(query);
+    let color = utils::highlight::color_enabled();
+    for (i, chunk) in chunks.iter().take(limit).enumerate() {
+        let page = chunk.page.map(|p| format!(" p.{p}")).unwrap_or_default();
+        let index = chunk
+            .chunk_index
+            .map(|n| format!(" #{n}"))
+            .unwrap_or_default();
+        println!(
+            "{:>2}. [{:.3}] {}{index}{page} — {}",
+            i + 1,
+            chunk.score,
+            chunk.filename,
+            chunk.section
+        );
+        println!(
+            "    {}\n",
+            utils::highlight::snippet(&chunk.text, &terms, 200, color)
+        );
+    }
     println!(
-        "  Compression:        {:.1}%",
-        result.compression_ratio * 100.0
+        "Showing {} of {} candidates",
+        limit.min(chunks.len()),
+        chunks.len()
     );
-    println!("--------------------------\n");
 
-    println!("Generating answer...\n");
-    core::provider::ask_with_context(query, &result.context, model).await?;
+    Ok(())
+}
 
+/// Dump the assembled system message and prompt verbatim between markers
+fn print_raw_prompt(out: &mut dyn Write, query: &str, context: &str) -> Result<()> {
+    writeln!(out, "===== SYSTEM =====")?;
+    writeln!(out, "{}", core::provider::system_prompt()?)?;
+    writeln!(out, "===== PROMPT =====")?;
+    writeln!(out, "{}", core::provider::build_prompt(query, context))?;
+    writeln!(out, "===== END =====\n")?;
     Ok(())
 }
 
-async fn cmd_list() -> Result<()> {
+async fn cmd_list(limit: Option<usize>, offset: usize, sort: ListSort) -> Result<()> {
     let store = db::open_store().await?;
 
     match db::list_filenames(&store).await {
-        Ok(files) if !files.is_empty() => {
+        Ok(mut files) if !files.is_empty() => {
+            // list_filenames is already alphabetical, and the sort is stable
+            if sort == ListSort::Chunks {
+                files.sort_by_key(|f| std::cmp::Reverse(f.1));
+            }
+            let total = files.len();
+            let page: Vec<_> = files
+                .iter()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .collect();
+
             println!("Indexed documents:\n");
-            for (filename, chunks) in &files {
+            for (filename, chunks) in &page {
                 println!("  {filename}  ({chunks} chunks)");
             }
-            println!("\n  Total: {} document(s)", files.len());
+            if page.len() == total {
+                println!("\n  Total: {total} document(s)");
+            } else if page.is_empty() {
+                println!("  (none — offset {offset} is past the last document)");
+                println!("\n  Total: {total} document(s)");
+            } else {
+                println!(
+                    "\n  Showing {}–{} of {total} document(s)",
+                    offset + 1,
+                    offset + page.len()
+                );
+            }
         }
         Ok(_) => {
             println!("No documents indexed. Add one with: ghost-lib add <path>");
@@ -173,22 +1238,154 @@ async fn cmd_delete(filename: &str) -> Result<()> {
     Ok(())
 }
 
-async fn cmd_stats() -> Result<()> {
+async fn cmd_delete_chunk(filename: &str, chunk_index: &str) -> Result<()> {
+    let chunk_index: u64 = chunk_index
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Chunk index must be a number, got '{chunk_index}'"))?;
+    let mut store = db::open_store().await?;
+    let filter = db::Filter::filename(filename).must("chunk_index", chunk_index);
+    let deleted = db::delete_points(&mut store, &filter).await?;
+
+    if deleted > 0 {
+        println!("Deleted {deleted} point(s) for chunk {chunk_index} of: {filename}");
+    } else {
+        println!("No chunk {chunk_index} found for: {filename}");
+        println!("Use `ghost-lib search` to see chunk indexes.");
+    }
+
+    Ok(())
+}
+
+async fn cmd_rename(old: &str, new: &str) -> Result<()> {
+    let mut store = db::open_store().await?;
+    let renamed = db::rename_document(&mut store, old, new).await?;
+    println!("Renamed {old} → {new} ({renamed} chunks)");
+    Ok(())
+}
+
+async fn cmd_clear(yes: bool) -> Result<()> {
+    let mut store = db::open_store().await?;
+    let (points, _) = db::collection_info(&store).await?;
+    let collection = db::collection_name();
+    if points == 0 {
+        println!("Collection '{collection}' is already empty.");
+        return Ok(());
+    }
+
+    if !yes {
+        print!("Delete all {points} chunks from collection '{collection}'? [y/N] ");
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    let removed = db::clear_collection(&mut store).await?;
+    println!("Deleted {removed} chunks; collection '{collection}' is now empty.");
+    Ok(())
+}
+
+async fn cmd_stats(per_doc: bool) -> Result<()> {
     let store = db::open_store().await?;
 
     let (points, _segments) = db::collection_info(&store).await?;
-    if points > 0 {
-        println!("Ghost Library Stats");
-        println!("  Collection:  {}", db::COLLECTION_NAME);
-        println!("  Documents:   {points} chunks indexed");
-    } else {
+    if points == 0 {
+        println!("No documents indexed. Add one with: ghost-lib add <path>");
+        return Ok(());
+    }
+
+    println!("Ghost Library Stats");
+    println!("  Collection:  {}", db::collection_name());
+    println!("  Documents:   {points} chunks indexed");
+
+    if per_doc {
+        let mut docs = Vec::new();
+        for (filename, chunks) in db::list_filenames(&store).await? {
+            let tokens: usize = db::chunk_texts(&store, &filename)
+                .await?
+                .into_iter()
+                .map(utils::text_cleaner::estimate_tokens)
+                .sum();
+            docs.push((filename, chunks, tokens));
+        }
+        docs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let total_label = format!("Total ({} documents)", docs.len());
+        let width = docs
+            .iter()
+            .map(|d| d.0.chars().count())
+            .chain([total_label.len()])
+            .max()
+            .unwrap_or(0);
+        println!();
+        println!(
+            "  {:<width$}  {:>7}  {:>9}",
+            "Document", "Chunks", "~Tokens"
+        );
+        for (filename, chunks, tokens) in &docs {
+            println!("  {filename:<width$}  {chunks:>7}  {tokens:>9}");
+        }
+        let total_tokens: usize = docs.iter().map(|d| d.2).sum();
+        println!("  {total_label:<width$}  {points:>7}  {total_tokens:>9}");
+    }
+
+    Ok(())
+}
+
+async fn cmd_reindex() -> Result<()> {
+    let mut store = db::open_store().await?;
+    if store.points.is_empty() {
         println!("No documents indexed. Add one with: ghost-lib add <path>");
+        return Ok(());
     }
 
+    let embedder = core::ingest::create_embedder()?;
+    let summary = core::ingest::reindex(&embedder, &mut store).await?;
+
+    println!(
+        "\nRe-embedded {} chunks across {} document(s)",
+        summary.chunks, summary.documents
+    );
+    if let (Some(old), Some(new)) = (summary.old_dims, summary.new_dims) {
+        if old != new {
+            println!("  Vector dimension changed: {old} → {new} (collection rebuilt)");
+        }
+    }
+
+    Ok(())
+}
+
+async fn cmd_export(path: &Path) -> Result<()> {
+    let store = db::open_store().await?;
+    let count = db::export_jsonl(&store, path).await?;
+    println!("Exported {count} chunks to {}", path.display());
+    Ok(())
+}
+
+async fn cmd_import(path: &Path) -> Result<()> {
+    let mut store = db::open_store().await?;
+    let count = db::import_jsonl(&mut store, path).await?;
+    let (points, _) = db::collection_info(&store).await?;
+    println!("Imported {count} chunks from {}", path.display());
+    println!("  Collection now holds {points} chunks");
     Ok(())
 }
 
 async fn cmd_check() -> Result<()> {
+    if core::provider::is_openai_backend() {
+        let backend = core::provider::backend();
+        print!("{} ...  ", backend.name());
+        match backend.health_check().await {
+            true => println!("OK"),
+            false => println!("UNREACHABLE — {}", backend.unreachable_hint()),
+        }
+        check_embedder().await;
+        return Ok(());
+    }
+
     print!("Ollama ...  ");
     match core::provider::health_check().await? {
         true => {
@@ -196,6 +1393,13 @@ async fn cmd_check() -> Result<()> {
             match core::provider::list_models().await {
                 Ok(models) if !models.is_empty() => {
                     println!("  Models: {}", models.join(", "));
+                    let default = core::provider::active_model_name(None);
+                    if !models
+                        .iter()
+                        .any(|m| core::provider::is_same_model(m, &default))
+                    {
+                        println!("  Warning: default model '{default}' is not pulled — run: ollama pull {default}");
+                    }
                 }
                 Ok(_) => {
                     println!("  No models found — run: ollama pull llama3");
@@ -208,9 +1412,216 @@ async fn cmd_check() -> Result<()> {
         false => println!("UNREACHABLE — run: ollama serve"),
     }
 
+    check_embedder().await;
+
     let store = db::open_store().await?;
     let (points, _) = db::collection_info(&store).await?;
     println!("Store  ...  OK ({points} chunks)");
+    if let Err(e) = core::ingest::ensure_dimension(&store) {
+        println!("  Warning: {e}");
+    }
+
+    Ok(())
+}
+
+/// `check --json`: the same probes as `check` as one JSON object, for scripts.
+/// Fails (after printing) when the answering backend, embedder or store is unusable.
+async fn cmd_check_json() -> Result<()> {
+    let openai = core::provider::is_openai_backend();
+    let backend_key = if openai { "openai" } else { "ollama" };
+    let backend_ok = core::provider::backend().health_check().await;
+
+    let mut report = serde_json::json!({
+        "backend": backend_key,
+        (backend_key): backend_ok,
+    });
+    if !openai {
+        let models = if backend_ok {
+            core::provider::list_models().await.unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let default = core::provider::active_model_name(None);
+        report["default_model_pulled"] = models
+            .iter()
+            .any(|m| core::provider::is_same_model(m, &default))
+            .into();
+        report["default_model"] = default.into();
+        report["models"] = models.into();
+    }
+
+    let embedder_ok = core::ingest::check_embedder().await.is_ok();
+    report["embedder"] = embedder_ok.into();
+
+    let chunks = match db::open_store().await {
+        Ok(store) => Some(db::collection_info(&store).await?.0),
+        Err(_) => None,
+    };
+    report["store"] = chunks.is_some().into();
+    report["chunks"] = chunks.into();
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    let down: Vec<&str> = [
+        (backend_key, backend_ok),
+        ("embedder", embedder_ok),
+        ("store", chunks.is_some()),
+    ]
+    .into_iter()
+    .filter(|(_, ok)| !ok)
+    .map(|(name, _)| name)
+    .collect();
+    if !down.is_empty() {
+        anyhow::bail!("Required services are down: {}", down.join(", "));
+    }
+    Ok(())
+}
+
+/// Report whether the ONNX embedding model loads and embeds (the usual first-run failure)
+async fn check_embedder() {
+    print!("Embedder ...  ");
+    std::io::stdout().flush().ok();
+    match core::ingest::check_embedder().await {
+        Ok((model, dim)) => println!("OK ({model}, {dim} dims)"),
+        Err(e) => {
+            println!("FAILED — {e:#}");
+            println!("  A stalled download or corrupt model cache is the usual cause; delete the fastembed cache (FASTEMBED_CACHE_DIR, default .fastembed_cache) and retry.");
+        }
+    }
+}
+
+async fn cmd_models_list() -> Result<()> {
+    require_ollama().await?;
+
+    let models = core::provider::list_models().await?;
+    if models.is_empty() {
+        println!("No models found — pull one with: ghost-lib models pull llama3");
+        return Ok(());
+    }
+
+    let current = core::provider::active_model_name(None);
+    println!("Ollama models:\n");
+    for name in &models {
+        let marker = if core::provider::is_same_model(name, &current) {
+            "  (default)"
+        } else {
+            ""
+        };
+        println!("  {name}{marker}");
+    }
+    println!("\n  Total: {} model(s)", models.len());
+
+    Ok(())
+}
+
+async fn cmd_models_pull(name: &str) -> Result<()> {
+    require_ollama().await?;
+
+    if pull_with_progress(name).await? {
+        println!("\nPulled {name}");
+    } else {
+        println!("\nPull interrupted. Run the same command again to resume.");
+        exit_interrupted();
+    }
+    Ok(())
+}
+
+/// Pull a model from Ollama with a progress bar. Returns false if interrupted with Ctrl+C.
+async fn pull_with_progress(name: &str) -> Result<bool> {
+    use indicatif::{ProgressBar, ProgressStyle};
+    use tokio_stream::StreamExt;
+
+    let mut stream = core::provider::pull_model_stream(name).await?;
+
+    let pb = ProgressBar::new(0);
+    pb.set_style(
+        ProgressStyle::with_template(
+            "{spinner:.green} [{bar:40.cyan/blue}] {bytes}/{total_bytes} {msg} ({eta})",
+        )
+        .unwrap()
+        .progress_chars("=>-"),
+    );
+
+    loop {
+        tokio::select! {
+            status = stream.next() => {
+                let Some(status) = status else { break };
+                let status = match status {
+                    Ok(status) => status,
+                    Err(e) => {
+                        pb.abandon_with_message("failed");
+                        anyhow::bail!("Pull of '{name}' failed: {e}");
+                    }
+                };
+                if let Some(total) = status.total {
+                    if pb.length() != Some(total) {
+                        pb.set_length(total);
+                        pb.reset();
+                    }
+                }
+                if let Some(completed) = status.completed {
+                    pb.set_position(completed);
+                }
+                pb.set_message(status.message);
+            }
+            _ = tokio::signal::ctrl_c() => {
+                pb.abandon_with_message("interrupted");
+                return Ok(false);
+            }
+        }
+    }
+
+    pb.finish_with_message("done");
+    Ok(true)
+}
+
+/// Make sure an Ollama model is pulled before generating, pulling it when
+/// GHOST_AUTO_PULL=1 and otherwise naming the command to run
+async fn ensure_model_pulled(model: &str) -> Result<()> {
+    if core::provider::is_openai_backend() {
+        return Ok(());
+    }
+    let models = core::provider::list_models().await?;
+    if models
+        .iter()
+        .any(|m| core::provider::is_same_model(m, model))
+    {
+        return Ok(());
+    }
+    if !core::provider::auto_pull() {
+        anyhow::bail!(
+            "Model '{model}' is not pulled. Run: ollama pull {model}\n\
+             (or set GHOST_AUTO_PULL=1 to pull missing models automatically)"
+        );
+    }
+    // stderr, like the progress bar, so `ask --json` output stays clean
+    eprintln!("Model '{model}' is not pulled yet — pulling it (GHOST_AUTO_PULL=1)");
+    if !pull_with_progress(model).await? {
+        anyhow::bail!("Pull of '{model}' interrupted");
+    }
+    eprintln!();
+    Ok(())
+}
+
+async fn cmd_models_current() -> Result<()> {
+    let current = core::provider::active_model_name(None);
+    let source = if config::set_by_config("GHOST_MODEL") {
+        format!("from {}", config::config_path().display())
+    } else if std::env::var("GHOST_MODEL").is_ok() {
+        "from GHOST_MODEL".to_string()
+    } else {
+        "built-in default".to_string()
+    };
+    println!("Default model: {current} ({source})");
+
+    if core::provider::health_check().await? {
+        let models = core::provider::list_models().await?;
+        if !models
+            .iter()
+            .any(|m| core::provider::is_same_model(m, &current))
+        {
+            println!("  Not pulled yet — run: ghost-lib models pull {current}");
+        }
+    }
 
     Ok(())
 }
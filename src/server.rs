@@ -0,0 +1,262 @@
+/// Local HTTP API (`ghost-lib serve`): the core pipeline behind a localhost boundary.
+use anyhow::{Context, Result};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use fastembed::TextEmbedding;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+use tokio_util::sync::CancellationToken;
+
+use crate::core::{distill, ingest, provider};
+use crate::db;
+
+/// State shared by every request. Only the embedder is kept: the store is
+/// reopened per request (like the TUI does) so changes made with the CLI while
+/// the server runs are seen, and never overwritten by a stale copy.
+#[derive(Clone)]
+struct AppState {
+    embedder: Arc<Mutex<TextEmbedding>>,
+    /// Serialises `/add` so two requests don't each save their own copy
+    write_lock: Arc<Mutex<()>>,
+}
+
+#[derive(Deserialize)]
+struct AskRequest {
+    query: String,
+    model: Option<String>,
+    budget: Option<usize>,
+    top_k: Option<u64>,
+    dedup_threshold: Option<f32>,
+    file: Option<String>,
+    temperature: Option<f32>,
+    max_tokens: Option<i32>,
+    #[serde(default)]
+    stream: bool,
+}
+
+#[derive(Deserialize)]
+struct AddRequest {
+    path: PathBuf,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Handler error rendered as `{"error": "..."}`
+struct ApiError(StatusCode, String);
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}"))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+/// Entry point for `ghost-lib serve`.
+pub async fn cmd_serve(addr: &str) -> Result<()> {
+    let embedder = ingest::create_embedder()?;
+    // Fail at startup rather than on the first request if the store is unreadable
+    db::open_store().await?;
+
+    let state = AppState {
+        embedder,
+        write_lock: Arc::new(Mutex::new(())),
+    };
+
+    let app = Router::new()
+        .route("/ask", post(ask))
+        .route("/add", post(add))
+        .route("/list", get(list))
+        .route("/docs", get(list))
+        .route("/stats", get(stats))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind {addr}"))?;
+
+    println!("Ghost Librarian API listening on http://{addr}");
+    println!("  POST /ask   POST /add   GET /list (/docs)   GET /stats");
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .context("HTTP server failed")
+}
+
+async fn ask(State(state): State<AppState>, Json(req): Json<AskRequest>) -> ApiResult<Response> {
+    if req.query.trim().is_empty() {
+        return Err(ApiError(StatusCode::BAD_REQUEST, "query is empty".into()));
+    }
+    if let Err(e) = distill::dedup_threshold(req.dedup_threshold) {
+        return Err(ApiError(StatusCode::BAD_REQUEST, format!("{e:#}")));
+    }
+
+    let result = {
+        let store = db::open_store().await?;
+        let filter = match &req.file {
+            Some(name) => {
+                if db::count_by_filename(&store, name).await? == 0 {
+                    return Err(ApiError(
+                        StatusCode::NOT_FOUND,
+                        format!("'{name}' is not in the index"),
+                    ));
+                }
+                Some(db::Filter::filename(name.as_str()))
+            }
+            None => None,
+        };
+        distill::distill(
+            &req.query,
+            Some(&state.embedder),
+            &[&store],
+            req.budget,
+            distill::BudgetUnit::Tokens,
+            req.top_k,
+            req.dedup_threshold,
+            filter.as_ref(),
+            false,
+            req.model.as_deref(),
+            None,
+        )
+        .await?
+    };
+
+    if result.context.is_empty() {
+        return Err(ApiError(
+            StatusCode::NOT_FOUND,
+            "No relevant documents found".into(),
+        ));
+    }
+
+    let stats = result.stats_json();
+    let sources = json!(result.sources);
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(provider::ask_with_context_stream(
+        req.query,
+        result.context.clone(),
+        req.model,
+        provider::GenerationParams::with_overrides(req.temperature, req.max_tokens),
+        tx,
+        CancellationToken::new(),
+    ));
+
+    if req.stream {
+        // Newline-delimited JSON: one context event, then tokens, then done/error
+        let head = json!({
+            "event": "context",
+            "context": result.context,
+            "sources": sources,
+            "stats": stats,
+        });
+        let events = UnboundedReceiverStream::new(rx).map(|ev| match ev {
+            provider::StreamEvent::Token(text) => json!({ "event": "token", "text": text }),
+            provider::StreamEvent::Usage(usage) => json!({ "event": "usage", "usage": usage }),
+            provider::StreamEvent::Done => json!({ "event": "done" }),
+            provider::StreamEvent::Error(e) => json!({ "event": "error", "message": e }),
+        });
+        let lines = tokio_stream::once(head)
+            .chain(events)
+            .map(|v| Ok::<_, std::convert::Infallible>(format!("{v}\n")));
+
+        return Ok((
+            [(header::CONTENT_TYPE, "application/x-ndjson")],
+            Body::from_stream(lines),
+        )
+            .into_response());
+    }
+
+    let mut answer = String::new();
+    let mut usage = None;
+    let mut rx = rx;
+    while let Some(ev) = rx.recv().await {
+        match ev {
+            provider::StreamEvent::Token(text) => answer.push_str(&text),
+            provider::StreamEvent::Usage(u) => usage = Some(u),
+            provider::StreamEvent::Done => break,
+            provider::StreamEvent::Error(e) => {
+                return Err(ApiError(StatusCode::BAD_GATEWAY, e));
+            }
+        }
+    }
+
+    Ok(Json(json!({
+        "answer": answer,
+        "context": result.context,
+        "sources": sources,
+        "stats": stats,
+        "usage": usage,
+    }))
+    .into_response())
+}
+
+async fn add(State(state): State<AppState>, Json(req): Json<AddRequest>) -> ApiResult<Json<Value>> {
+    if !req.path.exists() {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("File not found: {}", req.path.display()),
+        ));
+    }
+
+    let _guard = state.write_lock.lock().await;
+    let mut store = db::open_store().await?;
+    let outcome = ingest::ingest_file(
+        &req.path,
+        &state.embedder,
+        &mut store,
+        false,
+        &req.tags,
+        &ingest::Progress::Terminal,
+    )
+    .await?;
+    let (status, chunks) = match outcome {
+        ingest::IngestOutcome::Ingested(chunks) => ("indexed", chunks),
+        ingest::IngestOutcome::Unchanged => ("unchanged", 0),
+        ingest::IngestOutcome::AlreadyIndexed => ("already_indexed", 0),
+    };
+
+    Ok(Json(json!({
+        "path": req.path.display().to_string(),
+        "status": status,
+        "chunks": chunks,
+    })))
+}
+
+async fn list() -> ApiResult<Json<Value>> {
+    let store = db::open_store().await?;
+    let files = db::list_filenames(&store).await?;
+    let documents: Vec<Value> = files
+        .into_iter()
+        .map(|(filename, chunks)| json!({ "filename": filename, "chunks": chunks }))
+        .collect();
+    Ok(Json(json!({ "documents": documents })))
+}
+
+async fn stats() -> ApiResult<Json<Value>> {
+    let store = db::open_store().await?;
+    let (points, _) = db::collection_info(&store).await?;
+    let documents = db::list_filenames(&store).await?.len();
+    Ok(Json(json!({
+        "collection": db::collection_name(),
+        "chunks": points,
+        "documents": documents,
+    })))
+}
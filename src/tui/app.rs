@@ -1,4 +1,23 @@
 /// Application state for the TUI chat interface.
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::core::distill::SourceRef;
+use crate::core::provider::Usage;
+use crate::db;
+
+/// Redraw ticks (~33 ms each) a hints-bar notice stays visible
+const NOTICE_TICKS: u64 = 90;
+
+/// Lines moved per PageUp/PageDown or mouse-wheel notch
+const SCROLL_STEP: u16 = 5;
+
+/// Most recent queries kept in the input history
+const HISTORY_LIMIT: usize = 500;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Role {
@@ -12,6 +31,18 @@ pub struct DistillStats {
     pub chunks_retrieved: usize,
     pub after_dedup: usize,
     pub compression_pct: f64,
+    /// Best hybrid score among the retrieved chunks
+    pub top_score: f64,
+    pub low_relevance: bool,
+    /// Ranked by keywords alone, so `top_score` is relative and not shown
+    pub keyword_only: bool,
+}
+
+/// The distilled context an answer was generated from
+#[derive(Debug, Clone)]
+pub struct RetrievedContext {
+    pub text: String,
+    pub sources: Vec<SourceRef>,
 }
 
 #[derive(Debug, Clone)]
@@ -19,6 +50,9 @@ pub struct ChatMessage {
     pub role: Role,
     pub content: String,
     pub stats: Option<DistillStats>,
+    pub context: Option<RetrievedContext>,
+    /// Token counts and timing, once the backend reports them
+    pub usage: Option<Usage>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -28,18 +62,41 @@ pub enum AppPhase {
     Streaming,
 }
 
+/// Characters that make up a word for word-wise cursor movement
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
 pub struct App {
     pub messages: Vec<ChatMessage>,
     pub phase: AppPhase,
+    /// When the current Distilling/Streaming phase began, for the elapsed timer
+    phase_started: Option<Instant>,
     pub input: String,
     pub cursor_pos: usize,
     pub scroll_offset: u16,
+    /// Whether the retrieved-context side panel is open
+    pub show_context: bool,
+    /// Lines scrolled down from the top of the context panel
+    pub context_scroll: u16,
     pub model_name: String,
+    /// Cancels the answer currently streaming in
+    pub generation: Option<CancellationToken>,
+    /// Progress line for an `/add` running in the background
+    pub ingest_progress: Option<String>,
     pub budget: Option<usize>,
     pub chunk_count: u64,
     pub tick_count: u64,
     pub ollama_ok: bool,
     pub should_quit: bool,
+    /// Short-lived notice in the hints bar, with the tick it expires at
+    pub notice: Option<(String, u64)>,
+    /// Submitted queries, oldest first
+    pub history: Vec<String>,
+    /// Entry currently recalled with Up/Down, if any
+    history_pos: Option<usize>,
+    /// What was typed before browsing history, restored past the newest entry
+    history_draft: String,
 }
 
 impl App {
@@ -47,15 +104,24 @@ impl App {
         Self {
             messages: Vec::new(),
             phase: AppPhase::Idle,
+            phase_started: None,
             input: String::new(),
             cursor_pos: 0,
             scroll_offset: 0,
+            show_context: false,
+            context_scroll: 0,
             model_name,
+            generation: None,
+            ingest_progress: None,
             budget,
             chunk_count: 0,
             tick_count: 0,
             ollama_ok: false,
             should_quit: false,
+            notice: None,
+            history: load_history(),
+            history_pos: None,
+            history_draft: String::new(),
         }
     }
 
@@ -64,10 +130,106 @@ impl App {
             role,
             content,
             stats,
+            context: None,
+            usage: None,
         });
         self.scroll_offset = 0;
     }
 
+    /// Record the context behind the answer that was just pushed
+    pub fn attach_context(&mut self, context: RetrievedContext) {
+        if let Some(msg) = self.messages.last_mut() {
+            msg.context = Some(context);
+        }
+        self.context_scroll = 0;
+    }
+
+    /// Record the backend's usage report for the answer being streamed
+    pub fn attach_usage(&mut self, usage: Usage) {
+        if let Some(msg) = self.messages.last_mut() {
+            msg.usage = Some(usage);
+        }
+    }
+
+    /// Context of the most recent answer that has one
+    pub fn last_context(&self) -> Option<&RetrievedContext> {
+        self.messages.iter().rev().find_map(|m| m.context.as_ref())
+    }
+
+    pub fn toggle_context(&mut self) {
+        self.show_context = !self.show_context;
+        self.context_scroll = 0;
+    }
+
+    pub fn scroll_context_up(&mut self) {
+        self.context_scroll = self.context_scroll.saturating_sub(SCROLL_STEP);
+    }
+
+    pub fn scroll_context_down(&mut self) {
+        self.context_scroll = self.context_scroll.saturating_add(SCROLL_STEP);
+    }
+
+    /// Show `text` in the hints bar for about three seconds
+    pub fn flash(&mut self, text: impl Into<String>) {
+        self.notice = Some((text.into(), self.tick_count + NOTICE_TICKS));
+    }
+
+    /// The notice to display, if it hasn't expired
+    pub fn active_notice(&self) -> Option<&str> {
+        self.notice
+            .as_ref()
+            .filter(|(_, until)| self.tick_count < *until)
+            .map(|(text, _)| text.as_str())
+    }
+
+    /// Content of the most recent assistant answer, if any
+    pub fn last_answer(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m.role == Role::Assistant && !m.content.is_empty())
+            .map(|m| m.content.as_str())
+    }
+
+    /// The last question asked, with the answer to it removed so it can be regenerated
+    pub fn take_last_query(&mut self) -> Option<String> {
+        let idx = self.messages.iter().rposition(|m| m.role == Role::User)?;
+        let query = self.messages[idx].content.clone();
+        let tail = self.messages.split_off(idx + 1);
+        self.messages
+            .extend(tail.into_iter().filter(|m| m.role != Role::Assistant));
+        self.scroll_offset = 0;
+        Some(query)
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll_offset = self.scroll_offset.saturating_add(SCROLL_STEP);
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(SCROLL_STEP);
+    }
+
+    /// Switch phase, restarting the elapsed timer for busy phases
+    pub fn set_phase(&mut self, phase: AppPhase) {
+        self.phase = phase;
+        self.phase_started = (phase != AppPhase::Idle).then(Instant::now);
+    }
+
+    /// Time spent in the current Distilling/Streaming phase
+    pub fn phase_elapsed(&self) -> Option<Duration> {
+        self.phase_started.map(|start| start.elapsed())
+    }
+
+    /// Stop the streaming answer, keeping what has arrived so far
+    pub fn stop_generation(&mut self) {
+        if let Some(cancel) = self.generation.take() {
+            cancel.cancel();
+        }
+        self.append_to_last(" (stopped)");
+        self.set_phase(AppPhase::Idle);
+    }
+
     pub fn append_to_last(&mut self, token: &str) {
         if let Some(msg) = self.messages.last_mut() {
             msg.content.push_str(token);
@@ -111,6 +273,81 @@ impl App {
         }
     }
 
+    /// Char index of the start of the word before the cursor, skipping any
+    /// punctuation or whitespace in between
+    fn prev_word_start(&self) -> usize {
+        let chars: Vec<char> = self.input.chars().take(self.cursor_pos).collect();
+        let mut pos = chars.len();
+        while pos > 0 && !is_word_char(chars[pos - 1]) {
+            pos -= 1;
+        }
+        while pos > 0 && is_word_char(chars[pos - 1]) {
+            pos -= 1;
+        }
+        pos
+    }
+
+    pub fn move_word_left(&mut self) {
+        self.cursor_pos = self.prev_word_start();
+    }
+
+    /// Move to the end of the next word
+    pub fn move_word_right(&mut self) {
+        let mut rest = self.input.chars().skip(self.cursor_pos).peekable();
+        while rest.next_if(|&c| !is_word_char(c)).is_some() {
+            self.cursor_pos += 1;
+        }
+        while rest.next_if(|&c| is_word_char(c)).is_some() {
+            self.cursor_pos += 1;
+        }
+    }
+
+    /// Delete from the start of the previous word up to the cursor
+    pub fn delete_word_before(&mut self) {
+        let start = self.prev_word_start();
+        let byte_at = |pos: usize| {
+            self.input
+                .char_indices()
+                .nth(pos)
+                .map(|(i, _)| i)
+                .unwrap_or(self.input.len())
+        };
+        let range = byte_at(start)..byte_at(self.cursor_pos);
+        self.input.replace_range(range, "");
+        self.cursor_pos = start;
+    }
+
+    /// Line index and column (in chars) of the cursor within the input
+    pub fn cursor_line_col(&self) -> (usize, usize) {
+        let before: String = self.input.chars().take(self.cursor_pos).collect();
+        let line = before.matches('\n').count();
+        let col = before
+            .rsplit('\n')
+            .next()
+            .map_or(0, |last| last.chars().count());
+        (line, col)
+    }
+
+    pub fn input_line_count(&self) -> usize {
+        self.input.split('\n').count()
+    }
+
+    /// Move the cursor one line up or down, keeping its column where possible.
+    /// Returns false when already on the first/last line.
+    pub fn move_cursor_line(&mut self, up: bool) -> bool {
+        let (line, col) = self.cursor_line_col();
+        let target = match (up, line) {
+            (true, 0) => return false,
+            (true, _) => line - 1,
+            (false, _) if line + 1 >= self.input_line_count() => return false,
+            (false, _) => line + 1,
+        };
+        let lines: Vec<&str> = self.input.split('\n').collect();
+        let start: usize = lines[..target].iter().map(|l| l.chars().count() + 1).sum();
+        self.cursor_pos = start + col.min(lines[target].chars().count());
+        true
+    }
+
     pub fn move_cursor_home(&mut self) {
         self.cursor_pos = 0;
     }
@@ -121,6 +358,205 @@ impl App {
 
     pub fn take_input(&mut self) -> String {
         self.cursor_pos = 0;
+        self.history_pos = None;
         std::mem::take(&mut self.input)
     }
+
+    // --- Input history ---
+
+    /// Remember a submitted query in memory and in the history file
+    pub fn record_history(&mut self, query: &str) {
+        if self.history.last().map(String::as_str) == Some(query) {
+            return;
+        }
+        self.history.push(query.to_string());
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.remove(0);
+        }
+        append_history(query);
+    }
+
+    /// Recall the previous (older) query
+    pub fn history_prev(&mut self) {
+        let pos = match self.history_pos {
+            None if self.history.is_empty() => return,
+            None => {
+                self.history_draft = self.input.clone();
+                self.history.len() - 1
+            }
+            Some(0) => return,
+            Some(pos) => pos - 1,
+        };
+        self.history_pos = Some(pos);
+        self.set_input(self.history[pos].clone());
+    }
+
+    /// Recall the next (newer) query, ending back at the unsent draft
+    pub fn history_next(&mut self) {
+        let Some(pos) = self.history_pos else {
+            return;
+        };
+        if pos + 1 < self.history.len() {
+            self.history_pos = Some(pos + 1);
+            self.set_input(self.history[pos + 1].clone());
+        } else {
+            self.history_pos = None;
+            let draft = std::mem::take(&mut self.history_draft);
+            self.set_input(draft);
+        }
+    }
+
+    fn set_input(&mut self, input: String) {
+        self.input = input;
+        self.cursor_pos = self.input.chars().count();
+    }
+}
+
+fn history_path() -> PathBuf {
+    db::data_dir().join("history")
+}
+
+/// Queries are stored one JSON string per line, so embedded newlines survive
+fn load_history() -> Vec<String> {
+    let Ok(data) = fs::read_to_string(history_path()) else {
+        return Vec::new();
+    };
+    let mut history: Vec<String> = data
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let excess = history.len().saturating_sub(HISTORY_LIMIT);
+    history.drain(..excess);
+
+    // The file is append-only while running; compact it once it doubles
+    if excess > HISTORY_LIMIT {
+        let lines: String = history
+            .iter()
+            .map(|q| format!("{}\n", serde_json::json!(q)))
+            .collect();
+        let _ = fs::write(history_path(), lines);
+    }
+    history
+}
+
+/// Best effort: a read-only data dir just means history isn't kept
+fn append_history(query: &str) {
+    let path = history_path();
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(file, "{}", serde_json::json!(query));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_navigation_restores_draft() {
+        let mut app = App::new("llama3".into(), None);
+        app.history = vec!["first".into(), "second".into()];
+        app.input = "draft".into();
+
+        app.history_prev();
+        assert_eq!(app.input, "second");
+        assert_eq!(app.cursor_pos, 6);
+        app.history_prev();
+        app.history_prev();
+        assert_eq!(app.input, "first");
+
+        app.history_next();
+        assert_eq!(app.input, "second");
+        app.history_next();
+        assert_eq!(app.input, "draft");
+        app.history_next();
+        assert_eq!(app.input, "draft");
+    }
+
+    #[test]
+    fn test_stop_generation_keeps_partial_answer() {
+        let mut app = App::new("llama3".into(), None);
+        let cancel = CancellationToken::new();
+        app.generation = Some(cancel.clone());
+        app.phase = AppPhase::Streaming;
+        app.push_message(Role::Assistant, "Partial".into(), None);
+
+        app.stop_generation();
+        assert!(cancel.is_cancelled());
+        assert!(app.generation.is_none());
+        assert_eq!(app.phase, AppPhase::Idle);
+        assert_eq!(app.messages.last().unwrap().content, "Partial (stopped)");
+    }
+
+    #[test]
+    fn test_multiline_cursor_movement() {
+        let mut app = App::new("llama3".into(), None);
+        for c in "first line\nab".chars() {
+            app.insert_char(c);
+        }
+        assert_eq!(app.cursor_line_col(), (1, 2));
+        assert_eq!(app.input_line_count(), 2);
+
+        assert!(app.move_cursor_line(true));
+        assert_eq!(app.cursor_line_col(), (0, 2));
+        assert!(!app.move_cursor_line(true));
+
+        app.move_cursor_end();
+        app.move_cursor_line(true);
+        assert_eq!(app.cursor_line_col(), (0, 2));
+        app.move_cursor_right();
+        app.move_cursor_line(false);
+        assert_eq!(app.cursor_line_col(), (1, 2));
+
+        app.move_cursor_home();
+        app.move_cursor_line(false);
+        app.delete_char_before();
+        assert_eq!(app.input, "first lineab");
+        assert_eq!(app.cursor_line_col(), (0, 10));
+    }
+
+    #[test]
+    fn test_take_last_query_drops_its_answer() {
+        let mut app = App::new("llama3".into(), None);
+        app.push_message(Role::User, "first".into(), None);
+        app.push_message(Role::Assistant, "one".into(), None);
+        app.push_message(Role::User, "second".into(), None);
+        app.push_message(Role::Assistant, "two".into(), None);
+        app.push_message(Role::System, "Model switched to phi3".into(), None);
+
+        assert_eq!(app.take_last_query().as_deref(), Some("second"));
+        let contents: Vec<&str> = app.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            ["first", "one", "second", "Model switched to phi3"]
+        );
+    }
+
+    #[test]
+    fn test_word_movement_skips_punctuation() {
+        let mut app = App::new("llama3".into(), None);
+        for c in "what's GHOST_MODEL, again?".chars() {
+            app.insert_char(c);
+        }
+
+        app.move_word_left();
+        assert_eq!(app.cursor_pos, 20); // before "again"
+        app.move_word_left();
+        assert_eq!(app.cursor_pos, 7); // before "GHOST_MODEL"
+        app.move_word_left();
+        assert_eq!(app.cursor_pos, 5); // before "s"
+        app.move_word_right();
+        assert_eq!(app.cursor_pos, 6); // after "s"
+        app.move_word_right();
+        assert_eq!(app.cursor_pos, 18); // after "GHOST_MODEL"
+
+        app.move_cursor_end();
+        app.delete_word_before();
+        assert_eq!(app.input, "what's GHOST_MODEL, ");
+        app.delete_word_before();
+        assert_eq!(app.input, "what's ");
+        assert_eq!(app.cursor_pos, 7);
+    }
 }
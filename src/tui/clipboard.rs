@@ -0,0 +1,52 @@
+/// Clipboard access without a GUI toolkit: platform copy tools first, then OSC 52.
+use anyhow::{bail, Result};
+use base64::Engine;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Command-line clipboard writers, tried in order
+const COPY_TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Copy `text`, returning how it was delivered
+pub fn copy(text: &str) -> Result<&'static str> {
+    for (tool, args) in COPY_TOOLS {
+        if pipe_to(tool, args, text).is_ok() {
+            return Ok(tool);
+        }
+    }
+    osc52(text)?;
+    Ok("terminal (OSC 52)")
+}
+
+fn pipe_to(tool: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(text.as_bytes())?;
+    if !child.wait()?.success() {
+        bail!("{tool} failed");
+    }
+    Ok(())
+}
+
+/// Ask the terminal itself to set the clipboard; works over SSH and without a display
+fn osc52(text: &str) -> Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{encoded}\x07")?;
+    stdout.flush()?;
+    Ok(())
+}
@@ -1,20 +1,32 @@
 /// Event loop: crossterm keyboard + LLM token channel, multiplexed with tokio::select!
 use anyhow::Result;
-use crossterm::event::{Event, EventStream, KeyCode, KeyModifiers};
+use crossterm::event::{Event, EventStream, KeyCode, KeyModifiers, MouseEvent, MouseEventKind};
 use futures::StreamExt;
-use ratatui::{backend::CrosstermBackend, Terminal};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Position, Rect},
+    Terminal,
+};
 use std::io;
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex};
+use tokio_util::sync::CancellationToken;
 
 use crate::core::{distill, ingest, provider};
 use crate::db;
 
-use super::app::{App, AppPhase, DistillStats, Role};
-use super::ui;
+use super::app::{App, AppPhase, DistillStats, RetrievedContext, Role};
+use super::{clipboard, transcript, ui};
 
 type Embedder = Arc<Mutex<fastembed::TextEmbedding>>;
 
+/// Updates from a background `/add`
+enum AddUpdate {
+    Progress(ingest::IngestEvent),
+    /// Summary line, or the error that stopped ingestion
+    Done(Result<String, String>),
+}
+
 /// Run the main event loop with integrated redraw. Returns when the user quits.
 pub async fn run_loop(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
@@ -25,6 +37,8 @@ pub async fn run_loop(
     let (llm_tx, mut llm_rx) = mpsc::unbounded_channel::<provider::StreamEvent>();
     let (distill_tx, mut distill_rx) =
         mpsc::unbounded_channel::<Result<(distill::DistillResult, String), String>>();
+    let (model_tx, mut model_rx) = mpsc::unbounded_channel::<Result<String, String>>();
+    let (add_tx, mut add_rx) = mpsc::unbounded_channel::<AddUpdate>();
 
     // Pre-flight: load store to get chunk count
     if let Ok(store) = db::open_store().await {
@@ -32,12 +46,17 @@ pub async fn run_loop(
         app.chunk_count = count;
     }
 
-    // Pre-flight: check Ollama connectivity
-    app.ollama_ok = provider::health_check().await.unwrap_or(false);
+    // Pre-flight: check backend connectivity
+    let backend = provider::backend();
+    app.ollama_ok = backend.health_check().await;
     if !app.ollama_ok {
         app.push_message(
             Role::System,
-            "Ollama is not reachable. Start it with: ollama serve".into(),
+            format!(
+                "{} is not reachable. {}",
+                backend.name(),
+                backend.unreachable_hint()
+            ),
             None,
         );
     }
@@ -48,12 +67,22 @@ pub async fn run_loop(
         Err(err) => {
             app.push_message(
                 Role::System,
-                format!("Warning: embedder init failed: {err}"),
+                format!(
+                    "Warning: embedder init failed: {err}. Falling back to keyword-only \
+                     retrieval (reduced quality); /add is unavailable."
+                ),
                 None,
             );
             None
         }
     };
+    if embedder.is_some() && distill::keyword_only_forced() {
+        app.push_message(
+            Role::System,
+            "GHOST_KEYWORD_ONLY is set: retrieval uses keywords only (reduced quality).".into(),
+            None,
+        );
+    }
 
     // Redraw timer — ~30 fps for smooth streaming display
     let mut tick = tokio::time::interval(tokio::time::Duration::from_millis(33));
@@ -70,8 +99,21 @@ pub async fn run_loop(
             // Keyboard events
             maybe_event = event_stream.next() => {
                 let Some(Ok(event)) = maybe_event else { break };
-                if let Event::Key(key) = event {
-                    handle_key(app, key, &llm_tx, &distill_tx, &embedder);
+                match event {
+                    Event::Key(key) => {
+                        let channels = Channels {
+                            distill: &distill_tx,
+                            model: &model_tx,
+                            add: &add_tx,
+                        };
+                        handle_key(app, key, &channels, &embedder)
+                    }
+                    Event::Mouse(mouse) => {
+                        let size = terminal.size()?;
+                        let screen = Rect::new(0, 0, size.width, size.height);
+                        handle_mouse(app, mouse, ui::scroll_areas(screen, app));
+                    }
+                    _ => {}
                 }
                 if app.should_quit {
                     break;
@@ -82,16 +124,56 @@ pub async fn run_loop(
             // LLM streaming tokens
             Some(stream_event) = llm_rx.recv() => {
                 match stream_event {
+                    // Tokens still queued from a stopped answer are dropped
+                    _ if app.phase != AppPhase::Streaming => {}
                     provider::StreamEvent::Token(tok) => {
                         app.append_to_last(&tok);
                     }
+                    provider::StreamEvent::Usage(usage) => app.attach_usage(usage),
                     provider::StreamEvent::Done => {
-                        app.phase = AppPhase::Idle;
+                        app.generation = None;
+                        app.set_phase(AppPhase::Idle);
                     }
                     provider::StreamEvent::Error(e) => {
                         app.push_message(Role::System, format!("LLM error: {e}"), None);
-                        app.phase = AppPhase::Idle;
+                        app.generation = None;
+                        app.set_phase(AppPhase::Idle);
+                    }
+                }
+            }
+
+            // `/add` progress and results
+            Some(update) = add_rx.recv() => {
+                match update {
+                    AddUpdate::Progress(ingest::IngestEvent::Status(line)) => {
+                        app.ingest_progress = Some(line);
+                    }
+                    AddUpdate::Progress(ingest::IngestEvent::Embedded { done, total }) => {
+                        app.ingest_progress = Some(format!("Embedding: {done}/{total} chunks"));
                     }
+                    AddUpdate::Done(result) => {
+                        app.ingest_progress = None;
+                        let message = match result {
+                            Ok(summary) => summary,
+                            Err(e) => format!("Add failed: {e}"),
+                        };
+                        app.push_message(Role::System, message, None);
+                        if let Ok(store) = db::open_store().await {
+                            let (count, _) = db::collection_info(&store).await.unwrap_or((0, 0));
+                            app.chunk_count = count;
+                        }
+                    }
+                }
+            }
+
+            // `/model` validation results
+            Some(result) = model_rx.recv() => {
+                match result {
+                    Ok(name) => {
+                        app.push_message(Role::System, format!("Model switched to {name}"), None);
+                        app.model_name = name;
+                    }
+                    Err(e) => app.push_message(Role::System, e, None),
                 }
             }
 
@@ -99,13 +181,16 @@ pub async fn run_loop(
             Some(result) = distill_rx.recv() => {
                 match result {
                     Ok((dr, query)) => {
+                        for warning in &dr.warnings {
+                            app.push_message(Role::System, format!("Warning: {warning}"), None);
+                        }
                         if dr.context.is_empty() {
                             app.push_message(
                                 Role::System,
                                 "No relevant documents found. Add documents first with: ghost-lib add <path>".into(),
                                 None,
                             );
-                            app.phase = AppPhase::Idle;
+                            app.set_phase(AppPhase::Idle);
                             continue;
                         }
 
@@ -113,21 +198,34 @@ pub async fn run_loop(
                             chunks_retrieved: dr.chunks_retrieved,
                             after_dedup: dr.chunks_after_dedup,
                             compression_pct: dr.compression_ratio * 100.0,
+                            top_score: dr.top_score,
+                            low_relevance: dr.low_relevance(),
+                            keyword_only: dr.keyword_only,
                         };
 
                         app.push_message(Role::Assistant, String::new(), Some(stats));
-                        app.phase = AppPhase::Streaming;
+                        app.attach_context(RetrievedContext {
+                            text: dr.context.clone(),
+                            sources: dr.sources,
+                        });
+                        app.set_phase(AppPhase::Streaming);
 
                         let tx = llm_tx.clone();
                         let context = dr.context;
                         let model = Some(app.model_name.clone());
+                        let cancel = CancellationToken::new();
+                        app.generation = Some(cancel.clone());
                         tokio::spawn(async move {
-                            provider::ask_with_context_stream(query, context, model, tx).await;
+                            let params = provider::GenerationParams::from_env();
+                            provider::ask_with_context_stream(
+                                query, context, model, params, tx, cancel,
+                            )
+                            .await;
                         });
                     }
                     Err(e) => {
                         app.push_message(Role::System, format!("Distillation error: {e}"), None);
-                        app.phase = AppPhase::Idle;
+                        app.set_phase(AppPhase::Idle);
                     }
                 }
             }
@@ -137,11 +235,227 @@ pub async fn run_loop(
     Ok(())
 }
 
+/// Distill `query` in the background; the result arrives on `distill_tx`
+fn start_query(
+    app: &mut App,
+    query: String,
+    distill_tx: &mpsc::UnboundedSender<Result<(distill::DistillResult, String), String>>,
+    embedder: &Option<Arc<Embedder>>,
+) {
+    app.set_phase(AppPhase::Distilling);
+
+    // Without an embedder, distill falls back to keyword-only retrieval
+    let embedder = embedder.clone().filter(|_| !distill::keyword_only_forced());
+
+    let budget = app.budget;
+    let model = app.model_name.clone();
+    let tx = distill_tx.clone();
+    tokio::spawn(async move {
+        let store = match db::open_store().await {
+            Ok(c) => c,
+            Err(e) => {
+                let _ = tx.send(Err(e.to_string()));
+                return;
+            }
+        };
+        match distill::distill(
+            &query,
+            embedder.as_deref(),
+            &[&store],
+            budget,
+            distill::BudgetUnit::Tokens,
+            None,
+            None,
+            None,
+            false,
+            Some(&model),
+            None,
+        )
+        .await
+        {
+            Ok(result) => {
+                let _ = tx.send(Ok((result, query)));
+            }
+            Err(e) => {
+                let _ = tx.send(Err(e.to_string()));
+            }
+        }
+    });
+}
+
+/// Senders for work the key handler spawns in the background
+struct Channels<'a> {
+    distill: &'a mpsc::UnboundedSender<Result<(distill::DistillResult, String), String>>,
+    model: &'a mpsc::UnboundedSender<Result<String, String>>,
+    add: &'a mpsc::UnboundedSender<AddUpdate>,
+}
+
+/// Slash commands typed into the input box
+fn run_command(app: &mut App, input: &str, channels: &Channels, embedder: &Option<Arc<Embedder>>) {
+    let (command, arg) = match input.split_once(char::is_whitespace) {
+        Some((command, arg)) => (command, Some(arg.trim()).filter(|a| !a.is_empty())),
+        None => (input, None),
+    };
+    match command {
+        "/save" => save_transcript(app, arg),
+        "/add" => match arg {
+            Some(path) => start_add(app, path, channels.add, embedder),
+            None => app.push_message(Role::System, "Usage: /add <file or directory>".into(), None),
+        },
+        "/model" => match arg {
+            Some(name) => switch_model(name.to_string(), channels.model.clone()),
+            None => app.push_message(
+                Role::System,
+                format!(
+                    "Current model: {}. Use /model <name> to switch.",
+                    app.model_name
+                ),
+                None,
+            ),
+        },
+        _ => app.push_message(
+            Role::System,
+            format!(
+                "Unknown command {command}. Available: /add <path>, /save [path], /model <name>"
+            ),
+            None,
+        ),
+    }
+}
+
+/// Ingest a file or directory in the background, reporting on `tx`
+fn start_add(
+    app: &mut App,
+    target: &str,
+    tx: &mpsc::UnboundedSender<AddUpdate>,
+    embedder: &Option<Arc<Embedder>>,
+) {
+    if app.ingest_progress.is_some() {
+        app.push_message(Role::System, "Another /add is still running.".into(), None);
+        return;
+    }
+    let Some(embedder) = embedder.clone() else {
+        app.push_message(
+            Role::System,
+            "Embedder not available — cannot add documents.".into(),
+            None,
+        );
+        return;
+    };
+
+    let path = std::path::PathBuf::from(target);
+    app.ingest_progress = Some(format!("Adding {target}"));
+    let tx = tx.clone();
+    tokio::spawn(async move {
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+        let forward_tx = tx.clone();
+        let forward = tokio::spawn(async move {
+            while let Some(event) = progress_rx.recv().await {
+                let _ = forward_tx.send(AddUpdate::Progress(event));
+            }
+        });
+
+        let progress = ingest::Progress::Channel(progress_tx);
+        let result = add_documents(&path, &embedder, &progress).await;
+        drop(progress);
+        let _ = forward.await;
+        let _ = tx.send(AddUpdate::Done(result.map_err(|e| format!("{e:#}"))));
+    });
+}
+
+/// Ingest one file or every supported file under a directory; returns a summary line
+async fn add_documents(
+    path: &std::path::Path,
+    embedder: &Embedder,
+    progress: &ingest::Progress,
+) -> Result<String> {
+    if !path.exists() {
+        anyhow::bail!("File not found: {}", path.display());
+    }
+    let files = if path.is_dir() {
+        ingest::collect_documents(path)?
+    } else {
+        vec![path.to_path_buf()]
+    };
+    if files.is_empty() {
+        anyhow::bail!("No supported documents found in {}", path.display());
+    }
+
+    let mut store = db::open_store().await?;
+    let (mut chunks, mut added, mut skipped, mut failed) = (0, 0, 0, Vec::new());
+    for file in &files {
+        match ingest::ingest_file(file, embedder, &mut store, false, &[], progress).await {
+            Ok(ingest::IngestOutcome::Ingested(n)) => {
+                chunks += n;
+                added += 1;
+            }
+            Ok(_) => skipped += 1,
+            Err(e) => failed.push(format!("{}: {e:#}", ingest::document_name(file))),
+        }
+    }
+
+    if files.len() == 1 && !failed.is_empty() {
+        anyhow::bail!("{}", failed.remove(0));
+    }
+    let mut summary = format!("Added {added} document(s), {chunks} chunks");
+    if skipped > 0 {
+        summary.push_str(&format!("; {skipped} already indexed"));
+    }
+    if !failed.is_empty() {
+        summary.push_str(&format!(
+            "; {} failed ({})",
+            failed.len(),
+            failed.join("; ")
+        ));
+    }
+    Ok(summary)
+}
+
+/// Check `name` against the backend's models before switching to it
+fn switch_model(name: String, tx: mpsc::UnboundedSender<Result<String, String>>) {
+    tokio::spawn(async move {
+        let result = match provider::backend().list_models().await {
+            Ok(models) if models.iter().any(|m| provider::is_same_model(m, &name)) => Ok(name),
+            Ok(models) => Err(format!(
+                "Unknown model '{name}'. Available: {}",
+                models.join(", ")
+            )),
+            Err(e) => Err(format!("Could not list models: {e:#}")),
+        };
+        let _ = tx.send(result);
+    });
+}
+
+fn save_transcript(app: &mut App, path: Option<&str>) {
+    let message = match transcript::save(app, path) {
+        Ok(path) => format!("Transcript saved to {}", path.display()),
+        Err(e) => format!("Save failed: {e:#}"),
+    };
+    app.push_message(Role::System, message, None);
+}
+
+/// Mouse wheel scrolls whichever of the message history or context panel it is over
+fn handle_mouse(app: &mut App, mouse: MouseEvent, (messages, context): (Rect, Option<Rect>)) {
+    let at = Position::new(mouse.column, mouse.row);
+    if messages.contains(at) {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => app.scroll_up(),
+            MouseEventKind::ScrollDown => app.scroll_down(),
+            _ => {}
+        }
+    } else if context.is_some_and(|area| area.contains(at)) {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => app.scroll_context_up(),
+            MouseEventKind::ScrollDown => app.scroll_context_down(),
+            _ => {}
+        }
+    }
+}
+
 fn handle_key(
     app: &mut App,
     key: crossterm::event::KeyEvent,
-    _llm_tx: &mpsc::UnboundedSender<provider::StreamEvent>,
-    distill_tx: &mpsc::UnboundedSender<Result<(distill::DistillResult, String), String>>,
+    channels: &Channels,
     embedder: &Option<Arc<Embedder>>,
 ) {
     // Ctrl+C or Esc → quit
@@ -152,46 +466,72 @@ fn handle_key(
         return;
     }
 
+    // Ctrl+O → toggle the retrieved-context panel
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('o') {
+        app.toggle_context();
+        return;
+    }
+
+    // Ctrl+S → save the transcript
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('s') {
+        save_transcript(app, None);
+        return;
+    }
+
+    // Ctrl+Y → copy the last answer
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('y') {
+        let notice = match app.last_answer().map(clipboard::copy) {
+            None => "Nothing to copy yet".to_string(),
+            Some(Ok(via)) => format!("Copied last answer via {via}"),
+            Some(Err(e)) => format!("Copy failed: {e}"),
+        };
+        app.flash(notice);
+        return;
+    }
+
     match app.phase {
         AppPhase::Idle => match key.code {
+            // Shift+Enter (where the terminal reports it) or Alt+Enter → newline
+            KeyCode::Enter
+                if key
+                    .modifiers
+                    .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+            {
+                app.insert_char('\n')
+            }
             KeyCode::Enter => {
                 let query = app.take_input().trim().to_string();
                 if query.is_empty() {
                     return;
                 }
+                if query.starts_with('/') {
+                    app.record_history(&query);
+                    run_command(app, &query, channels, embedder);
+                    return;
+                }
 
+                app.record_history(&query);
                 app.push_message(Role::User, query.clone(), None);
-                app.phase = AppPhase::Distilling;
-
-                let Some(embedder) = embedder.clone() else {
-                    app.push_message(
-                        Role::System,
-                        "Embedder not available — cannot distill.".into(),
-                        None,
-                    );
-                    app.phase = AppPhase::Idle;
-                    return;
-                };
-
-                let budget = app.budget;
-                let tx = distill_tx.clone();
-                tokio::spawn(async move {
-                    let store = match db::open_store().await {
-                        Ok(c) => c,
-                        Err(e) => {
-                            let _ = tx.send(Err(e.to_string()));
-                            return;
-                        }
-                    };
-                    match distill::distill(&query, &embedder, &store, budget).await {
-                        Ok(result) => {
-                            let _ = tx.send(Ok((result, query)));
-                        }
-                        Err(e) => {
-                            let _ = tx.send(Err(e.to_string()));
-                        }
-                    }
-                });
+                start_query(app, query, channels.distill, embedder);
+            }
+            // Ctrl+R → answer the last question again, e.g. after /model
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                match app.take_last_query() {
+                    Some(query) => start_query(app, query, channels.distill, embedder),
+                    None => app.flash("Nothing to regenerate yet"),
+                }
+            }
+            // Word-wise editing: Ctrl+←/→ or Alt+b/f, Ctrl+W deletes the previous word
+            KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => app.move_word_left(),
+            KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.move_word_right()
+            }
+            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::ALT) => app.move_word_left(),
+            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::ALT) => {
+                app.move_word_right()
+            }
+            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.delete_word_before()
             }
             KeyCode::Char(c) => app.insert_char(c),
             KeyCode::Backspace => app.delete_char_before(),
@@ -199,21 +539,22 @@ fn handle_key(
             KeyCode::Right => app.move_cursor_right(),
             KeyCode::Home => app.move_cursor_home(),
             KeyCode::End => app.move_cursor_end(),
-            KeyCode::PageUp => {
-                app.scroll_offset = app.scroll_offset.saturating_add(5);
-            }
-            KeyCode::PageDown => {
-                app.scroll_offset = app.scroll_offset.saturating_sub(5);
-            }
+            // Move between input lines first, then through history
+            KeyCode::Up if !app.move_cursor_line(true) => app.history_prev(),
+            KeyCode::Down if !app.move_cursor_line(false) => app.history_next(),
+            KeyCode::PageUp => app.scroll_up(),
+            KeyCode::PageDown => app.scroll_down(),
             _ => {}
         },
         AppPhase::Distilling | AppPhase::Streaming => match key.code {
-            KeyCode::PageUp => {
-                app.scroll_offset = app.scroll_offset.saturating_add(5);
-            }
-            KeyCode::PageDown => {
-                app.scroll_offset = app.scroll_offset.saturating_sub(5);
+            KeyCode::Char('x')
+                if key.modifiers.contains(KeyModifiers::CONTROL)
+                    && app.phase == AppPhase::Streaming =>
+            {
+                app.stop_generation()
             }
+            KeyCode::PageUp => app.scroll_up(),
+            KeyCode::PageDown => app.scroll_down(),
             _ => {}
         },
     }
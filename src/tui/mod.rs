@@ -1,34 +1,79 @@
 mod app;
+mod clipboard;
 mod event;
+mod transcript;
 mod ui;
 
 use anyhow::Result;
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{
+        DisableMouseCapture, EnableMouseCapture, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
+use ratatui::{backend::CrosstermBackend, Terminal, TerminalOptions, Viewport};
 use std::io;
 
 use crate::core::provider;
 
-/// Entry point for `ghost-lib chat`.
-pub async fn cmd_chat(model: Option<&str>, budget: Option<usize>) -> Result<()> {
+/// Whether TERM says the terminal can't be trusted with the alternate screen
+/// (Windows consoles don't set TERM at all)
+fn dumb_terminal() -> bool {
+    match std::env::var("TERM") {
+        Ok(term) => term.is_empty() || term == "dumb",
+        Err(_) => cfg!(unix),
+    }
+}
+
+/// Entry point for `ghost-lib chat`. With `simple` (or on a dumb terminal) the
+/// UI is drawn inline in the normal screen, without mouse capture.
+pub async fn cmd_chat(model: Option<&str>, budget: Option<usize>, simple: bool) -> Result<()> {
     let model_name = provider::active_model_name(model);
+    let simple = simple || dumb_terminal();
 
     // Set up terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    if !simple {
+        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    }
+    // Lets terminals that support it report Shift+Enter distinctly from Enter
+    let enhanced_keys = supports_keyboard_enhancement().unwrap_or(false);
+    if enhanced_keys {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let mut terminal = if simple {
+        // As tall as the screen, so the layout matches the full-screen UI
+        let (_, rows) = crossterm::terminal::size()?;
+        Terminal::with_options(
+            backend,
+            TerminalOptions {
+                viewport: Viewport::Inline(rows),
+            },
+        )?
+    } else {
+        Terminal::new(backend)?
+    };
 
     // Install panic hook to restore terminal on crash
     let default_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |info| {
         let _ = disable_raw_mode();
-        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
+        if enhanced_keys {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+        if !simple {
+            let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
+        }
         default_hook(info);
     }));
 
@@ -40,11 +85,19 @@ pub async fn cmd_chat(model: Option<&str>, budget: Option<usize>) -> Result<()>
 
     // Restore terminal
     disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    if enhanced_keys {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
+    if simple {
+        // Leave the last frame on screen and continue below it
+        println!();
+    } else {
+        execute!(
+            terminal.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture
+        )?;
+    }
     terminal.show_cursor()?;
 
     result
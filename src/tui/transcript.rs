@@ -0,0 +1,130 @@
+/// Markdown export of a chat session (`/save`, Ctrl+S).
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::app::{App, Role};
+
+/// Write the session to `path`, or to `ghost-chat-<timestamp>.md` in the
+/// working directory, returning where it went
+pub fn save(app: &App, path: Option<&str>) -> Result<PathBuf> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = match path {
+        Some(p) => PathBuf::from(p),
+        None => PathBuf::from(format!("ghost-chat-{}.md", file_stamp(secs))),
+    };
+    std::fs::write(&path, render(app, secs))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+fn render(app: &App, secs: u64) -> String {
+    let mut out = format!(
+        "# Ghost Librarian chat — {}\n\nModel: `{}`\n",
+        human_stamp(secs),
+        app.model_name
+    );
+
+    for msg in &app.messages {
+        match msg.role {
+            Role::User => {
+                out.push_str(&format!("\n## {}\n", msg.content.replace('\n', " ")));
+            }
+            Role::Assistant => {
+                if let Some(stats) = &msg.stats {
+                    let ranking = if stats.keyword_only {
+                        "keyword fallback".to_string()
+                    } else {
+                        format!("top score {:.2}", stats.top_score)
+                    };
+                    out.push_str(&format!(
+                        "\n*chunks: {}→{} dedup | {:.1}% compressed | {ranking}*\n",
+                        stats.chunks_retrieved, stats.after_dedup, stats.compression_pct,
+                    ));
+                    if stats.low_relevance {
+                        out.push_str(
+                            "\n> **Low relevance:** the answer may not come from your documents.\n",
+                        );
+                    }
+                }
+                out.push_str(&format!("\n{}\n", msg.content.trim_end()));
+            }
+            Role::System => {
+                out.push_str(&format!("\n> {}\n", msg.content.replace('\n', "\n> ")));
+            }
+        }
+    }
+    out
+}
+
+/// UTC civil date and time for a Unix timestamp
+fn utc(secs: u64) -> (i64, u32, u32, u64, u64, u64) {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Howard Hinnant's days-to-civil algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day, rem / 3_600, rem % 3_600 / 60, rem % 60)
+}
+
+fn file_stamp(secs: u64) -> String {
+    let (y, mo, d, h, mi, s) = utc(secs);
+    format!("{y:04}{mo:02}{d:02}-{h:02}{mi:02}{s:02}")
+}
+
+fn human_stamp(secs: u64) -> String {
+    let (y, mo, d, h, mi, _) = utc(secs);
+    format!("{y:04}-{mo:02}-{d:02} {h:02}:{mi:02} UTC")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::app::DistillStats;
+
+    #[test]
+    fn test_utc_stamps() {
+        assert_eq!(file_stamp(0), "19700101-000000");
+        // 2024-02-29 13:45:07 UTC
+        assert_eq!(file_stamp(1_709_214_307), "20240229-134507");
+        assert_eq!(human_stamp(1_709_214_307), "2024-02-29 13:45 UTC");
+    }
+
+    #[test]
+    fn test_render_transcript() {
+        let mut app = App::new("llama3".into(), None);
+        app.push_message(Role::User, "What is distillation?".into(), None);
+        app.push_message(
+            Role::Assistant,
+            "Compressing context.\n".into(),
+            Some(DistillStats {
+                chunks_retrieved: 12,
+                after_dedup: 5,
+                compression_pct: 41.0,
+                top_score: 0.82,
+                low_relevance: false,
+                keyword_only: false,
+            }),
+        );
+        let md = render(&app, 0);
+        assert_eq!(
+            md,
+            "# Ghost Librarian chat — 1970-01-01 00:00 UTC\n\nModel: `llama3`\n\
+             \n## What is distillation?\n\
+             \n*chunks: 12→5 dedup | 41.0% compressed | top score 0.82*\n\
+             \nCompressing context.\n"
+        );
+    }
+}
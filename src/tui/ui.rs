@@ -6,6 +6,9 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, Wrap},
     Frame,
 };
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use super::app::{App, AppPhase, Role};
 
@@ -16,6 +19,7 @@ const GREEN: Color = Color::Rgb(0x50, 0xdc, 0x82);
 const AMBER: Color = Color::Rgb(0xdc, 0xaa, 0x50);
 const DIM: Color = Color::Rgb(0x60, 0x60, 0x70);
 const BG: Color = Color::Rgb(0x1a, 0x1a, 0x2e);
+const CODE: Color = Color::Rgb(0xa0, 0xa0, 0xb4);
 
 const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
 
@@ -27,19 +31,46 @@ pub fn draw(f: &mut Frame, app: &App) {
     let bg_block = Block::default().style(Style::default().bg(BG));
     f.render_widget(bg_block, area);
 
-    // 4-section vertical layout: header (3) | messages (flex) | input (3) | hints (1)
-    let chunks = Layout::vertical([
+    let chunks = layout(area, app.input_line_count());
+
+    draw_header(f, app, chunks[0]);
+    let (messages, context) = split_messages(chunks[1], app.show_context);
+    draw_messages(f, app, messages);
+    if let Some(context) = context {
+        draw_context(f, app, context);
+    }
+    draw_input(f, app, chunks[2]);
+    draw_hints(f, app, chunks[3]);
+}
+
+/// Most input lines shown at once; longer input scrolls to follow the cursor
+const MAX_INPUT_LINES: usize = 8;
+
+/// 4-section vertical layout: header (3) | messages (flex) | input (3+) | hints (1)
+fn layout(area: Rect, input_lines: usize) -> [Rect; 4] {
+    let input_height = input_lines.clamp(1, MAX_INPUT_LINES) as u16 + 2;
+    Layout::vertical([
         Constraint::Length(3),
         Constraint::Min(1),
-        Constraint::Length(3),
+        Constraint::Length(input_height),
         Constraint::Length(1),
     ])
-    .split(area);
+    .areas(area)
+}
 
-    draw_header(f, app, chunks[0]);
-    draw_messages(f, app, chunks[1]);
-    draw_input(f, app, chunks[2]);
-    draw_hints(f, app, chunks[3]);
+/// Messages pane, plus the context side panel when it is open
+fn split_messages(area: Rect, show_context: bool) -> (Rect, Option<Rect>) {
+    if !show_context {
+        return (area, None);
+    }
+    let [messages, context] =
+        Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(area);
+    (messages, Some(context))
+}
+
+/// Where the message history and context panel are drawn for a terminal of the given size
+pub fn scroll_areas(area: Rect, app: &App) -> (Rect, Option<Rect>) {
+    split_messages(layout(area, app.input_line_count())[1], app.show_context)
 }
 
 // ── Header ──────────────────────────────────────────────────────
@@ -116,25 +147,51 @@ fn draw_messages(f: &mut Frame, app: &App, area: Rect) {
 
         match msg.role {
             Role::User => {
-                lines.push(Line::from(vec![
-                    Span::styled(
-                        " > ",
-                        Style::default().fg(CYAN).add_modifier(Modifier::BOLD),
-                    ),
-                    Span::styled(&msg.content, Style::default().fg(CYAN)),
-                ]));
+                for (i, text_line) in msg.content.lines().enumerate() {
+                    let prefix = if i == 0 { " > " } else { "   " };
+                    lines.push(Line::from(vec![
+                        Span::styled(
+                            prefix,
+                            Style::default().fg(CYAN).add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(text_line, Style::default().fg(CYAN)),
+                    ]));
+                }
             }
             Role::Assistant => {
+                let streaming = app.phase == AppPhase::Streaming
+                    && std::ptr::eq(msg as *const _, app.messages.last().unwrap() as *const _);
+
                 // Stats line if present
                 if let Some(stats) = &msg.stats {
+                    let usage = msg
+                        .usage
+                        .and_then(|u| u.summary())
+                        .map(|u| format!(" | {u}"))
+                        .unwrap_or_default();
+                    let ranking = if stats.keyword_only {
+                        "keyword fallback".to_string()
+                    } else {
+                        format!("top {:.2}", stats.top_score)
+                    };
                     let stats_text = format!(
-                        " [chunks: {}→{} dedup | {:.1}% compressed]",
-                        stats.chunks_retrieved, stats.after_dedup, stats.compression_pct
+                        " [chunks: {}→{} dedup | {:.1}% compressed | {ranking}{usage}]",
+                        stats.chunks_retrieved, stats.after_dedup, stats.compression_pct,
                     );
-                    lines.push(Line::from(Span::styled(
-                        stats_text,
-                        Style::default().fg(GREEN),
-                    )));
+                    let mut spans = vec![Span::styled(stats_text, Style::default().fg(GREEN))];
+                    if stats.low_relevance {
+                        spans.push(Span::styled(
+                            " ⚠ low relevance — answer may not come from your documents",
+                            Style::default().fg(AMBER).add_modifier(Modifier::BOLD),
+                        ));
+                    }
+                    if let Some(elapsed) = app.phase_elapsed().filter(|_| streaming) {
+                        spans.push(Span::styled(
+                            format!(" ({})", format_elapsed(elapsed)),
+                            Style::default().fg(DIM),
+                        ));
+                    }
+                    lines.push(Line::from(spans));
                 }
 
                 lines.push(Line::from(Span::styled(
@@ -142,27 +199,17 @@ fn draw_messages(f: &mut Frame, app: &App, area: Rect) {
                     Style::default().fg(PURPLE).add_modifier(Modifier::BOLD),
                 )));
 
-                // Content lines — append cursor block if still streaming
-                let content = if app.phase == AppPhase::Streaming
-                    && std::ptr::eq(msg as *const _, app.messages.last().unwrap() as *const _)
-                {
-                    format!("{}█", msg.content)
-                } else {
-                    msg.content.clone()
-                };
+                lines.extend(render_markdown(&msg.content));
 
-                for text_line in content.lines() {
-                    lines.push(Line::from(Span::styled(
-                        format!(" {text_line}"),
-                        Style::default().fg(Color::White),
-                    )));
-                }
-                // If content is empty (streaming just started), show cursor
-                if content.is_empty() {
-                    lines.push(Line::from(Span::styled(
-                        " █",
-                        Style::default().fg(Color::White),
-                    )));
+                // Streaming cursor goes after the rendered text so it can't split markup
+                if streaming {
+                    let cursor = Span::styled("█", Style::default().fg(Color::White));
+                    match lines.last_mut() {
+                        Some(last) if !msg.content.is_empty() && !msg.content.ends_with('\n') => {
+                            last.spans.push(cursor)
+                        }
+                        _ => lines.push(Line::from(vec![Span::raw(" "), cursor])),
+                    }
                 }
             }
             Role::System => {
@@ -185,22 +232,194 @@ fn draw_messages(f: &mut Frame, app: &App, area: Rect) {
             ),
             Span::styled("Distilling context", Style::default().fg(AMBER)),
             Span::styled(spinning_dots(app.tick_count), Style::default().fg(AMBER)),
+            Span::styled(
+                app.phase_elapsed()
+                    .map(|elapsed| format!(" ({})", format_elapsed(elapsed)))
+                    .unwrap_or_default(),
+                Style::default().fg(DIM),
+            ),
         ]));
     }
 
+    // Background `/add` progress
+    if let Some(progress) = &app.ingest_progress {
+        let spinner_char = SPINNER[(app.tick_count as usize / 2) % SPINNER.len()];
+        lines.push(Line::raw(""));
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!(" {spinner_char} "),
+                Style::default().fg(CYAN).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(progress.clone(), Style::default().fg(CYAN)),
+        ]));
+    }
+
+    // Pre-wrapped so long URLs break at the pane edge and the scroll math counts screen rows
+    let width = inner_area.width as usize;
+    let lines: Vec<Line> = lines
+        .into_iter()
+        .flat_map(|line| wrap_line(line, width))
+        .collect();
+
     let total_lines = lines.len() as u16;
     let visible = inner_area.height;
     let max_scroll = total_lines.saturating_sub(visible);
     let scroll = max_scroll.saturating_sub(app.scroll_offset);
 
     let messages = Paragraph::new(lines)
-        .wrap(Wrap { trim: false })
         .scroll((scroll, 0))
         .style(Style::default().bg(BG));
 
     f.render_widget(messages, inner_area);
 }
 
+/// Elapsed time for the busy indicators: `3.4s`, or `2m 05s` past a minute
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{:.1}s", elapsed.as_secs_f64())
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// Wrap a line to `width` columns, at whitespace where possible and between
+/// graphemes for tokens wider than the pane (URLs, identifiers, unspaced CJK)
+fn wrap_line(line: Line<'_>, width: usize) -> Vec<Line<'static>> {
+    let line_style = line.style;
+    let graphemes: Vec<(&str, Style)> = line
+        .spans
+        .iter()
+        .flat_map(|span| span.content.graphemes(true).map(move |g| (g, span.style)))
+        .collect();
+    if width == 0 || graphemes.iter().map(|(g, _)| g.width()).sum::<usize>() <= width {
+        return vec![owned_line(&graphemes, line_style)];
+    }
+
+    // Runs of whitespace and non-whitespace, as grapheme index ranges
+    let mut tokens: Vec<(usize, usize)> = Vec::new();
+    for (i, (g, _)) in graphemes.iter().enumerate() {
+        let space = g.chars().all(char::is_whitespace);
+        match tokens.last_mut() {
+            Some((start, end)) if graphemes[*start].0.chars().all(char::is_whitespace) == space => {
+                *end = i + 1
+            }
+            _ => tokens.push((i, i + 1)),
+        }
+    }
+
+    let mut rows: Vec<Vec<(&str, Style)>> = vec![Vec::new()];
+    let mut used = 0;
+    for (start, end) in tokens {
+        let token = &graphemes[start..end];
+        let token_width: usize = token.iter().map(|(g, _)| g.width()).sum();
+        let space = token[0].0.chars().all(char::is_whitespace);
+
+        if used + token_width <= width {
+            rows.last_mut().unwrap().extend_from_slice(token);
+            used += token_width;
+        } else if space {
+            // Whitespace at a break is dropped
+            rows.push(Vec::new());
+            used = 0;
+        } else {
+            if used > 0 {
+                rows.push(Vec::new());
+                used = 0;
+            }
+            for &(g, style) in token {
+                let w = g.width();
+                if used + w > width && used > 0 {
+                    rows.push(Vec::new());
+                    used = 0;
+                }
+                rows.last_mut().unwrap().push((g, style));
+                used += w;
+            }
+        }
+    }
+    rows.iter().map(|row| owned_line(row, line_style)).collect()
+}
+
+/// Rebuild a line from styled graphemes, merging neighbours that share a style
+fn owned_line(graphemes: &[(&str, Style)], style: Style) -> Line<'static> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    for &(g, s) in graphemes {
+        match spans.last_mut() {
+            Some(last) if last.style == s => last.content.to_mut().push_str(g),
+            _ => spans.push(Span::styled(g.to_string(), s)),
+        }
+    }
+    Line::from(spans).style(style)
+}
+
+// ── Retrieved context panel ─────────────────────────────────────
+fn draw_context(f: &mut Frame, app: &App, area: Rect) {
+    let mut lines: Vec<Line> = Vec::new();
+    let title = match app.last_context() {
+        None => {
+            lines.push(Line::from(Span::styled(
+                " No answer yet — ask something first.",
+                Style::default().fg(DIM),
+            )));
+            " Context ".to_string()
+        }
+        Some(ctx) => {
+            lines.push(Line::from(Span::styled(
+                " Sources:",
+                Style::default().fg(PURPLE).add_modifier(Modifier::BOLD),
+            )));
+            for (i, src) in ctx.sources.iter().enumerate() {
+                let page = src.page.map(|p| format!(" p.{p}")).unwrap_or_default();
+                lines.push(Line::from(Span::styled(
+                    format!(
+                        "  [{}] {}{page} — {} ({:.3})",
+                        i + 1,
+                        src.filename,
+                        src.section,
+                        src.score
+                    ),
+                    Style::default().fg(GREEN),
+                )));
+            }
+
+            // Packed chunks are "[label] text" blocks separated by blank lines
+            for chunk in ctx.text.split("\n\n") {
+                lines.push(Line::raw(""));
+                let (label, body) = match chunk.strip_prefix('[').and_then(|c| c.split_once("] ")) {
+                    Some((label, body)) => (format!(" [{label}]"), body),
+                    None => (String::new(), chunk),
+                };
+                if !label.is_empty() {
+                    lines.push(Line::from(Span::styled(
+                        label,
+                        Style::default().fg(AMBER).add_modifier(Modifier::BOLD),
+                    )));
+                }
+                for text_line in body.lines() {
+                    lines.push(Line::from(Span::styled(
+                        format!(" {text_line}"),
+                        Style::default().fg(Color::White),
+                    )));
+                }
+            }
+            format!(" Context ({} chunks) ", ctx.sources.len())
+        }
+    };
+
+    let block = Block::default()
+        .title(Span::styled(title, Style::default().fg(PURPLE)))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(PURPLE))
+        .style(Style::default().bg(BG));
+
+    let panel = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((app.context_scroll, 0));
+    f.render_widget(panel, area);
+}
+
 // ── Input bar ───────────────────────────────────────────────────
 fn draw_input(f: &mut Frame, app: &App, area: Rect) {
     let block = Block::default()
@@ -210,36 +429,70 @@ fn draw_input(f: &mut Frame, app: &App, area: Rect) {
     let inner = block.inner(area);
     f.render_widget(block, area);
 
-    let prompt_span = Span::styled("> ", Style::default().fg(CYAN).add_modifier(Modifier::BOLD));
-    let input_span = Span::styled(&app.input, Style::default().fg(Color::White));
-
-    let input_line = if app.input.is_empty() && app.phase == AppPhase::Idle {
-        Line::from(vec![
-            prompt_span,
+    let prompt_style = Style::default().fg(CYAN).add_modifier(Modifier::BOLD);
+    let input_lines: Vec<Line> = if app.input.is_empty() && app.phase == AppPhase::Idle {
+        vec![Line::from(vec![
+            Span::styled("> ", prompt_style),
             Span::styled("Type your question...", Style::default().fg(DIM)),
-        ])
+        ])]
     } else {
-        Line::from(vec![prompt_span, input_span])
+        // `split('\n')` rather than `lines()` so a trailing newline still gets its own row
+        app.input
+            .split('\n')
+            .enumerate()
+            .map(|(i, text)| {
+                let prefix = if i == 0 { "> " } else { "  " };
+                Line::from(vec![
+                    Span::styled(prefix, prompt_style),
+                    Span::styled(text, Style::default().fg(Color::White)),
+                ])
+            })
+            .collect()
     };
 
-    let input_widget = Paragraph::new(input_line).style(Style::default().bg(BG));
+    // Keep the cursor's line in view when the input is taller than the box
+    let (cursor_line, cursor_col) = app.cursor_line_col();
+    let scroll = (cursor_line + 1).saturating_sub(inner.height as usize) as u16;
+
+    let input_widget = Paragraph::new(input_lines)
+        .style(Style::default().bg(BG))
+        .scroll((scroll, 0));
     f.render_widget(input_widget, inner);
 
     // Cursor position: "> " prefix is 2 chars wide
     if app.phase == AppPhase::Idle {
-        let cursor_x = inner.x + 2 + app.cursor_pos as u16;
-        let cursor_y = inner.y;
+        let cursor_x = inner.x + 2 + cursor_col as u16;
+        let cursor_y = inner.y + cursor_line as u16 - scroll;
         f.set_cursor_position((cursor_x, cursor_y));
     }
 }
 
 // ── Keybinding hints bar ────────────────────────────────────────
-fn draw_hints(f: &mut Frame, _app: &App, area: Rect) {
+fn draw_hints(f: &mut Frame, app: &App, area: Rect) {
+    if let Some(notice) = app.active_notice() {
+        let line = Line::from(Span::styled(
+            format!(" {notice}"),
+            Style::default().fg(GREEN),
+        ));
+        f.render_widget(Paragraph::new(line).style(Style::default().bg(BG)), area);
+        return;
+    }
+
+    let (key, action) = match app.phase {
+        AppPhase::Streaming => (" Ctrl+X", " Stop "),
+        _ => (" ↑/↓", " History "),
+    };
     let hints = Line::from(vec![
         Span::styled(" Enter", Style::default().fg(CYAN)),
         Span::styled(" Send ", Style::default().fg(DIM)),
         Span::styled(" Esc", Style::default().fg(CYAN)),
         Span::styled(" Quit ", Style::default().fg(DIM)),
+        Span::styled(key, Style::default().fg(CYAN)),
+        Span::styled(action, Style::default().fg(DIM)),
+        Span::styled(" Ctrl+O", Style::default().fg(CYAN)),
+        Span::styled(" Context ", Style::default().fg(DIM)),
+        Span::styled(" Ctrl+Y", Style::default().fg(CYAN)),
+        Span::styled(" Copy ", Style::default().fg(DIM)),
         Span::styled(" PgUp/Dn", Style::default().fg(CYAN)),
         Span::styled(" Scroll ", Style::default().fg(DIM)),
     ]);
@@ -248,8 +501,180 @@ fn draw_hints(f: &mut Frame, _app: &App, area: Rect) {
     f.render_widget(widget, area);
 }
 
+// ── Markdown ────────────────────────────────────────────────────
+/// Render an answer's markdown: headings, bullets, fenced code and inline
+/// bold/italic/code. Anything unrecognised is shown as written.
+fn render_markdown(text: &str) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+
+    for raw in text.lines() {
+        let trimmed = raw.trim_start();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            lines.push(Line::from(Span::styled(
+                format!("   {raw}"),
+                Style::default().fg(CODE).add_modifier(Modifier::DIM),
+            )));
+            continue;
+        }
+
+        let indent = raw.len() - trimmed.len();
+        let base = Style::default().fg(Color::White);
+        let mut spans = vec![Span::raw(" ")];
+
+        if let Some(heading) = trimmed
+            .strip_prefix("### ")
+            .or_else(|| trimmed.strip_prefix("## "))
+            .or_else(|| trimmed.strip_prefix("# "))
+        {
+            spans.extend(render_inline(
+                heading,
+                base.fg(PURPLE).add_modifier(Modifier::BOLD),
+            ));
+        } else if let Some(item) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|bullet| trimmed.strip_prefix(bullet))
+        {
+            spans.push(Span::styled(
+                format!("{}• ", " ".repeat(indent + 1)),
+                Style::default().fg(CYAN),
+            ));
+            spans.extend(render_inline(item, base));
+        } else {
+            spans.push(Span::raw(" ".repeat(indent)));
+            spans.extend(render_inline(trimmed, base));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// Inline `**bold**`, `*italic*`/`_italic_` and `` `code` `` spans.
+/// Unclosed markers are left as literal text.
+fn render_inline(text: &str, base: Style) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let styled = match c {
+            '`' => inline_span(rest, "`", base.fg(CODE)),
+            '*' if rest.starts_with("**") => {
+                inline_span(rest, "**", base.add_modifier(Modifier::BOLD))
+            }
+            '_' if rest.starts_with("__") => {
+                inline_span(rest, "__", base.add_modifier(Modifier::BOLD))
+            }
+            // `_` only opens emphasis at a word start, so snake_case stays intact
+            '*' => inline_span(rest, "*", base.add_modifier(Modifier::ITALIC)),
+            '_' if plain.chars().last().is_none_or(|p| !p.is_alphanumeric()) => {
+                inline_span(rest, "_", base.add_modifier(Modifier::ITALIC))
+            }
+            _ => None,
+        };
+        match styled {
+            Some((span, consumed)) => {
+                if !plain.is_empty() {
+                    spans.push(Span::styled(std::mem::take(&mut plain), base));
+                }
+                spans.push(span);
+                rest = &rest[consumed..];
+            }
+            None => {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    if !plain.is_empty() {
+        spans.push(Span::styled(plain, base));
+    }
+    spans
+}
+
+/// A span delimited by `marker` at the start of `text`, and the bytes it covers
+fn inline_span(text: &str, marker: &str, style: Style) -> Option<(Span<'static>, usize)> {
+    let body = &text[marker.len()..];
+    let end = body.find(marker)?;
+    let inner = &body[..end];
+    if inner.is_empty() || inner.starts_with(' ') || inner.ends_with(' ') {
+        return None;
+    }
+    Some((
+        Span::styled(inner.to_string(), style),
+        marker.len() * 2 + end,
+    ))
+}
+
 // ── Helpers ─────────────────────────────────────────────────────
 fn spinning_dots(tick: u64) -> String {
     let n = ((tick / 5) % 4) as usize;
     ".".repeat(n)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_of(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_render_inline_markup() {
+        let base = Style::default();
+        let spans = render_inline("a **bold** and *it* with `code` in snake_case_name", base);
+        let bold = spans.iter().find(|s| s.content == "bold").unwrap();
+        assert!(bold.style.add_modifier.contains(Modifier::BOLD));
+        let italic = spans.iter().find(|s| s.content == "it").unwrap();
+        assert!(italic.style.add_modifier.contains(Modifier::ITALIC));
+        assert!(spans.iter().any(|s| s.content == "code"));
+        let joined: String = spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(joined, "a bold and it with code in snake_case_name");
+    }
+
+    #[test]
+    fn test_render_markdown_degrades_gracefully() {
+        let lines = render_markdown("- item **open\n```rust\nlet x = 1;");
+        assert_eq!(text_of(&lines[0]), "  • item **open");
+        // Unterminated fence (e.g. mid-stream) still renders as code
+        assert_eq!(text_of(&lines[1]), "   let x = 1;");
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_millis(3420)), "3.4s");
+        assert_eq!(format_elapsed(Duration::from_secs(125)), "2m 05s");
+    }
+
+    #[test]
+    fn test_wrap_line_breaks_long_url() {
+        let url = format!("https://example.com/{}", "a".repeat(60));
+        assert_eq!(url.len(), 80);
+        let line = Line::from(vec![
+            Span::raw(format!(" See {url} now")),
+            Span::styled("█", Style::default().fg(Color::White)),
+        ]);
+
+        let rows = wrap_line(line, 40);
+        let texts: Vec<String> = rows.iter().map(text_of).collect();
+        assert_eq!(texts, [" See ", &url[..40], &url[40..], "now█"]);
+        assert!(rows.iter().all(|r| r.width() <= 40));
+        // The streaming cursor keeps its own style at the very end
+        let cursor = rows.last().unwrap().spans.last().unwrap();
+        assert_eq!(cursor.content, "█");
+        assert_eq!(cursor.style.fg, Some(Color::White));
+    }
+
+    #[test]
+    fn test_wrap_line_keeps_short_lines_and_wide_chars() {
+        assert_eq!(wrap_line(Line::raw("  indented"), 40).len(), 1);
+        let rows = wrap_line(Line::raw("東京都の人口は約千四百万人です"), 10);
+        assert!(rows.len() > 1 && rows.iter().all(|r| r.width() <= 10));
+    }
+}
@@ -0,0 +1,163 @@
+use std::sync::OnceLock;
+
+/// Particles and copulas dropped when compressing Japanese.
+/// Negations (ない, ません) are deliberately absent.
+const JA_STOPWORDS: &[&str] = &[
+    "の",
+    "は",
+    "が",
+    "を",
+    "に",
+    "へ",
+    "と",
+    "で",
+    "も",
+    "や",
+    "から",
+    "まで",
+    "より",
+    "です",
+    "ます",
+    "でした",
+    "ました",
+    "である",
+    "という",
+    "など",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Kanji,
+    Hiragana,
+    Katakana,
+    Hangul,
+    /// Latin letters, digits and other non-CJK alphanumerics
+    Other,
+    Punct,
+}
+
+fn script(c: char) -> Script {
+    match c {
+        '\u{3041}'..='\u{309F}' => Script::Hiragana,
+        '\u{30A0}'..='\u{30FF}' | '\u{FF66}'..='\u{FF9F}' => Script::Katakana,
+        '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}' | '々' => {
+            Script::Kanji
+        }
+        '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' => {
+            Script::Hangul
+        }
+        c if c.is_alphanumeric() => Script::Other,
+        _ => Script::Punct,
+    }
+}
+
+pub fn is_cjk(c: char) -> bool {
+    !matches!(script(c), Script::Other | Script::Punct)
+}
+
+pub fn contains_cjk(text: &str) -> bool {
+    text.chars().any(is_cjk)
+}
+
+/// Whether `text` should take the CJK path: forced by GHOST_LANG
+/// (`ja`, `zh`, `ko` or `cjk` on, any other value off), otherwise detected
+pub fn enabled(text: &str) -> bool {
+    static FORCED: OnceLock<Option<bool>> = OnceLock::new();
+    let forced = FORCED.get_or_init(|| {
+        let lang = std::env::var("GHOST_LANG").ok()?.to_lowercase();
+        match lang.as_str() {
+            "" => None,
+            "ja" | "zh" | "ko" | "cjk" => Some(true),
+            _ => Some(false),
+        }
+    });
+    forced.unwrap_or_else(|| contains_cjk(text))
+}
+
+/// Consecutive characters of the same script
+fn runs(text: &str) -> Vec<(Script, &str)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut current = None;
+    for (i, c) in text.char_indices() {
+        let s = script(c);
+        if current.is_some_and(|cur| cur != s) {
+            runs.push((current.unwrap(), &text[start..i]));
+            start = i;
+        }
+        current = Some(s);
+    }
+    if let Some(s) = current {
+        runs.push((s, &text[start..]));
+    }
+    runs
+}
+
+/// Lowercased keyword tokens for text without spaces between words. Kanji runs
+/// become overlapping bigrams (dictionary-free CJK indexing); katakana, hangul and
+/// Latin runs stay whole; hiragana (mostly particles and inflections) is dropped.
+pub fn keyword_tokens(text: &str) -> Vec<String> {
+    let lower = text.to_lowercase();
+    let mut tokens = Vec::new();
+    for (script, run) in runs(&lower) {
+        match script {
+            Script::Kanji => {
+                let chars: Vec<char> = run.chars().collect();
+                if chars.len() == 1 {
+                    tokens.push(run.to_string());
+                } else {
+                    tokens.extend(chars.windows(2).map(|pair| pair.iter().collect()));
+                }
+            }
+            Script::Katakana | Script::Hangul | Script::Other => tokens.push(run.to_string()),
+            Script::Hiragana | Script::Punct => {}
+        }
+    }
+    tokens
+}
+
+/// Drop Japanese particles from a whitespace-delimited word, keeping punctuation
+pub fn remove_stopwords(word: &str) -> String {
+    runs(word)
+        .into_iter()
+        .filter(|(script, run)| !(*script == Script::Hiragana && JA_STOPWORDS.contains(run)))
+        .map(|(_, run)| run)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyword_tokens_japanese() {
+        let tokens = keyword_tokens("東京都の人口は約1400万人です。Tokyoはコンピュータ");
+        assert_eq!(
+            tokens,
+            [
+                "東京",
+                "京都",
+                "人口",
+                "約",
+                "1400",
+                "万人",
+                "tokyo",
+                "コンピュータ"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remove_stopwords_keeps_negation() {
+        assert_eq!(
+            remove_stopwords("東京都の人口は多くありません。"),
+            "東京都人口多くありません。"
+        );
+    }
+
+    #[test]
+    fn test_latin_text_is_not_cjk() {
+        assert!(!contains_cjk("Plain English, café."));
+        assert!(contains_cjk("ハングル 한국어"));
+    }
+}
@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::io::{Cursor, Read};
+
+/// Extract text from a `.docx` file as markdown-flavoured plain text.
+///
+/// Heading styles become `#` headings so section metadata keeps working,
+/// and tables are flattened to one pipe-separated line per row.
+pub fn extract_docx_text(bytes: &[u8]) -> Result<String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .context("Not a valid DOCX file (it may be corrupt or password-protected)")?;
+
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")
+        .context("DOCX file has no word/document.xml (corrupt or not a Word document)")?
+        .read_to_string(&mut xml)
+        .context("Failed to read DOCX document body")?;
+
+    document_xml_to_text(&xml)
+}
+
+/// Convert WordprocessingML (`word/document.xml`) to text
+fn document_xml_to_text(xml: &str) -> Result<String> {
+    let mut reader = Reader::from_str(xml);
+
+    let mut blocks: Vec<String> = Vec::new();
+    let mut para = String::new();
+    let mut heading: Option<usize> = None;
+    let mut in_text = false;
+
+    let mut table_depth = 0usize;
+    let mut table_rows: Vec<String> = Vec::new();
+    let mut row_cells: Vec<String> = Vec::new();
+    let mut cell = String::new();
+
+    loop {
+        match reader.read_event().context("Malformed DOCX document XML")? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"p" => {
+                    para.clear();
+                    heading = None;
+                }
+                b"t" => in_text = true,
+                b"tbl" => table_depth += 1,
+                b"tr" if table_depth == 1 => row_cells.clear(),
+                b"tc" if table_depth == 1 => cell.clear(),
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"pStyle" => heading = heading_level(&e),
+                b"tab" => para.push('\t'),
+                b"br" | b"cr" => para.push('\n'),
+                _ => {}
+            },
+            Event::Text(e) if in_text => {
+                para.push_str(&e.unescape().context("Malformed DOCX text run")?);
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => {
+                    let text = para.trim();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    if table_depth > 0 {
+                        if !cell.is_empty() {
+                            cell.push(' ');
+                        }
+                        cell.push_str(text);
+                    } else if let Some(level) = heading {
+                        blocks.push(format!("{} {text}", "#".repeat(level)));
+                    } else {
+                        blocks.push(text.to_string());
+                    }
+                }
+                b"tc" if table_depth == 1 => {
+                    row_cells.push(cell.trim().replace('\n', " "));
+                }
+                b"tr" if table_depth == 1 && row_cells.iter().any(|c| !c.is_empty()) => {
+                    table_rows.push(format!("| {} |", row_cells.join(" | ")));
+                }
+                b"tbl" => {
+                    table_depth = table_depth.saturating_sub(1);
+                    if table_depth == 0 && !table_rows.is_empty() {
+                        blocks.push(table_rows.join("\n"));
+                        table_rows.clear();
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(blocks.join("\n\n"))
+}
+
+/// Map a `w:pStyle` such as `Heading2` or `Title` to a markdown heading level
+fn heading_level(style: &BytesStart) -> Option<usize> {
+    let val = style.try_get_attribute("w:val").ok()??;
+    let val = val.unescape_value().ok()?.to_lowercase();
+    if val == "title" {
+        return Some(1);
+    }
+    let level: usize = val.strip_prefix("heading")?.trim().parse().ok()?;
+    Some(level.clamp(1, 6))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+<w:body>
+  <w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Overview</w:t></w:r></w:p>
+  <w:p><w:r><w:t xml:space="preserve">Ghost Librarian </w:t></w:r><w:r><w:t>runs locally &amp; offline.</w:t></w:r></w:p>
+  <w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Limits</w:t></w:r></w:p>
+  <w:tbl>
+    <w:tr><w:tc><w:p><w:r><w:t>Format</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>Max size</w:t></w:r></w:p></w:tc></w:tr>
+    <w:tr><w:tc><w:p><w:r><w:t>PDF</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>50 MB</w:t></w:r></w:p></w:tc></w:tr>
+  </w:tbl>
+</w:body>
+</w:document>"#;
+
+    #[test]
+    fn test_docx_headings_and_tables() {
+        let text = document_xml_to_text(DOC).unwrap();
+        assert_eq!(
+            text,
+            "# Overview\n\nGhost Librarian runs locally & offline.\n\n## Limits\n\n\
+             | Format | Max size |\n| PDF | 50 MB |"
+        );
+    }
+
+    #[test]
+    fn test_docx_rejects_non_zip() {
+        let err = extract_docx_text(b"definitely not a zip").unwrap_err();
+        assert!(err.to_string().contains("Not a valid DOCX"));
+    }
+}
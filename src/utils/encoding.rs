@@ -0,0 +1,150 @@
+use anyhow::{anyhow, bail, Result};
+use encoding_rs::{Encoding, EUC_JP, SHIFT_JIS, UTF_8, WINDOWS_1252};
+
+use crate::utils::cjk;
+
+/// Legacy encodings tried on text that is not UTF-8. Latin-1 text is often also
+/// valid Shift_JIS, so every candidate that decodes cleanly is scored with
+/// `implausibility` and the best one wins; ties go to the earlier entry.
+const CANDIDATES: &[&Encoding] = &[SHIFT_JIS, EUC_JP, WINDOWS_1252];
+
+/// Encoding forced by GHOST_INPUT_ENCODING (any WHATWG label, e.g. `shift_jis`, `latin1`)
+fn forced() -> Result<Option<&'static Encoding>> {
+    let Some(label) = std::env::var("GHOST_INPUT_ENCODING")
+        .ok()
+        .filter(|v| !v.is_empty())
+    else {
+        return Ok(None);
+    };
+    Encoding::for_label(label.trim().as_bytes())
+        .map(Some)
+        .ok_or_else(|| anyhow!("Unknown GHOST_INPUT_ENCODING: {label}"))
+}
+
+/// Decode a text file to UTF-8. Returns the encoding name when the input was not
+/// plain UTF-8, so callers can report the transcoding.
+pub fn decode(bytes: &[u8]) -> Result<(String, Option<&'static str>)> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        let name = (encoding != UTF_8).then(|| encoding.name());
+        return Ok((text.into_owned(), name));
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Ok((text.to_string(), None));
+    }
+
+    if let Some(encoding) = forced()? {
+        let (text, _) = encoding.decode_without_bom_handling(bytes);
+        return Ok((text.into_owned(), Some(encoding.name())));
+    }
+
+    let mut best: Option<(usize, String, &'static Encoding)> = None;
+    for &encoding in CANDIDATES {
+        let Some(text) = encoding.decode_without_bom_handling_and_without_replacement(bytes) else {
+            continue;
+        };
+        let score = implausibility(&text);
+        if best
+            .as_ref()
+            .is_none_or(|(best_score, _, _)| score < *best_score)
+        {
+            best = Some((score, text.into_owned(), encoding));
+        }
+    }
+    match best {
+        Some((_, text, encoding)) => Ok((text, Some(encoding.name()))),
+        None => bail!(
+            "Could not detect the text encoding; set GHOST_INPUT_ENCODING (e.g. shift_jis, latin1)"
+        ),
+    }
+}
+
+/// How many characters of a decode look like mojibake: CJK glued to Latin
+/// letters (`L馗ole`), symbols and control characters outside common
+/// punctuation, and runs of three or more non-ASCII Latin letters (`“Œ‹ž`).
+fn implausibility(text: &str) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let foreign = |c: Option<&char>| c.is_some_and(|c| !c.is_ascii() && !c.is_whitespace());
+    (0..chars.len())
+        .filter(|&i| {
+            let c = chars[i];
+            if c.is_ascii() || c.is_whitespace() {
+                return false;
+            }
+            let (prev, next) = (i.checked_sub(1).map(|p| &chars[p]), chars.get(i + 1));
+            if cjk::is_cjk(c) {
+                [prev, next]
+                    .iter()
+                    .any(|n| n.is_some_and(|n| n.is_ascii_alphabetic()))
+            } else if c.is_alphabetic() {
+                foreign(prev) && foreign(next)
+            } else {
+                !is_common_punctuation(c)
+            }
+        })
+        .count()
+}
+
+/// Latin-1 symbols, general punctuation and CJK/fullwidth punctuation
+fn is_common_punctuation(c: char) -> bool {
+    matches!(
+        c,
+        '\u{A0}'..='\u{BF}'
+            | '\u{2010}'..='\u{206F}'
+            | '\u{3000}'..='\u{303F}'
+            | '\u{FF01}'..='\u{FF65}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_is_untouched() {
+        assert_eq!(
+            decode("café 東京".as_bytes()).unwrap(),
+            ("café 東京".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_detects_legacy_encodings() {
+        let (sjis, _, _) = SHIFT_JIS.encode("東京都の人口");
+        assert_eq!(
+            decode(&sjis).unwrap(),
+            ("東京都の人口".to_string(), Some("Shift_JIS"))
+        );
+
+        let latin1 = b"caf\xe9 au lait, na\xefve";
+        assert_eq!(
+            decode(latin1).unwrap(),
+            ("café au lait, naïve".to_string(), Some("windows-1252"))
+        );
+    }
+
+    #[test]
+    fn test_latin1_that_is_also_valid_shift_jis() {
+        // \xe9 followed by a letter is a valid Shift_JIS pair (`une 馗ole`)
+        assert_eq!(
+            decode(b"une \xe9cole").unwrap(),
+            ("une école".to_string(), Some("windows-1252"))
+        );
+        assert_eq!(
+            decode(b"L\xe9cole est r\xe9elle").unwrap().0,
+            "Lécole est réelle"
+        );
+
+        let (sjis, _, _) = SHIFT_JIS.encode("Rustの本は2024年に出た");
+        assert_eq!(decode(&sjis).unwrap().0, "Rustの本は2024年に出た");
+    }
+
+    #[test]
+    fn test_bom_selects_encoding() {
+        let utf16 = [0xFF, 0xFE, b'h', 0, b'i', 0];
+        assert_eq!(
+            decode(&utf16).unwrap(),
+            ("hi".to_string(), Some("UTF-16LE"))
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use anyhow::{bail, Context, Result};
+use quick_xml::events::{BytesText, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+type Archive<'a> = zip::ZipArchive<Cursor<&'a [u8]>>;
+
+/// Extract an `.epub` as markdown-flavoured text, one `#` chapter per spine item.
+///
+/// Chapters are read in spine (reading) order. A chapter that has no heading of
+/// its own gets its `<title>` (or file name) as a heading so it still becomes a
+/// section; chapters without any text, such as cover images, are skipped.
+/// Chapters that are missing or malformed are skipped too, and described in the
+/// returned warnings instead of failing the whole book.
+pub fn extract_epub_text(bytes: &[u8]) -> Result<(String, Vec<String>)> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .context("Not a valid EPUB file (it may be corrupt or DRM-protected)")?;
+
+    let container = read_entry(&mut archive, "META-INF/container.xml")?;
+    let opf_path = rootfile_path(&container)?;
+    let opf = read_entry(&mut archive, &opf_path)?;
+    let base = opf_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+
+    let mut chapters = Vec::new();
+    let mut warnings = Vec::new();
+    for href in spine_hrefs(&opf)? {
+        let path = resolve_href(base, &href);
+        let chapter = read_entry(&mut archive, &path).and_then(|xhtml| xhtml_to_text(&xhtml));
+        let (title, body) = match chapter {
+            Ok(chapter) => chapter,
+            Err(e) => {
+                warnings.push(format!("skipped EPUB chapter {path}: {e:#}"));
+                continue;
+            }
+        };
+        if body.trim().is_empty() {
+            continue;
+        }
+        if body.starts_with('#') {
+            chapters.push(body);
+        } else {
+            let title = title.unwrap_or_else(|| file_stem(&href));
+            chapters.push(format!("# {title}\n\n{body}"));
+        }
+    }
+
+    if chapters.is_empty() {
+        bail!("EPUB contains no readable text chapters");
+    }
+    Ok((chapters.join("\n\n"), warnings))
+}
+
+/// Archive path of a manifest href relative to the package document's
+/// directory, with `.` and `..` segments resolved
+fn resolve_href(base: &str, href: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let joined = match href.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None if base.is_empty() => href.to_string(),
+        None => format!("{base}/{href}"),
+    };
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+fn read_entry(archive: &mut Archive, name: &str) -> Result<String> {
+    let mut entry = archive
+        .by_name(name)
+        .with_context(|| format!("EPUB is missing {name}"))?;
+    let mut data = String::new();
+    entry
+        .read_to_string(&mut data)
+        .with_context(|| format!("Failed to read {name} from EPUB"))?;
+    Ok(data)
+}
+
+/// Locate the OPF package document from `META-INF/container.xml`
+fn rootfile_path(container: &str) -> Result<String> {
+    let mut reader = Reader::from_str(container);
+    loop {
+        match reader
+            .read_event()
+            .context("Malformed EPUB container.xml")?
+        {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"rootfile" => {
+                if let Some(attr) = e.try_get_attribute("full-path")? {
+                    return Ok(attr.unescape_value()?.into_owned());
+                }
+            }
+            Event::Eof => bail!("EPUB container.xml does not name a package document"),
+            _ => {}
+        }
+    }
+}
+
+/// Resolve the spine's `itemref`s to manifest hrefs, in reading order
+fn spine_hrefs(opf: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(opf);
+    let mut manifest: HashMap<String, String> = HashMap::new();
+    let mut spine: Vec<String> = Vec::new();
+
+    loop {
+        match reader
+            .read_event()
+            .context("Malformed EPUB package document")?
+        {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"item" => {
+                    let id = e.try_get_attribute("id")?;
+                    let href = e.try_get_attribute("href")?;
+                    if let (Some(id), Some(href)) = (id, href) {
+                        manifest.insert(
+                            id.unescape_value()?.into_owned(),
+                            href.unescape_value()?.into_owned(),
+                        );
+                    }
+                }
+                b"itemref" => {
+                    if let Some(idref) = e.try_get_attribute("idref")? {
+                        spine.push(idref.unescape_value()?.into_owned());
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(spine
+        .iter()
+        .filter_map(|id| manifest.get(id))
+        .map(|href| {
+            let href = href.split('#').next().unwrap_or(href);
+            percent_encoding::percent_decode_str(href)
+                .decode_utf8_lossy()
+                .into_owned()
+        })
+        .collect())
+}
+
+/// Convert a chapter's XHTML to text, returning its `<title>` alongside
+fn xhtml_to_text(xhtml: &str) -> Result<(Option<String>, String)> {
+    let mut reader = Reader::from_str(xhtml);
+    reader.config_mut().check_end_names = false;
+
+    let mut title: Option<String> = None;
+    let mut in_title = false;
+    let mut skip_depth = 0usize;
+    let mut blocks: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    let flush = |current: &mut String, blocks: &mut Vec<String>| {
+        let text = current.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() && text.trim_start_matches('#').trim() != "" {
+            blocks.push(text);
+        }
+        current.clear();
+    };
+
+    loop {
+        match reader
+            .read_event()
+            .context("Malformed EPUB chapter XHTML")?
+        {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"script" | b"style" => skip_depth += 1,
+                b"title" => in_title = true,
+                name => {
+                    if let Some(level) = heading_level(name) {
+                        flush(&mut current, &mut blocks);
+                        current.push_str(&"#".repeat(level));
+                        current.push(' ');
+                    } else if is_block(name) {
+                        flush(&mut current, &mut blocks);
+                    }
+                }
+            },
+            Event::Empty(e) if matches!(e.local_name().as_ref(), b"br" | b"hr") => {
+                flush(&mut current, &mut blocks);
+            }
+            Event::Text(e) if skip_depth == 0 => {
+                let text = decode_text(&e);
+                if in_title {
+                    let text = text.trim();
+                    if !text.is_empty() {
+                        title = Some(text.to_string());
+                    }
+                } else {
+                    current.push_str(&text);
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"script" | b"style" => skip_depth = skip_depth.saturating_sub(1),
+                b"title" => in_title = false,
+                name if heading_level(name).is_some() || is_block(name) => {
+                    flush(&mut current, &mut blocks);
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    flush(&mut current, &mut blocks);
+
+    Ok((title, blocks.join("\n\n")))
+}
+
+fn heading_level(name: &[u8]) -> Option<usize> {
+    match name {
+        [b'h', d @ b'1'..=b'6'] => Some((d - b'0') as usize),
+        _ => None,
+    }
+}
+
+fn is_block(name: &[u8]) -> bool {
+    matches!(
+        name,
+        b"p" | b"div"
+            | b"section"
+            | b"li"
+            | b"tr"
+            | b"blockquote"
+            | b"pre"
+            | b"dt"
+            | b"dd"
+            | b"figcaption"
+    )
+}
+
+/// Unescape text, resolving the HTML entities XML parsers don't know about
+fn decode_text(text: &BytesText) -> String {
+    text.unescape_with(|entity| match entity {
+        "nbsp" => Some(" "),
+        "mdash" => Some("—"),
+        "ndash" => Some("–"),
+        "hellip" => Some("…"),
+        "lsquo" | "rsquo" => Some("'"),
+        "ldquo" | "rdquo" => Some("\""),
+        "copy" => Some("©"),
+        _ => None,
+    })
+    .map(|t| t.into_owned())
+    .unwrap_or_else(|_| String::from_utf8_lossy(text).into_owned())
+}
+
+fn file_stem(href: &str) -> String {
+    let name = href.rsplit('/').next().unwrap_or(href);
+    name.split('.').next().unwrap_or(name).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epub_spine_order() {
+        let opf = r#"<package xmlns="http://www.idpf.org/2007/opf">
+  <manifest>
+    <item id="cover" href="cover.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="text/ch2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c1" href="text/ch1.xhtml#start" media-type="application/xhtml+xml"/>
+    <item id="c3" href="text/Caf%C3%A9%20%231.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine><itemref idref="cover"/><itemref idref="c1"/><itemref idref="c2"/><itemref idref="c3"/></spine>
+</package>"#;
+        assert_eq!(
+            spine_hrefs(opf).unwrap(),
+            vec![
+                "cover.xhtml",
+                "text/ch1.xhtml",
+                "text/ch2.xhtml",
+                "text/Café #1.xhtml"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_epub_resolve_href() {
+        assert_eq!(resolve_href("", "ch1.xhtml"), "ch1.xhtml");
+        assert_eq!(
+            resolve_href("OEBPS", "text/ch1.xhtml"),
+            "OEBPS/text/ch1.xhtml"
+        );
+        assert_eq!(
+            resolve_href("OEBPS/pkg", "../text/./ch1.xhtml"),
+            "OEBPS/text/ch1.xhtml"
+        );
+        assert_eq!(resolve_href("OEBPS", "/text/ch1.xhtml"), "text/ch1.xhtml");
+    }
+
+    #[test]
+    fn test_epub_chapter_text() {
+        let xhtml = r#"<html><head><title>Chapter One</title><style>p { margin: 0 }</style></head>
+<body><h1>The Beginning</h1><p>It was a&nbsp;dark night.</p><p>Rain <em>fell</em>.</p>
+<img src="map.png"/></body></html>"#;
+        let (title, body) = xhtml_to_text(xhtml).unwrap();
+        assert_eq!(title.as_deref(), Some("Chapter One"));
+        assert_eq!(
+            body,
+            "# The Beginning\n\nIt was a dark night.\n\nRain fell."
+        );
+    }
+
+    #[test]
+    fn test_epub_image_only_chapter_is_empty() {
+        let xhtml = r#"<html><body><div><img src="cover.jpg"/></div></body></html>"#;
+        let (_, body) = xhtml_to_text(xhtml).unwrap();
+        assert!(body.is_empty());
+    }
+}
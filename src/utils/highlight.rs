@@ -0,0 +1,101 @@
+use regex::Regex;
+use std::io::IsTerminal;
+use std::ops::Range;
+
+const HIGHLIGHT: &str = "\x1b[1;33m";
+const RESET: &str = "\x1b[0m";
+
+/// Whether to colour stdout: it must be a terminal and NO_COLOR unset (https://no-color.org)
+pub fn color_enabled() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && std::io::stdout().is_terminal()
+}
+
+/// Byte ranges of case-insensitive occurrences of any of `terms`, in order
+fn match_ranges(text: &str, terms: &[String]) -> Vec<Range<usize>> {
+    if terms.is_empty() {
+        return Vec::new();
+    }
+    // Longest first, so "distillation" wins over "distill" at the same position
+    let mut terms: Vec<&String> = terms.iter().collect();
+    terms.sort_by_key(|t| std::cmp::Reverse(t.len()));
+    let alternatives: Vec<String> = terms.iter().map(|t| regex::escape(t)).collect();
+    let Ok(re) = Regex::new(&format!("(?i){}", alternatives.join("|"))) else {
+        return Vec::new();
+    };
+    re.find_iter(text).map(|m| m.range()).collect()
+}
+
+/// At most `max_chars` characters of `text` on one line, centred on the first
+/// match of `terms`, with every match inside the window highlighted when `color`
+pub fn snippet(text: &str, terms: &[String], max_chars: usize, color: bool) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let matches = match_ranges(&flat, terms);
+
+    // Leave a quarter of the window as lead-in before the first match
+    let total = flat.chars().count();
+    let first = matches
+        .first()
+        .map_or(0, |m| flat[..m.start].chars().count());
+    let start = if total <= max_chars {
+        0
+    } else {
+        first.saturating_sub(max_chars / 4).min(total - max_chars)
+    };
+    let end = (start + max_chars).min(total);
+    let byte_at = |c: usize| flat.char_indices().nth(c).map_or(flat.len(), |(i, _)| i);
+    let (mut from, to) = (byte_at(start), byte_at(end));
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+        from = to - flat[from..to].trim_start().len();
+    }
+    let mut pos = from;
+    for m in matches.iter().filter(|m| m.start >= from && m.end <= to) {
+        out.push_str(&flat[pos..m.start]);
+        if color {
+            out.push_str(HIGHLIGHT);
+            out.push_str(&flat[m.clone()]);
+            out.push_str(RESET);
+        } else {
+            out.push_str(&flat[m.clone()]);
+        }
+        pos = m.end;
+    }
+    out.push_str(&flat[pos..to]);
+    if end < total {
+        let trimmed = out.trim_end().len();
+        out.truncate(trimmed);
+        out.push('…');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet_centres_on_first_match() {
+        let text = format!(
+            "{} Context distillation keeps the   context small.",
+            "filler ".repeat(40)
+        );
+        let terms = vec!["context".to_string(), "distillation".to_string()];
+
+        let plain = snippet(&text, &terms, 60, false);
+        assert_eq!(
+            plain,
+            "…filler filler Context distillation keeps the context small."
+        );
+
+        let colored = snippet(&text, &terms, 60, true);
+        assert!(colored.contains("\x1b[1;33mContext\x1b[0m \x1b[1;33mdistillation\x1b[0m"));
+
+        // No match: the start of the chunk, as before
+        assert_eq!(
+            snippet("short text", &["zzz".to_string()], 40, true),
+            "short text"
+        );
+    }
+}
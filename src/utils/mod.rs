@@ -1 +1,9 @@
+pub mod cjk;
+pub mod docx;
+pub mod encoding;
+pub mod epub;
+pub mod highlight;
+pub mod ocr;
+#[cfg(test)]
+pub(crate) mod test_util;
 pub mod text_cleaner;
@@ -0,0 +1,105 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Average non-whitespace characters per page below which a PDF counts as scanned
+const MIN_CHARS_PER_PAGE: usize = 20;
+
+/// Whether GHOST_OCR asks for scanned PDFs to be run through Tesseract
+pub fn enabled() -> bool {
+    std::env::var("GHOST_OCR").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Whether extracted PDF pages carry too little text to be a real text layer
+pub fn looks_scanned(pages: &[String]) -> bool {
+    let chars: usize = pages
+        .iter()
+        .map(|p| p.chars().filter(|c| !c.is_whitespace()).count())
+        .sum();
+    chars < MIN_CHARS_PER_PAGE * pages.len().max(1)
+}
+
+/// Tesseract language(s), e.g. `eng` or `jpn+eng` (GHOST_OCR_LANG, default eng)
+fn language() -> String {
+    std::env::var("GHOST_OCR_LANG")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "eng".to_string())
+}
+
+/// OCR every page of a PDF: rasterize with `pdftoppm`, then read each image with `tesseract`
+pub async fn ocr_pdf(path: &Path) -> Result<Vec<String>> {
+    let dir = std::env::temp_dir().join(format!("ghost-lib-ocr-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).context("Failed to create OCR scratch directory")?;
+    let result = ocr_pages(path, &dir).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+async fn ocr_pages(path: &Path, dir: &Path) -> Result<Vec<String>> {
+    run(
+        "pdftoppm",
+        Command::new("pdftoppm")
+            .args(["-r", "300", "-png"])
+            .arg(path)
+            .arg(dir.join("page")),
+    )
+    .await?;
+
+    // pdftoppm zero-pads page numbers, so name order is page order
+    let mut images: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "png"))
+        .collect();
+    images.sort();
+    if images.is_empty() {
+        bail!("pdftoppm produced no page images");
+    }
+
+    let lang = language();
+    let mut pages = Vec::with_capacity(images.len());
+    for image in &images {
+        let text = run(
+            "tesseract",
+            Command::new("tesseract")
+                .arg(image)
+                .arg("stdout")
+                .args(["-l", &lang]),
+        )
+        .await?;
+        pages.push(text);
+    }
+    Ok(pages)
+}
+
+/// Run an external tool and return its stdout, with a clear error when it is not installed
+async fn run(tool: &str, command: &mut Command) -> Result<String> {
+    let output = match command.output().await {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => bail!(
+            "GHOST_OCR is set but `{tool}` was not found on PATH \
+             (install poppler-utils and tesseract-ocr, or unset GHOST_OCR)"
+        ),
+        Err(e) => return Err(e).with_context(|| format!("Failed to run {tool}")),
+    };
+    if !output.status.success() {
+        bail!(
+            "{tool} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_looks_scanned() {
+        assert!(looks_scanned(&[]));
+        assert!(looks_scanned(&["".into(), "  \n 12 ".into()]));
+        let page = "A page of ordinary extracted text, long enough to count.".to_string();
+        assert!(!looks_scanned(&[page.clone(), page]));
+    }
+}
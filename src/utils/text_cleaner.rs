@@ -1,4 +1,12 @@
 use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::OnceLock;
+use tokenizers::Tokenizer;
+
+use super::cjk;
 
 /// Negation words to preserve during stopword removal
 const NEGATIONS: &[&str] = &[
@@ -155,6 +163,34 @@ const STOPWORDS: &[&str] = &[
     "once",
 ];
 
+/// Unit words that anchor numeric context the same way a number does
+const UNITS: &[&str] = &[
+    "percent",
+    "percentage",
+    "pct",
+    "bps",
+    "basis",
+    "points",
+    "dollars",
+    "usd",
+    "eur",
+    "yen",
+    "thousand",
+    "million",
+    "billion",
+    "trillion",
+    "bn",
+    "kg",
+    "km",
+    "cm",
+    "mm",
+    "hours",
+    "days",
+    "weeks",
+    "months",
+    "years",
+];
+
 /// Filler phrases to remove during compression
 const FILLER_PHRASES: &[&str] = &[
     "it is important to note that",
@@ -194,45 +230,225 @@ pub fn normalize(text: &str) -> String {
         .to_string()
 }
 
-/// Extract markdown sections as (heading, content) pairs
-pub fn extract_markdown_sections(text: &str) -> Vec<(String, String)> {
-    let re = Regex::new(r"(?m)^(#{1,6})\s+(.+)$").unwrap();
-    let mut sections = Vec::new();
-    let mut last_heading = String::new();
-    let mut last_start = 0;
-    let mut found_first = false;
-
-    for cap in re.captures_iter(text) {
-        let m = cap.get(0).unwrap();
-        if found_first {
-            let content = text[last_start..m.start()].trim().to_string();
-            sections.push((last_heading.clone(), content));
+/// How a plain-text format marks its section headings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadingStyle {
+    /// `#` to `######` prefixes (Markdown, and plain text)
+    Markdown,
+    /// `*`, `**`, ... prefixes (Org mode)
+    Org,
+    /// A title line underlined, optionally also overlined, with `===`, `---`, ... (reStructuredText)
+    Rst,
+}
+
+impl HeadingStyle {
+    /// Heading style for a document, chosen by its extension
+    pub fn for_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            Some("org") => HeadingStyle::Org,
+            Some("rst") => HeadingStyle::Rst,
+            _ => HeadingStyle::Markdown,
         }
-        last_heading = cap[2].to_string();
-        last_start = m.end();
-        found_first = true;
     }
+}
+
+/// Characters reStructuredText allows in section adornments
+const RST_ADORNMENTS: &str = "=-`:'\"~^_*+#<>.";
+
+/// The adornment character when `line` is a run of one such character
+fn rst_adornment(line: &str) -> Option<char> {
+    let c = line.chars().next()?;
+    (RST_ADORNMENTS.contains(c) && line.chars().count() >= 3 && line.chars().all(|x| x == c))
+        .then_some(c)
+}
 
-    if found_first {
-        let content = text[last_start..].trim().to_string();
-        sections.push((last_heading, content));
-    } else if !text.trim().is_empty() {
-        sections.push(("(no heading)".to_string(), text.trim().to_string()));
+/// Each heading as (byte range of its heading line(s), heading text), in document order
+fn heading_spans(text: &str, style: HeadingStyle) -> Vec<(Range<usize>, String)> {
+    match style {
+        HeadingStyle::Markdown => {
+            let re = Regex::new(r"(?m)^(#{1,6})\s+(.+)$").unwrap();
+            re.captures_iter(text)
+                .map(|cap| (cap.get(0).unwrap().range(), cap[2].trim().to_string()))
+                .collect()
+        }
+        HeadingStyle::Org => {
+            // TODO/DONE keywords, priority cookies and trailing :tags: aren't part of the title
+            let re = Regex::new(
+                r"(?m)^\*+\s+(?:(?:TODO|DONE)\s+)?(?:\[#[A-Z]\]\s+)?(.+?)(?:\s+:[\w@#%:]+:)?[ \t]*$",
+            )
+            .unwrap();
+            re.captures_iter(text)
+                .map(|cap| (cap.get(0).unwrap().range(), cap[1].trim().to_string()))
+                .collect()
+        }
+        HeadingStyle::Rst => {
+            let mut lines = Vec::new();
+            let mut offset = 0;
+            for line in text.split('\n') {
+                lines.push((offset, line.trim_end()));
+                offset += line.len() + 1;
+            }
+            let end_of = |i: usize| lines[i].0 + lines[i].1.len();
+            let is_title = |line: &str| !line.trim().is_empty() && rst_adornment(line).is_none();
+
+            let mut spans = Vec::new();
+            let mut i = 0;
+            while i + 1 < lines.len() {
+                let (start, line) = lines[i];
+                // Overline + title + matching underline
+                if let Some(c) = rst_adornment(line) {
+                    if i + 2 < lines.len()
+                        && is_title(lines[i + 1].1)
+                        && rst_adornment(lines[i + 2].1) == Some(c)
+                    {
+                        spans.push((start..end_of(i + 2), lines[i + 1].1.trim().to_string()));
+                        i += 3;
+                        continue;
+                    }
+                }
+                // Title + underline starting a paragraph (docutils only warns
+                // when the underline is shorter than the title, so allow that)
+                let starts_paragraph = i == 0 || lines[i - 1].1.trim().is_empty();
+                if starts_paragraph && is_title(line) && rst_adornment(lines[i + 1].1).is_some() {
+                    spans.push((start..end_of(i + 1), line.trim().to_string()));
+                    i += 2;
+                    continue;
+                }
+                i += 1;
+            }
+            spans
+        }
     }
+}
 
-    sections
+/// Byte offset of each heading, with the heading text, in document order
+pub fn heading_offsets(text: &str, style: HeadingStyle) -> Vec<(usize, String)> {
+    heading_spans(text, style)
+        .into_iter()
+        .map(|(span, heading)| (span.start, heading))
+        .collect()
 }
 
-/// Remove stopwords while preserving negations
+/// Extract sections as (heading, content) pairs, recognising headings in `style`
+pub fn extract_sections(text: &str, style: HeadingStyle) -> Vec<(String, String)> {
+    let spans = heading_spans(text, style);
+    if spans.is_empty() {
+        if text.trim().is_empty() {
+            return Vec::new();
+        }
+        return vec![("(no heading)".to_string(), text.trim().to_string())];
+    }
+    spans
+        .iter()
+        .enumerate()
+        .map(|(i, (span, heading))| {
+            let end = spans.get(i + 1).map_or(text.len(), |(next, _)| next.start);
+            (heading.clone(), text[span.end..end].trim().to_string())
+        })
+        .collect()
+}
+
+/// Built-in stopwords plus any from the GHOST_STOPWORDS file (one per line,
+/// `#` comments allowed). GHOST_STOPWORDS_MODE=replace drops the built-in list.
+fn stopwords() -> &'static HashSet<String> {
+    static SET: OnceLock<HashSet<String>> = OnceLock::new();
+    SET.get_or_init(|| {
+        let Some(path) = std::env::var("GHOST_STOPWORDS")
+            .ok()
+            .filter(|p| !p.is_empty())
+        else {
+            return build_stopwords(None, false);
+        };
+        let replace = std::env::var("GHOST_STOPWORDS_MODE").as_deref() == Ok("replace");
+        match std::fs::read_to_string(&path) {
+            Ok(custom) => build_stopwords(Some(&custom), replace),
+            Err(e) => {
+                eprintln!(
+                    "Warning: cannot read stopwords file {path}: {e}. Using the built-in list."
+                );
+                build_stopwords(None, false)
+            }
+        }
+    })
+}
+
+fn build_stopwords(custom: Option<&str>, replace: bool) -> HashSet<String> {
+    let mut set: HashSet<String> = if replace {
+        HashSet::new()
+    } else {
+        STOPWORDS.iter().map(|w| w.to_string()).collect()
+    };
+    let custom_words = custom
+        .into_iter()
+        .flat_map(str::lines)
+        .map(|line| line.trim().to_lowercase())
+        .filter(|w| !w.is_empty() && !w.starts_with('#'));
+    set.extend(custom_words);
+    set
+}
+
+/// Whether a token carries a quantity: it has a digit (`50`, `3.5%`, `$1,200`)
+/// or is a unit word like `percent`
+fn is_numeric_token(clean: &str, word: &str) -> bool {
+    word.chars().any(|c| c.is_ascii_digit()) || word.contains('%') || UNITS.contains(&clean)
+}
+
+/// Remove stopwords while preserving negations.
+/// Runs of stopwords next to a number or unit are kept, so "up to 50 percent"
+/// does not collapse to "50 percent".
+/// Words in CJK scripts have their Japanese particles dropped instead.
 pub fn remove_stopwords(text: &str) -> String {
-    text.split_whitespace()
-        .filter(|word| {
+    let cjk = cjk::enabled(text);
+    let stopwords = stopwords();
+    let words: Vec<&str> = text.split_whitespace().collect();
+    // None for CJK words (handled separately), else (is stopword, is numeric)
+    let kinds: Vec<Option<(bool, bool)>> = words
+        .iter()
+        .map(|word| {
+            if cjk && cjk::contains_cjk(word) {
+                return None;
+            }
             let lower = word.to_lowercase();
             let clean = lower.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'');
-            if NEGATIONS.contains(&clean) {
-                return true;
+            let stop = !NEGATIONS.contains(&clean) && stopwords.contains(clean);
+            Some((stop, is_numeric_token(clean, word)))
+        })
+        .collect();
+    let is_stop = |i: usize| matches!(kinds.get(i), Some(Some((true, _))));
+    let is_numeric = |i: usize| matches!(kinds.get(i), Some(Some((_, true))));
+
+    let mut keep = vec![true; words.len()];
+    let mut i = 0;
+    while i < words.len() {
+        if !is_stop(i) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while is_stop(i) {
+            i += 1;
+        }
+        let anchored = (start > 0 && is_numeric(start - 1)) || is_numeric(i);
+        if !anchored {
+            keep[start..i].fill(false);
+        }
+    }
+
+    words
+        .iter()
+        .zip(&kinds)
+        .zip(keep)
+        .filter_map(|((word, kind), keep)| {
+            if kind.is_none() {
+                let kept = cjk::remove_stopwords(word);
+                return (!kept.is_empty()).then_some(Cow::Owned(kept));
             }
-            !STOPWORDS.contains(&clean)
+            keep.then_some(Cow::Borrowed(*word))
         })
         .collect::<Vec<_>>()
         .join(" ")
@@ -256,8 +472,59 @@ pub fn compress_text(text: &str) -> String {
     remove_stopwords(&without_fillers)
 }
 
-/// Estimate token count using words * 1.3 heuristic
+/// Split text into sentences at `.`, `!`, `?` followed by whitespace, CJK full stops,
+/// and line breaks. Slices keep their terminator; empty pieces are dropped.
+pub fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, n)| n);
+        let boundary = match c {
+            '\n' | '。' | '！' | '？' => true,
+            '.' | '!' | '?' => next.is_none_or(char::is_whitespace),
+            _ => false,
+        };
+        if boundary {
+            let end = i + c.len_utf8();
+            sentences.push(text[start..end].trim());
+            start = end;
+        }
+    }
+    sentences.push(text[start..].trim());
+    sentences.retain(|s| !s.is_empty());
+    sentences
+}
+
+/// Hugging Face `tokenizer.json` named by GHOST_TOKENIZER, loaded once
+fn tokenizer() -> Option<&'static Tokenizer> {
+    static TOKENIZER: OnceLock<Option<Tokenizer>> = OnceLock::new();
+    TOKENIZER
+        .get_or_init(|| {
+            let path = std::env::var("GHOST_TOKENIZER")
+                .ok()
+                .filter(|p| !p.is_empty())?;
+            match Tokenizer::from_file(&path) {
+                Ok(tokenizer) => Some(tokenizer),
+                Err(e) => {
+                    eprintln!("Warning: cannot load tokenizer {path}: {e}. Estimating tokens from word count.");
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+/// Count tokens with the GHOST_TOKENIZER tokenizer when one is configured,
+/// otherwise estimate using the words * 1.3 heuristic
 pub fn estimate_tokens(text: &str) -> usize {
+    count_tokens(tokenizer(), text)
+}
+
+fn count_tokens(tokenizer: Option<&Tokenizer>, text: &str) -> usize {
+    if let Some(encoding) = tokenizer.and_then(|t| t.encode(text, false).ok()) {
+        return encoding.len();
+    }
     let word_count = text.split_whitespace().count();
     (word_count as f64 * 1.3).ceil() as usize
 }
@@ -299,13 +566,47 @@ mod tests {
     #[test]
     fn test_extract_markdown_sections() {
         let md = "# Title\nSome intro\n## Section A\nContent A\n## Section B\nContent B";
-        let sections = extract_markdown_sections(md);
+        let sections = extract_sections(md, HeadingStyle::Markdown);
         assert_eq!(sections.len(), 3);
         assert_eq!(sections[0].0, "Title");
         assert_eq!(sections[1].0, "Section A");
         assert_eq!(sections[2].0, "Section B");
     }
 
+    #[test]
+    fn test_org_sections() {
+        let org = "#+TITLE: Notes\n* Inbox\nBuy milk\n** TODO [#A] Call the bank :errands:\nAbout the loan\n*bold* is not a heading";
+        let sections = extract_sections(org, HeadingStyle::Org);
+        assert_eq!(
+            sections,
+            vec![
+                ("Inbox".to_string(), "Buy milk".to_string()),
+                (
+                    "Call the bank".to_string(),
+                    "About the loan\n*bold* is not a heading".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rst_sections() {
+        let rst = "=====\nGuide\n=====\n\nIntro text.\n\nInstall\n-------\nRun the installer.\n\n----\n\nUsage\n=====\nCall it.";
+        let sections = extract_sections(rst, HeadingStyle::Rst);
+        let headings: Vec<&str> = sections.iter().map(|(h, _)| h.as_str()).collect();
+        assert_eq!(headings, vec!["Guide", "Install", "Usage"]);
+        assert_eq!(sections[0].1, "Intro text.");
+        // A transition (a lone line of dashes) is not a heading
+        assert_eq!(sections[1].1, "Run the installer.\n\n----");
+
+        let offsets = heading_offsets(rst, HeadingStyle::Rst);
+        assert_eq!(&rst[offsets[1].0..offsets[1].0 + 7], "Install");
+        assert_eq!(
+            HeadingStyle::for_path(Path::new("docs/index.RST")),
+            HeadingStyle::Rst
+        );
+    }
+
     #[test]
     fn test_stopword_removal_preserves_negation() {
         let text = "This is not a good idea";
@@ -316,6 +617,29 @@ mod tests {
         assert!(!result.contains("This"));
     }
 
+    #[test]
+    fn test_stopword_removal_keeps_numeric_context() {
+        let result = remove_stopwords("Revenue increased by up to 50 percent over 3 years");
+        assert_eq!(result, "Revenue increased by up to 50 percent over 3 years");
+
+        // Stopwords away from numbers still go
+        let result = remove_stopwords("The margin was up to the usual 12% level");
+        assert_eq!(result, "margin usual 12% level");
+    }
+
+    #[test]
+    fn test_custom_stopwords_merge_or_replace() {
+        let custom = "# domain jargon\nSynergy\n\nleverage\nnot\n";
+        let merged = build_stopwords(Some(custom), false);
+        assert!(merged.contains("synergy") && merged.contains("leverage"));
+        assert!(merged.contains("the"));
+        assert!(!merged.contains("# domain jargon"));
+
+        let replaced = build_stopwords(Some(custom), true);
+        assert_eq!(replaced.len(), 3);
+        assert!(!replaced.contains("the"));
+    }
+
     #[test]
     fn test_filler_removal() {
         let text = "It is important to note that the system works well";
@@ -331,6 +655,25 @@ mod tests {
         assert_eq!(tokens, 11); // 8 * 1.3 = 10.4 -> 11
     }
 
+    #[test]
+    fn test_count_tokens_with_tokenizer() {
+        let json = r#"{
+            "version": "1.0",
+            "truncation": null,
+            "padding": null,
+            "added_tokens": [],
+            "normalizer": null,
+            "pre_tokenizer": { "type": "Whitespace" },
+            "post_processor": null,
+            "decoder": null,
+            "model": { "type": "WordLevel", "vocab": { "[UNK]": 0, "hello": 1 }, "unk_token": "[UNK]" }
+        }"#;
+        let tokenizer: Tokenizer = json.parse().unwrap();
+        // Punctuation counts as separate tokens, unlike the word-count heuristic
+        assert_eq!(count_tokens(Some(&tokenizer), "hello, world!"), 4);
+        assert_eq!(count_tokens(None, "hello, world!"), 3);
+    }
+
     #[test]
     fn test_compression_ratio() {
         let original = "This is a very important and absolutely critical document";
@@ -349,4 +692,16 @@ mod tests {
         let c = vec![0.0, 1.0, 0.0];
         assert!(cosine_similarity(&a, &c).abs() < 1e-6);
     }
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(
+            split_sentences("Version 1.2 ships today. Really?  Yes!\nNext line"),
+            ["Version 1.2 ships today.", "Really?", "Yes!", "Next line"]
+        );
+        assert_eq!(
+            split_sentences("東京です。大阪です。"),
+            ["東京です。", "大阪です。"]
+        );
+    }
 }